@@ -0,0 +1,40 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use lua2json::parse::parse;
+use lua2json::stream::{transcode, TranscodeOptions};
+
+/// An array of `n` records like a game's item dump, with a few escaped strings.
+fn records(n: usize) -> String {
+    let mut s = String::from("{");
+    for i in 0..n {
+        s.push_str(&format!(
+            "{{id = {i}, name = \"item {i}\", weight = {}.5, tags = {{\"a\", \"b\\tc\"}}, \
+             stats = {{hp = 10, [\"max hp\"] = 20, ok = true}}}},\n",
+            i % 100
+        ));
+    }
+    s.push('}');
+    s
+}
+
+fn bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("records");
+    for n in [100, 10_000] {
+        let input = records(n);
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::new("parse", n), &input, |b, input| {
+            b.iter(|| parse(input).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("transcode", n), &input, |b, input| {
+            b.iter(|| {
+                let mut out = Vec::with_capacity(input.len());
+                transcode(input.as_bytes(), &mut out, &TranscodeOptions::default()).unwrap();
+                out
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -0,0 +1,66 @@
+#ifndef LUA2JSON_H
+#define LUA2JSON_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The result of a call.
+ */
+typedef enum lua2json_status {
+  LUA2JSON_STATUS_OK = 0,
+  /**
+   * A pointer which must not be null was.
+   */
+  LUA2JSON_STATUS_INVALID_ARGUMENT = 1,
+  /**
+   * The input couldn't be decoded or parsed.
+   */
+  LUA2JSON_STATUS_PARSE_ERROR = 2,
+  /**
+   * A bug in the library, which has been caught.
+   */
+  LUA2JSON_STATUS_INTERNAL_ERROR = 3,
+} lua2json_status;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Convert the `len` bytes of a Lua table at `input` to JSON, indented if `pretty`, and store a
+ * pointer to the NUL-terminated JSON in `out`, which must be freed with [`lua2json_free`].
+ *
+ * The input is UTF-16 if it starts with a byte order mark for it, otherwise UTF-8 if it's
+ * valid, otherwise Latin-1. On failure, `out` is set to null and
+ * [`lua2json_last_error_message`] describes what went wrong.
+ *
+ * # Safety
+ *
+ * `input` must point to `len` readable bytes, and `out` must be valid to write a pointer to.
+ */
+enum lua2json_status lua2json_parse(const uint8_t *input, size_t len, bool pretty, char **out);
+
+/**
+ * Free JSON returned by [`lua2json_parse`]. Does nothing if `json` is null.
+ *
+ * # Safety
+ *
+ * `json` must be null or have been returned by [`lua2json_parse`], and not freed already.
+ */
+void lua2json_free(char *json);
+
+/**
+ * A description of why the last call on this thread failed, or null if it succeeded. The
+ * message is owned by the library, and valid until the next call on this thread.
+ */
+const char *lua2json_last_error_message(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* LUA2JSON_H */
@@ -0,0 +1,124 @@
+//! Random values and documents, for property testing code which takes a [`Value`], with
+//! [`arbitrary`](::arbitrary) directly or through a fuzzer or `proptest-arbitrary-interop`.
+//!
+//! ```
+//! use arbitrary::{Arbitrary, Unstructured};
+//! use lua2json::arbitrary::Document;
+//!
+//! let mut u = Unstructured::new(b"some random bytes, from a fuzzer or an RNG");
+//! let doc = Document::arbitrary(&mut u).unwrap();
+//! assert_eq!(doc.table, lua2json::parse::parse(&doc.lua).unwrap());
+//! ```
+
+use std::str;
+
+use ::arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::format::{to_lua, FormatOptions, Indent, KeyOrder, Quote};
+use crate::parse::{Key, Table, Value};
+
+/// How deeply tables are nested, counting the outermost.
+const MAX_DEPTH: usize = 4;
+/// The most entries in one table.
+const MAX_ENTRIES: usize = 8;
+
+/// Values which come back the same from being written out with [`to_lua`] and parsed: there
+/// are no NaNs, as they aren't equal to themselves, [`Value::Bytes`] are never UTF-8, and
+/// there are no [`Value::Number`]s or [`Value::Raw`]s, whose text could disagree with them.
+impl<'a> Arbitrary<'a> for Value {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Value> {
+        value(u, 0)
+    }
+}
+
+fn value(u: &mut Unstructured, depth: usize) -> Result<Value> {
+    let kinds = if depth < MAX_DEPTH { 5 } else { 4 };
+    Ok(match u.choose_index(kinds)? {
+        0 => Value::Bool(u.arbitrary()?),
+        1 => Value::Float(match f64::arbitrary(u)? {
+            f if f.is_nan() => 0.,
+            f => f,
+        }),
+        2 => Value::String(u.arbitrary()?),
+        3 => {
+            let mut bytes = Vec::<u8>::arbitrary(u)?;
+            if str::from_utf8(&bytes).is_ok() {
+                bytes.push(0xff);
+            }
+            Value::Bytes(bytes)
+        }
+        _ => Value::Object(table(u, depth + 1)?),
+    })
+}
+
+fn key(u: &mut Unstructured) -> Result<Option<Key>> {
+    Ok(match u.int_in_range(0..=7)? {
+        0 => Some(Key::Integer(u.int_in_range(1..=100u32)?.to_string())),
+        1..=3 => None,
+        _ => Some(Key::Name(u.arbitrary()?)),
+    })
+}
+
+fn table(u: &mut Unstructured, depth: usize) -> Result<Table> {
+    let len = u.int_in_range(0..=MAX_ENTRIES)?;
+    (0..len).map(|_| Ok((key(u)?, value(u, depth)?))).collect()
+}
+
+/// A table, and it written out in Lua with arbitrary [`FormatOptions`] for layout, which
+/// parses back to the table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    pub table: Table,
+    pub lua: String,
+}
+
+impl<'a> Arbitrary<'a> for Document {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Document> {
+        let table = table(u, 1)?;
+        let opts = FormatOptions {
+            indent: *u.choose(&[
+                Indent::None,
+                Indent::Spaces(2),
+                Indent::Spaces(4),
+                Indent::Tab,
+            ])?,
+            quote: *u.choose(&[Quote::Double, Quote::Single])?,
+            quote_keys: u.arbitrary()?,
+            trailing_commas: u.arbitrary()?,
+            // as reordering entries would change the table
+            key_order: KeyOrder::Preserve,
+        };
+        let lua = to_lua(&Value::Object(table.clone()), &opts);
+        Ok(Document { table, lua })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::arbitrary::{Arbitrary, Unstructured};
+
+    use crate::arbitrary::Document;
+    use crate::parse::{parse, Value};
+
+    #[test]
+    fn round_trip() {
+        // xorshift, for the same bytes every time
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut bytes = vec![0; 4096];
+        let mut tables = 0;
+        for _ in 0..500 {
+            for b in &mut bytes {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                *b = state as u8;
+            }
+            let mut u = Unstructured::new(&bytes);
+            let doc = Document::arbitrary(&mut u).unwrap();
+            assert_eq!(doc.table, parse(&doc.lua).expect(&doc.lua), "{}", doc.lua);
+            let v = Value::arbitrary(&mut u).unwrap();
+            tables += usize::from(v.as_table().is_some());
+        }
+        assert!(tables > 0);
+    }
+}
@@ -0,0 +1,224 @@
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+use crate::parse::{Table, Value};
+
+/// A [`Value`] which is [`Eq`], [`Ord`] and [`Hash`], for putting records in a `HashSet` or
+/// `BTreeSet`. Two are equal when the values are [`Value::structurally_eq`], so keys are
+/// compared as strings, and `[1]` is the same as `["1"]`.
+///
+/// To give numbers a total order, NaN is equal to NaN and greater than every other number,
+/// and `-0` is equal to `0`. Otherwise values are ordered by type: booleans, numbers, strings,
+/// raw text and then tables, which are compared entry by entry, with named entries in order
+/// of key before positional ones.
+#[derive(Debug, Clone)]
+pub struct CanonicalValue(Value);
+
+impl CanonicalValue {
+    /// Normalise `value`: named entries are sorted by key, with only the last of each key, as
+    /// when converting to JSON, before any positional entries, and numbers are
+    /// [`Value::Float`]s.
+    pub fn new(value: Value) -> CanonicalValue {
+        CanonicalValue(normalise(value))
+    }
+
+    /// The normalised value.
+    pub fn as_value(&self) -> &Value {
+        &self.0
+    }
+
+    pub fn into_value(self) -> Value {
+        self.0
+    }
+}
+
+impl From<Value> for CanonicalValue {
+    fn from(value: Value) -> CanonicalValue {
+        CanonicalValue::new(value)
+    }
+}
+
+fn normalise(value: Value) -> Value {
+    match value {
+        Value::Object(table) => {
+            let mut named = Table::new();
+            let mut positional = Table::new();
+            for (k, v) in table {
+                match k {
+                    Some(k) => named.push((Some(k), normalise(v))),
+                    None => positional.push((None, normalise(v))),
+                }
+            }
+            // the sort is stable, which leaves the duplicates of each key in order, so the last
+            // is kept
+            named.sort_by(|(a, _), (b, _)| a.as_deref().cmp(&b.as_deref()));
+            named.dedup_by(|(later, v), (earlier, kept)| {
+                let same = later.as_deref() == earlier.as_deref();
+                if same {
+                    std::mem::swap(v, kept);
+                }
+                same
+            });
+            named.extend(positional);
+            Value::Object(named)
+        }
+        // -0 == 0, and NaNs can differ in their bits, but not to total_cmp; adding 0 turns -0
+        // into 0
+        Value::Float(f) | Value::Number { value: f, .. } if f.is_nan() => Value::Float(f64::NAN),
+        Value::Float(f) | Value::Number { value: f, .. } => Value::Float(f + 0.),
+        other => other,
+    }
+}
+
+/// The position of each type in the order.
+fn rank(value: &Value) -> u8 {
+    match value {
+        Value::Bool(_) => 0,
+        Value::Float(_) | Value::Number { .. } => 1,
+        Value::String(_) | Value::Bytes(_) => 2,
+        Value::Raw(_) => 3,
+        Value::Object(_) => 4,
+    }
+}
+
+fn compare(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (Value::Float(a), Value::Float(b)) => a.total_cmp(b),
+        (Value::Raw(a), Value::Raw(b)) => a.cmp(b),
+        (Value::Object(a), Value::Object(b)) => {
+            for ((ka, va), (kb, vb)) in a.iter().zip(b) {
+                // positional entries after named ones, as they are in normalised tables
+                let key = (ka.is_none(), ka.as_deref()).cmp(&(kb.is_none(), kb.as_deref()));
+                let order = key.then_with(|| compare(va, vb));
+                if order.is_ne() {
+                    return order;
+                }
+            }
+            a.len().cmp(&b.len())
+        }
+        _ => match (a.as_bytes(), b.as_bytes()) {
+            (Some(a), Some(b)) => a.cmp(b),
+            _ => rank(a).cmp(&rank(b)),
+        },
+    }
+}
+
+impl PartialEq for CanonicalValue {
+    fn eq(&self, other: &CanonicalValue) -> bool {
+        compare(&self.0, &other.0).is_eq()
+    }
+}
+
+impl Eq for CanonicalValue {}
+
+impl PartialOrd for CanonicalValue {
+    fn partial_cmp(&self, other: &CanonicalValue) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CanonicalValue {
+    fn cmp(&self, other: &CanonicalValue) -> Ordering {
+        compare(&self.0, &other.0)
+    }
+}
+
+impl Hash for CanonicalValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash(&self.0, state)
+    }
+}
+
+fn hash(value: &Value, state: &mut impl Hasher) {
+    rank(value).hash(state);
+    match value {
+        Value::Bool(b) => b.hash(state),
+        Value::Float(f) | Value::Number { value: f, .. } => f.to_bits().hash(state),
+        Value::String(s) => s.as_bytes().hash(state),
+        Value::Bytes(b) => b.hash(state),
+        Value::Raw(s) => s.hash(state),
+        Value::Object(table) => {
+            table.len().hash(state);
+            for (k, v) in table {
+                k.as_deref().hash(state);
+                hash(v, state);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeSet, HashSet};
+
+    use crate::canonical::CanonicalValue;
+    use crate::parse::{parse, Value};
+
+    fn lua(s: &str) -> CanonicalValue {
+        CanonicalValue::new(Value::Object(parse(s).unwrap()))
+    }
+
+    #[test]
+    fn sets() {
+        let records = [
+            "{name = \"a\", hp = 1}",
+            "{hp = 1, name = \"a\"}",
+            "{hp = 1, name = \"b\", name = \"a\"}",
+            "{name = \"b\", hp = 0/0}",
+            "{hp = 0/0, name = \"b\"}",
+            "{hp = -0, name = \"c\"}",
+            "{hp = 0, name = \"c\"}",
+            "{1, 2}",
+        ];
+        let hashed = records.iter().map(|r| lua(r)).collect::<HashSet<_>>();
+        assert_eq!(4, hashed.len());
+        let ordered = records.iter().map(|r| lua(r)).collect::<BTreeSet<_>>();
+        assert_eq!(4, ordered.len());
+        assert_eq!(
+            Value::Object(parse("{hp = 0, name = \"c\"}").unwrap()),
+            *ordered.first().unwrap().as_value()
+        );
+    }
+
+    #[test]
+    fn order() {
+        let mut values = [
+            lua("{}"),
+            CanonicalValue::new(Value::Raw("f()".to_string())),
+            CanonicalValue::new(Value::from("b")),
+            CanonicalValue::new(Value::Bytes(b"a\xff".to_vec())),
+            CanonicalValue::new(Value::Float(f64::NAN)),
+            CanonicalValue::new(Value::Number {
+                value: 2.,
+                raw: "2".to_string(),
+            }),
+            CanonicalValue::new(Value::Float(f64::NEG_INFINITY)),
+            CanonicalValue::new(Value::Bool(true)),
+            CanonicalValue::new(Value::Bool(false)),
+        ];
+        let expected = values.iter().rev().cloned().collect::<Vec<_>>();
+        values.sort();
+        assert_eq!(expected, values);
+
+        assert!(lua("{a = 1}") < lua("{a = 1, b = 1}"));
+        assert!(lua("{a = 1, 5}") > lua("{a = 1, b = 1}"));
+        assert!(lua("{b = 1, a = 2}") > lua("{a = 1, b = 2}"));
+        assert_eq!(lua("{1, a = 2}"), lua("{a = 2, 1}"));
+        assert_eq!(lua("{y = {}, x = 2}"), lua("{x = 1, y = {}, x = 2}"));
+        assert_ne!(lua("{x = 1}"), lua("{x = 1, x = 2}"));
+    }
+
+    #[test]
+    fn integer_keys() {
+        let (a, b) = ("{[1] = \"x\", y = 2}", "{y = 2, [\"1\"] = \"x\"}");
+        let (va, vb) = (parse(a).unwrap(), parse(b).unwrap());
+        assert!(Value::Object(va).structurally_eq(&Value::Object(vb)));
+        assert_eq!(lua(a), lua(b));
+        assert_eq!(
+            1,
+            [lua(a), lua(b)].into_iter().collect::<HashSet<_>>().len()
+        );
+        assert_eq!(lua("{[1] = 1, [\"1\"] = 2}"), lua("{[\"1\"] = 2}"));
+    }
+}
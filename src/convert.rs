@@ -0,0 +1,183 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::error::AccessError;
+#[cfg(feature = "std")]
+use crate::parse::Table;
+use crate::parse::Value;
+
+impl From<f64> for Value {
+    fn from(f: f64) -> Value {
+        Value::Float(f)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Value {
+        Value::Float(n as f64)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::String(s)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+/// A table of positional entries.
+impl From<Vec<Value>> for Value {
+    fn from(values: Vec<Value>) -> Value {
+        Value::Object(values.into_iter().map(|v| (None, v)).collect())
+    }
+}
+
+/// A table of named entries, sorted by key as maps have no order of their own.
+#[cfg(feature = "std")]
+impl From<HashMap<String, Value>> for Value {
+    fn from(map: HashMap<String, Value>) -> Value {
+        let mut table = map
+            .into_iter()
+            .map(|(k, v)| (Some(k.into()), v))
+            .collect::<Table>();
+        table.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Value::Object(table)
+    }
+}
+
+fn mismatch(value: &Value, expected: &'static str) -> AccessError {
+    AccessError::TypeMismatch {
+        expected,
+        found: value.type_name(),
+    }
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = AccessError;
+    fn try_from(value: Value) -> Result<f64, AccessError> {
+        value.f64()
+    }
+}
+
+/// Numbers with no fractional part which fit.
+impl TryFrom<Value> for i64 {
+    type Error = AccessError;
+    fn try_from(value: Value) -> Result<i64, AccessError> {
+        match value.as_f64() {
+            Some(f) if f == f as i64 as f64 && f.abs() < (1u64 << 63) as f64 => Ok(f as i64),
+            _ => Err(mismatch(&value, "integer")),
+        }
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = AccessError;
+    fn try_from(value: Value) -> Result<String, AccessError> {
+        match value {
+            Value::String(s) => Ok(s),
+            other => Err(mismatch(&other, "string")),
+        }
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = AccessError;
+    fn try_from(value: Value) -> Result<bool, AccessError> {
+        value.as_bool().ok_or_else(|| mismatch(&value, "boolean"))
+    }
+}
+
+/// The values of a table with only positional entries.
+impl TryFrom<Value> for Vec<Value> {
+    type Error = AccessError;
+    fn try_from(value: Value) -> Result<Vec<Value>, AccessError> {
+        if !value.is_array_like() {
+            return Err(mismatch(&value, "array"));
+        }
+        Ok(value.into_entries().map(|(_, v)| v).collect())
+    }
+}
+
+/// The entries of a table with only named entries, where the last of any entries with the same
+/// name wins, as it would in Lua.
+#[cfg(feature = "std")]
+impl TryFrom<Value> for HashMap<String, Value> {
+    type Error = AccessError;
+    fn try_from(value: Value) -> Result<HashMap<String, Value>, AccessError> {
+        match &value {
+            Value::Object(t) if t.iter().all(|(k, _)| k.is_some()) => Ok(value
+                .into_entries()
+                .map(|(k, v)| (k.expect("only named entries").into_string(), v))
+                .collect()),
+            _ => Err(mismatch(&value, "table with named entries")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::error::AccessError;
+    use crate::parse::{parse, Value};
+
+    #[test]
+    fn conversions() {
+        let map = HashMap::from([
+            ("b".to_string(), Value::from(vec![1.5.into(), true.into()])),
+            ("a".to_string(), Value::from("x")),
+        ]);
+        assert_eq!(
+            Value::Object(parse(r#"{a = "x", b = {1.5, true}}"#).unwrap()),
+            Value::from(map.clone())
+        );
+        assert_eq!(Ok(map.clone()), HashMap::try_from(Value::from(map)));
+        assert_eq!(
+            Ok(HashMap::from([("a".to_string(), Value::from(2.))])),
+            HashMap::try_from(Value::Object(parse("{a = 1, a = 2}").unwrap()))
+        );
+
+        assert_eq!(Ok(3), i64::try_from(Value::from(3i64)));
+        assert_eq!(Ok(2.5), f64::try_from(Value::from(2.5)));
+        assert_eq!(Ok("s".to_string()), String::try_from(Value::from("s")));
+        assert_eq!(Ok(false), bool::try_from(Value::from(false)));
+        assert_eq!(
+            Ok(vec![Value::from(1.)]),
+            Vec::try_from(Value::Object(parse("{1}").unwrap()))
+        );
+    }
+
+    #[test]
+    fn mismatches() {
+        assert_eq!(
+            Err(AccessError::TypeMismatch {
+                expected: "integer",
+                found: "number"
+            }),
+            i64::try_from(Value::from(2.5))
+        );
+        assert_eq!(
+            Err(AccessError::TypeMismatch {
+                expected: "array",
+                found: "table"
+            }),
+            Vec::try_from(Value::Object(parse("{1, a = 2}").unwrap()))
+        );
+        assert!(HashMap::try_from(Value::Object(parse("{1, a = 2}").unwrap())).is_err());
+        assert!(String::try_from(Value::Bytes(vec![0xff])).is_err());
+        assert!(bool::try_from(Value::from(1.)).is_err());
+    }
+}
@@ -0,0 +1,473 @@
+use std::fmt;
+use std::ops::Range;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::error::line_column;
+use crate::format::{self, to_lua, FormatOptions, Indent};
+use crate::parse::{self, Key, Table, Value};
+use crate::path::{self, Segment};
+
+/// A table which remembers exactly how it was written, including comments and layout, so it
+/// can be edited in place and written back out with everything else untouched.
+#[derive(Debug, Clone)]
+pub struct Document {
+    source: String,
+    root: Node,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    span: Range<usize>,
+    kind: Kind,
+}
+
+#[derive(Debug, Clone)]
+enum Kind {
+    Scalar(Value),
+    Table(Vec<Entry>),
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    key: Option<Key>,
+    /// Where the entry starts, which is the start of the key for named entries.
+    start: usize,
+    value: Node,
+    /// Where the comma after this entry is, if there is one.
+    comma: Option<usize>,
+}
+
+impl Document {
+    /// Parse a table like [`parse`](parse::parse) does, but also accepting Lua comments.
+    pub fn parse(source: &str) -> Result<Document> {
+        let mut parser = Parser {
+            src: source,
+            pos: 0,
+        };
+        parser.trivia();
+        let root = parser.node()?;
+        if !matches!(root.kind, Kind::Table(_)) {
+            bail!("unexpected non-object");
+        }
+        parser.trivia();
+        if parser.pos != source.len() {
+            return Err(parser.error("unexpected trailing data"));
+        }
+        Ok(Document {
+            source: source.to_string(),
+            root,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// The table, without any of the formatting.
+    pub fn value(&self) -> Value {
+        self.root.value()
+    }
+
+    /// Replace the value at `path`, in the syntax of [`Value::get_path`], or add an entry if
+    /// the last part of the path is a key which isn't present. Only the text of that value
+    /// changes.
+    pub fn set(&mut self, path: &str, value: &Value) -> Result<()> {
+        let segments = path::segments(path)?;
+        let (last, parents) = match segments.split_last() {
+            Some(((last, _), parents)) => (last, parents),
+            None => {
+                if !matches!(value, Value::Object(_)) {
+                    bail!("the root must be a table");
+                }
+                let text = self.render(value, self.root.span.start);
+                return self.splice(self.root.span.clone(), &text);
+            }
+        };
+        let (parent, entries) = self.table_at(parents, path)?;
+        if let Some(entry) = find(entries, last) {
+            let text = self.render(value, entry.start);
+            return self.splice(entry.value.span.clone(), &text);
+        }
+        let key = match last {
+            Segment::Key(key) => key,
+            Segment::Index(_) => bail!("no {last} at {path:?}"),
+        };
+
+        let mut text = String::new();
+        format::write_key(&mut text, key, format::Quote::Double);
+        text.push_str(" = ");
+        let (at, text) = match entries.last() {
+            None => {
+                text.push_str(&self.render(value, parent.span.start));
+                (parent.span.start + 1, text)
+            }
+            Some(last) => {
+                let separator = if self.source[parent.span.clone()].contains('\n') {
+                    format!("\n{}", self.indentation(last.start))
+                } else {
+                    " ".to_string()
+                };
+                text.push_str(&self.render(value, last.start));
+                match last.comma {
+                    Some(comma) => (comma + 1, format!("{separator}{text},")),
+                    None => (last.value.span.end, format!(",{separator}{text}")),
+                }
+            }
+        };
+        self.splice(at..at, &text)
+    }
+
+    /// The byte range of the value at `path`, in the syntax of [`Value::get_path`].
+    pub fn span(&self, path: &str) -> Result<Range<usize>> {
+        let segments = path::segments(path)?;
+        let Some(((last, _), parents)) = segments.split_last() else {
+            return Ok(self.root.span.clone());
+        };
+        let (_, entries) = self.table_at(parents, path)?;
+        let entry = find(entries, last).ok_or_else(|| anyhow!("no {last} at {path:?}"))?;
+        Ok(entry.value.span.clone())
+    }
+
+    /// Remove the entry at `path`, along with its comma, and its line if it was on its own.
+    pub fn remove(&mut self, path: &str) -> Result<()> {
+        let segments = path::segments(path)?;
+        let ((last, _), parents) = segments
+            .split_last()
+            .ok_or_else(|| anyhow!("can't remove the root"))?;
+        let (_, entries) = self.table_at(parents, path)?;
+        let i = position(entries, last).ok_or_else(|| anyhow!("no {last} at {path:?}"))?;
+        let entry = &entries[i];
+
+        if let (None, Some(comma)) = (entry.comma, i.checked_sub(1).and_then(|p| entries[p].comma))
+        {
+            // the last entry, without a trailing comma, takes the previous entry's comma with it
+            return self.splice(comma..entry.value.span.end, "");
+        }
+        let src = self.source.as_bytes();
+        let line_start = self.source[..entry.start].rfind('\n').map_or(0, |n| n + 1);
+        let whole_line = self.source[line_start..entry.start].trim().is_empty();
+        let start = if whole_line { line_start } else { entry.start };
+        let mut end = entry.comma.map_or(entry.value.span.end, |comma| comma + 1);
+        if whole_line {
+            let rest = &self.source[end..];
+            let line = &rest[..rest.find('\n').map_or(rest.len(), |n| n + 1)];
+            let trimmed = line.trim();
+            if trimmed.is_empty() || (trimmed.starts_with("--") && !trimmed.starts_with("--[")) {
+                end += line.len();
+            }
+        } else {
+            while src.get(end).is_some_and(|c| b' ' == *c || b'\t' == *c) {
+                end += 1;
+            }
+        }
+        self.splice(start..end, "")
+    }
+
+    fn table_at(&self, parents: &[(Segment, usize)], path: &str) -> Result<(&Node, &[Entry])> {
+        let mut node = &self.root;
+        for (segment, upto) in parents {
+            let entries = match &node.kind {
+                Kind::Table(entries) => entries,
+                Kind::Scalar(_) => bail!("{path:?} goes through a non-table"),
+            };
+            node = &find(entries, segment)
+                .ok_or_else(|| anyhow!("no {segment} at {:?}", &path[..*upto]))?
+                .value;
+        }
+        match &node.kind {
+            Kind::Table(entries) => Ok((node, entries)),
+            Kind::Scalar(_) => bail!("the parent of {path:?} is not a table"),
+        }
+    }
+
+    /// The leading whitespace of the line containing `at`.
+    fn indentation(&self, at: usize) -> &str {
+        let line_start = self.source[..at].rfind('\n').map_or(0, |n| n + 1);
+        let line = &self.source[line_start..];
+        &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+    }
+
+    /// Format `value` to fit in at `at`, matching the indentation of that line.
+    fn render(&self, value: &Value, at: usize) -> String {
+        let indentation = self.indentation(at);
+        let opts = FormatOptions {
+            indent: if indentation.starts_with('\t') {
+                Indent::Tab
+            } else {
+                Indent::Spaces(2)
+            },
+            ..FormatOptions::default()
+        };
+        to_lua(value, &opts).replace('\n', &format!("\n{indentation}"))
+    }
+
+    fn splice(&mut self, range: Range<usize>, text: &str) -> Result<()> {
+        let mut source = self.source.clone();
+        source.replace_range(range, text);
+        *self = Document::parse(&source).context("edited document no longer parses")?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl Node {
+    fn value(&self) -> Value {
+        match &self.kind {
+            Kind::Scalar(v) => v.clone(),
+            Kind::Table(entries) => Value::Object(
+                entries
+                    .iter()
+                    .map(|e| (e.key.clone(), e.value.value()))
+                    .collect::<Table>(),
+            ),
+        }
+    }
+}
+
+fn position(entries: &[Entry], segment: &Segment) -> Option<usize> {
+    match segment {
+        Segment::Key(key) => entries
+            .iter()
+            .position(|e| e.key.as_deref() == Some(key.as_str())),
+        Segment::Index(index) => entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.key.is_none())
+            .nth(*index)
+            .map(|(i, _)| i),
+    }
+}
+
+fn find<'e>(entries: &'e [Entry], segment: &Segment) -> Option<&'e Entry> {
+    position(entries, segment).map(|i| &entries[i])
+}
+
+struct Parser<'s> {
+    src: &'s str,
+    pos: usize,
+}
+
+impl<'s> Parser<'s> {
+    fn rest(&self) -> &'s str {
+        &self.src[self.pos..]
+    }
+
+    fn error(&self, what: &str) -> anyhow::Error {
+        let (line, column) = line_column(self.src, self.pos);
+        anyhow!("{what} at line {line}, column {column}")
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.rest().starts_with(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    /// Skip whitespace and comments.
+    fn trivia(&mut self) {
+        loop {
+            let rest = self.rest();
+            self.pos += rest.len() - rest.trim_start().len();
+            let rest = match self.rest().strip_prefix("--") {
+                Some(rest) => rest,
+                None => return,
+            };
+            let level = rest
+                .strip_prefix('[')
+                .map(|r| r.len() - r.trim_start_matches('=').len())
+                .filter(|level| rest[1 + level..].starts_with('['));
+            let len = match level {
+                Some(level) => {
+                    let close = format!("]{}]", "=".repeat(level));
+                    rest.find(&close).map_or(rest.len(), |n| n + close.len())
+                }
+                None => rest.find('\n').unwrap_or(rest.len()),
+            };
+            self.pos += 2 + len;
+        }
+    }
+
+    /// Run one of the `parse` module's parsers at the current position.
+    fn run<T>(&mut self, parser: fn(&'s str) -> nom::IResult<&'s str, T>) -> Option<T> {
+        let (rest, found) = parser(self.rest()).ok()?;
+        self.pos = self.src.len() - rest.len();
+        Some(found)
+    }
+
+    fn node(&mut self) -> Result<Node> {
+        let start = self.pos;
+        if self.eat('{') {
+            return self.table(start);
+        }
+        let value = self
+            .run(parse::atom)
+            .ok_or_else(|| self.error("expected a value"))?;
+        Ok(Node {
+            span: start..self.pos,
+            kind: Kind::Scalar(value),
+        })
+    }
+
+    fn table(&mut self, start: usize) -> Result<Node> {
+        let mut entries = Vec::new();
+        loop {
+            self.trivia();
+            if self.eat('}') {
+                break;
+            }
+            let mut entry = self.entry()?;
+            self.trivia();
+            if self.rest().starts_with(',') {
+                entry.comma = Some(self.pos);
+                self.pos += 1;
+                entries.push(entry);
+                continue;
+            }
+            entries.push(entry);
+            self.trivia();
+            if !self.eat('}') {
+                return Err(self.error("expected ',' or '}'"));
+            }
+            break;
+        }
+        Ok(Node {
+            span: start..self.pos,
+            kind: Kind::Table(entries),
+        })
+    }
+
+    fn entry(&mut self) -> Result<Entry> {
+        let start = self.pos;
+        if let Some(key) = self.run(parse::value_name) {
+            self.trivia();
+            if self.eat('=') {
+                self.trivia();
+                return Ok(Entry {
+                    key: Some(key),
+                    start,
+                    value: self.node()?,
+                    comma: None,
+                });
+            }
+            self.pos = start;
+        }
+        Ok(Entry {
+            key: None,
+            start,
+            value: self.node()?,
+            comma: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cst::Document;
+    use crate::parse::{parse, Value};
+
+    const CONFIG: &str = r#"-- window settings
+{
+  width = 1920, -- pixels
+  --[[ height is
+       derived ]]
+  ratio = 1.5,
+  ["title"] = "game",
+  tags = { "a", "b" },
+}
+"#;
+
+    #[test]
+    fn lossless() {
+        let doc = Document::parse(CONFIG).unwrap();
+        assert_eq!(CONFIG, doc.to_string());
+        assert_eq!(
+            Value::Object(
+                parse(r#"{width = 1920, ratio = 1.5, ["title"] = "game", tags = {"a", "b"}}"#)
+                    .unwrap()
+            ),
+            doc.value()
+        );
+        assert!(Document::parse("{a = 1 -- no close").is_err());
+        assert!(Document::parse("{} {}").is_err());
+    }
+
+    #[test]
+    fn set() {
+        let mut doc = Document::parse(CONFIG).unwrap();
+        doc.set("width", &Value::Float(1280.)).unwrap();
+        doc.set("tags[1]", &Value::String("c".to_string())).unwrap();
+        doc.set("depth", &Value::Object(parse("{x = 1}").unwrap()))
+            .unwrap();
+        assert_eq!(
+            r#"-- window settings
+{
+  width = 1280, -- pixels
+  --[[ height is
+       derived ]]
+  ratio = 1.5,
+  ["title"] = "game",
+  tags = { "a", "c" },
+  depth = {
+    x = 1,
+  },
+}
+"#,
+            doc.to_string()
+        );
+
+        let mut doc = Document::parse("{a = 1, b = {}}").unwrap();
+        doc.set("c", &Value::Bool(true)).unwrap();
+        doc.set("b.d", &Value::Float(2.)).unwrap();
+        assert_eq!("{a = 1, b = {d = 2}, c = true}", doc.to_string());
+        assert!(doc.set("a.b", &Value::Float(2.)).is_err());
+        assert!(doc.set("b[0]", &Value::Float(2.)).is_err());
+    }
+
+    #[test]
+    fn span() {
+        let doc = Document::parse(CONFIG).unwrap();
+        let text = |path| &CONFIG[doc.span(path).unwrap()];
+        assert_eq!("1920", text("width"));
+        assert_eq!(r#""b""#, text("tags[1]"));
+        assert!(text("").starts_with('{'));
+        assert!(doc.span("tags[2]").is_err());
+        assert!(doc.span("width.x").is_err());
+    }
+
+    #[test]
+    fn remove() {
+        let mut doc = Document::parse(CONFIG).unwrap();
+        doc.remove("width").unwrap();
+        doc.remove("tags").unwrap();
+        doc.remove("title").unwrap();
+        assert_eq!(
+            r#"-- window settings
+{
+  --[[ height is
+       derived ]]
+  ratio = 1.5,
+}
+"#,
+            doc.to_string()
+        );
+
+        let mut doc = Document::parse("{a = 1, b = 2, 3}").unwrap();
+        doc.remove("[0]").unwrap();
+        assert_eq!("{a = 1, b = 2}", doc.to_string());
+        doc.remove("a").unwrap();
+        assert_eq!("{b = 2}", doc.to_string());
+        assert!(doc.remove("a").is_err());
+
+        let mut doc = Document::parse("{\n  a = 1,\n  b = 2\n}").unwrap();
+        doc.remove("b").unwrap();
+        assert_eq!("{\n  a = 1\n}", doc.to_string());
+    }
+}
@@ -0,0 +1,166 @@
+use std::fmt::Write;
+
+use anyhow::{bail, Result};
+
+use crate::parse::{Table, Value};
+use crate::path::{push_index, push_key};
+
+/// Options for [`to_csv`].
+#[derive(Debug, Clone, Default)]
+pub struct CsvOptions {
+    /// Write nested tables as a column per field, named like `stats.hp` or `tags.0`, instead of
+    /// rejecting them.
+    pub flatten: bool,
+}
+
+/// Render an array of records, like `{{name = "a", hp = 10}, {name = "b"}}`, as CSV.
+///
+/// The header is every field of every record, in the order they're first seen, and fields a
+/// record doesn't have are left empty.
+pub fn to_csv(value: &Value, opts: &CsvOptions) -> Result<String> {
+    let records = match value {
+        Value::Object(table) if table.iter().all(|(k, _)| k.is_none()) => table,
+        _ => bail!("expected an array of records, found a table with named entries"),
+    };
+    let mut header: Vec<String> = Vec::new();
+    let mut rows = Vec::with_capacity(records.len());
+    for (i, (_, record)) in records.iter().enumerate() {
+        let mut path = String::new();
+        push_index(&mut path, i);
+        let fields = match record {
+            Value::Object(fields) if fields.iter().all(|(k, _)| k.is_some()) => fields,
+            other => bail!(
+                "{path}: expected a record of named fields, found a {}",
+                other.type_name()
+            ),
+        };
+        let mut row = Vec::new();
+        cells(fields, "", &mut path, opts, &mut row)?;
+        for (column, _) in &row {
+            if !header.contains(column) {
+                header.push(column.clone());
+            }
+        }
+        rows.push(row);
+    }
+
+    let mut out = String::new();
+    if header.is_empty() {
+        return Ok(out);
+    }
+    write_row(&mut out, header.iter().map(|h| h.as_str()));
+    for row in &rows {
+        write_row(
+            &mut out,
+            header.iter().map(|column| {
+                row.iter()
+                    .find(|(c, _)| c == column)
+                    .map_or("", |(_, cell)| cell.as_str())
+            }),
+        );
+    }
+    Ok(out)
+}
+
+/// The columns and text of each field in `fields`, with nested columns named after `prefix`.
+fn cells(
+    fields: &Table,
+    prefix: &str,
+    path: &mut String,
+    opts: &CsvOptions,
+    row: &mut Vec<(String, String)>,
+) -> Result<()> {
+    for (i, (k, v)) in fields.iter().enumerate() {
+        let len = path.len();
+        let name = match k {
+            Some(k) => {
+                push_key(path, k);
+                k.to_string()
+            }
+            None => {
+                push_index(path, i);
+                i.to_string()
+            }
+        };
+        let column = if prefix.is_empty() {
+            name
+        } else {
+            format!("{prefix}.{name}")
+        };
+        let text = match v {
+            Value::Object(nested) if opts.flatten => {
+                cells(nested, &column, path, opts, row)?;
+                path.truncate(len);
+                continue;
+            }
+            Value::Object(_) => bail!("{path}: nested tables can only be written when flattening"),
+            Value::String(s) => s.clone(),
+            Value::Bytes(b) => String::from_utf8_lossy(b).into_owned(),
+            Value::Raw(raw) => raw.clone(),
+            Value::Number { raw, .. } => raw.clone(),
+            Value::Float(f) => f.to_string(),
+            Value::Bool(b) => b.to_string(),
+        };
+        path.truncate(len);
+        row.push((column, text));
+    }
+    Ok(())
+}
+
+fn write_row<'c>(out: &mut String, cells: impl Iterator<Item = &'c str>) {
+    for (i, cell) in cells.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        if cell.contains([',', '"', '\n', '\r']) {
+            write!(out, "\"{}\"", cell.replace('"', "\"\"")).expect("infallible");
+        } else {
+            out.push_str(cell);
+        }
+    }
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::csv::{to_csv, CsvOptions};
+    use crate::parse::{parse, Value};
+
+    fn csv(s: &str, flatten: bool) -> anyhow::Result<String> {
+        to_csv(&Value::Object(parse(s).unwrap()), &CsvOptions { flatten })
+    }
+
+    #[test]
+    fn records() {
+        assert_eq!(
+            "name,hp,note\na,10,\nb,12.5,\"says \"\"hi\"\", twice\"\n",
+            csv(
+                r#"{{name = "a", hp = 10}, {hp = 12.5, name = "b", note = "says \"hi\", twice"}}"#,
+                false
+            )
+            .unwrap()
+        );
+        assert_eq!("", csv("{}", false).unwrap());
+    }
+
+    #[test]
+    fn flatten() {
+        let input =
+            r#"{{name = "a", stats = {hp = 1, tags = {"x", "y"}}}, {stats = {}, ok = true}}"#;
+        assert_eq!(
+            "name,stats.hp,stats.tags.0,stats.tags.1,ok\na,1,x,y,\n,,,,true\n",
+            csv(input, true).unwrap()
+        );
+        assert_eq!(
+            "[0].stats: nested tables can only be written when flattening",
+            csv(input, false).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert!(csv("{a = 1}", false).is_err());
+        assert!(csv(r#"{{a = 1}, "b"}"#, false).is_err());
+        assert!(csv("{{1, 2}}", false).is_err());
+    }
+}
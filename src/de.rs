@@ -0,0 +1,192 @@
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+
+use crate::parse::{string_value, Table, Value};
+
+/// How `serde_json` passes numbers to visitors with its `arbitrary_precision` feature, which
+/// this crate enables.
+const JSON_NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
+/// Read any self-describing format, like JSON or TOML, into a `Value`.
+///
+/// Sequences become tables of positional entries and maps tables of named entries, with keys
+/// which are numbers or booleans written as strings. Integers too large for every one to be a
+/// float keep their digits as a [`Value::Number`]. Lua has no `nil` in tables, so map entries
+/// which are null are left out, and null anywhere else is an error.
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+        match deserializer.deserialize_any(ValueVisitor)? {
+            Some(value) => Ok(value),
+            None => Err(de::Error::custom("null has no Lua value")),
+        }
+    }
+}
+
+/// A value from [`ValueVisitor`], where `None` is null.
+struct Nullable(Option<Value>);
+
+impl<'de> Deserialize<'de> for Nullable {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Nullable, D::Error> {
+        deserializer.deserialize_any(ValueVisitor).map(Nullable)
+    }
+}
+
+struct ValueVisitor;
+
+/// A whole number as a float if it can be one exactly, otherwise as its digits.
+pub(crate) fn integer(n: i128) -> Value {
+    if n.unsigned_abs() <= 1 << f64::MANTISSA_DIGITS {
+        Value::Float(n as f64)
+    } else {
+        Value::Number {
+            value: n as f64,
+            raw: n.to_string(),
+        }
+    }
+}
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Option<Value>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a value with a Lua equivalent")
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<Option<Value>, E> {
+        Ok(Some(Value::Bool(b)))
+    }
+    fn visit_i64<E>(self, n: i64) -> Result<Option<Value>, E> {
+        Ok(Some(integer(n.into())))
+    }
+    fn visit_u64<E>(self, n: u64) -> Result<Option<Value>, E> {
+        Ok(Some(integer(n.into())))
+    }
+    fn visit_i128<E>(self, n: i128) -> Result<Option<Value>, E> {
+        Ok(Some(integer(n)))
+    }
+    fn visit_f64<E>(self, f: f64) -> Result<Option<Value>, E> {
+        Ok(Some(Value::Float(f)))
+    }
+    fn visit_str<E>(self, s: &str) -> Result<Option<Value>, E> {
+        Ok(Some(Value::String(s.to_string())))
+    }
+    fn visit_string<E>(self, s: String) -> Result<Option<Value>, E> {
+        Ok(Some(Value::String(s)))
+    }
+    fn visit_bytes<E>(self, b: &[u8]) -> Result<Option<Value>, E> {
+        Ok(Some(string_value(b.to_vec())))
+    }
+    fn visit_byte_buf<E>(self, b: Vec<u8>) -> Result<Option<Value>, E> {
+        Ok(Some(string_value(b)))
+    }
+    fn visit_none<E>(self) -> Result<Option<Value>, E> {
+        Ok(None)
+    }
+    fn visit_unit<E>(self) -> Result<Option<Value>, E> {
+        Ok(None)
+    }
+    fn visit_some<D: Deserializer<'de>>(self, d: D) -> Result<Option<Value>, D::Error> {
+        Value::deserialize(d).map(Some)
+    }
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, d: D) -> Result<Option<Value>, D::Error> {
+        Value::deserialize(d).map(Some)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Option<Value>, A::Error> {
+        let mut table = Table::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            table.push((None, value));
+        }
+        Ok(Some(Value::Object(table)))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Option<Value>, A::Error> {
+        let mut table = Table::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(Key(key)) = map.next_key()? {
+            if JSON_NUMBER_TOKEN == key && table.is_empty() {
+                let raw: String = map.next_value()?;
+                return Ok(Some(match raw.parse::<i128>() {
+                    Ok(n) => integer(n),
+                    Err(_) => Value::Float(raw.parse().map_err(de::Error::custom)?),
+                }));
+            }
+            if let Nullable(Some(value)) = map.next_value()? {
+                table.push((Some(key.into()), value));
+            }
+        }
+        Ok(Some(Value::Object(table)))
+    }
+}
+
+/// The key of a map entry, which may be written as a number or a boolean.
+struct Key(String);
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Key, D::Error> {
+        deserializer.deserialize_any(KeyVisitor)
+    }
+}
+
+struct KeyVisitor;
+
+impl Visitor<'_> for KeyVisitor {
+    type Value = Key;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a string, number or boolean key")
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Key, E> {
+        Ok(Key(s.to_string()))
+    }
+    fn visit_string<E>(self, s: String) -> Result<Key, E> {
+        Ok(Key(s))
+    }
+    fn visit_bool<E>(self, b: bool) -> Result<Key, E> {
+        Ok(Key(b.to_string()))
+    }
+    fn visit_i64<E>(self, n: i64) -> Result<Key, E> {
+        Ok(Key(n.to_string()))
+    }
+    fn visit_u64<E>(self, n: u64) -> Result<Key, E> {
+        Ok(Key(n.to_string()))
+    }
+    fn visit_f64<E>(self, f: f64) -> Result<Key, E> {
+        Ok(Key(f.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{parse, Value};
+
+    #[test]
+    fn from_json() {
+        let v: Value =
+            serde_json::from_str(r#"{"a": [1, 2.5, "x"], "b": {"c": true, "d": null}, "2": []}"#)
+                .unwrap();
+        assert_eq!(
+            Value::Object(parse(r#"{a = {1, 2.5, "x"}, b = {c = true}, ["2"] = {}}"#).unwrap()),
+            v
+        );
+        assert_eq!(
+            Value::Number {
+                value: 12345678901234567890.,
+                raw: "12345678901234567890".to_string()
+            },
+            serde_json::from_str::<Value>("12345678901234567890").unwrap()
+        );
+        assert!(serde_json::from_str::<Value>("[1, null]").is_err());
+        assert!(serde_json::from_str::<Value>("null").is_err());
+    }
+
+    #[test]
+    fn from_toml() {
+        let v: Value = ::toml::from_str("name = \"app\"\n[server]\nport = 8080\n").unwrap();
+        assert_eq!(
+            Value::Object(parse(r#"{name = "app", server = {port = 8080}}"#).unwrap()),
+            v
+        );
+    }
+}
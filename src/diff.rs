@@ -0,0 +1,264 @@
+use std::collections::hash_map::{Entry, HashMap};
+
+use crate::parse::{Table, Value};
+use crate::path;
+
+/// A difference found by [`diff`], at a path in the syntax of [`Value::get_path`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Added {
+        path: String,
+        value: Value,
+    },
+    Removed {
+        path: String,
+        value: Value,
+    },
+    Changed {
+        path: String,
+        old: Value,
+        new: Value,
+    },
+}
+
+impl Change {
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::Changed { path, .. } => path,
+        }
+    }
+}
+
+/// Everything that differs between `a` and `b`. Named entries are matched up by key, and
+/// positional entries by their position.
+pub fn diff(a: &Value, b: &Value) -> Vec<Change> {
+    diff_within(a, b, 0.)
+}
+
+/// Like [`diff`], but numbers within `epsilon` of each other are the same.
+pub fn diff_within(a: &Value, b: &Value, epsilon: f64) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_at(a, b, epsilon, &mut String::new(), &mut changes);
+    changes
+}
+
+impl Value {
+    /// Whether this is the same as `other` apart from the order of named entries, which
+    /// [`PartialEq`] doesn't ignore. Numbers are compared by value, however they were written,
+    /// so NaN is the same as NaN, and only the last of each key counts, as when converting to
+    /// JSON. This is when [`diff`] finds no changes.
+    pub fn structurally_eq(&self, other: &Value) -> bool {
+        self.structurally_eq_within(other, 0.)
+    }
+
+    /// Like [`Value::structurally_eq`], but numbers within `epsilon` of each other are the
+    /// same, for comparing the results of arithmetic.
+    pub fn structurally_eq_within(&self, other: &Value, epsilon: f64) -> bool {
+        let (a, b) = match (self, other) {
+            (Value::Object(a), Value::Object(b)) => (a, b),
+            _ => return scalar_eq(self, other, epsilon),
+        };
+        let (a_named, b_named) = (Named::new(a), Named::new(b));
+        a_named.entries.len() == b_named.entries.len()
+            && a_named.entries.iter().all(|&(key, a)| {
+                b_named
+                    .get(key)
+                    .is_some_and(|b| a.structurally_eq_within(b, epsilon))
+            })
+            && positional(a).count() == positional(b).count()
+            && positional(a)
+                .zip(positional(b))
+                .all(|(a, b)| a.structurally_eq_within(b, epsilon))
+    }
+}
+
+fn scalar_eq(a: &Value, b: &Value, epsilon: f64) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a == b || (a - b).abs() <= epsilon || (a.is_nan() && b.is_nan()),
+        _ => a == b,
+    }
+}
+
+fn diff_at(a: &Value, b: &Value, epsilon: f64, at: &mut String, changes: &mut Vec<Change>) {
+    let (a_table, b_table) = match (a, b) {
+        (Value::Object(a), Value::Object(b)) => (a, b),
+        _ => {
+            if !scalar_eq(a, b, epsilon) {
+                changes.push(Change::Changed {
+                    path: at.clone(),
+                    old: a.clone(),
+                    new: b.clone(),
+                });
+            }
+            return;
+        }
+    };
+
+    let len = at.len();
+    let (a_named, b_named) = (Named::new(a_table), Named::new(b_table));
+    for &(key, a) in &a_named.entries {
+        path::push_key(at, key);
+        match b_named.get(key) {
+            Some(b) => diff_at(a, b, epsilon, at, changes),
+            None => changes.push(Change::Removed {
+                path: at.clone(),
+                value: a.clone(),
+            }),
+        }
+        at.truncate(len);
+    }
+
+    let a_positional = positional(a_table).collect::<Vec<_>>();
+    let b_positional = positional(b_table).collect::<Vec<_>>();
+    for i in 0..a_positional.len().max(b_positional.len()) {
+        path::push_index(at, i);
+        match (a_positional.get(i), b_positional.get(i)) {
+            (Some(a), Some(b)) => diff_at(a, b, epsilon, at, changes),
+            (Some(a), None) => changes.push(Change::Removed {
+                path: at.clone(),
+                value: (*a).clone(),
+            }),
+            (None, Some(b)) => changes.push(Change::Added {
+                path: at.clone(),
+                value: (*b).clone(),
+            }),
+            (None, None) => unreachable!(),
+        }
+        at.truncate(len);
+    }
+
+    for &(key, b) in &b_named.entries {
+        if a_named.get(key).is_none() {
+            path::push_key(at, key);
+            changes.push(Change::Added {
+                path: at.clone(),
+                value: b.clone(),
+            });
+            at.truncate(len);
+        }
+    }
+}
+
+/// The named entries of a table, with an index of them by key. Only the last of each key
+/// counts, as when converting to JSON, but in the place of the first.
+struct Named<'v> {
+    entries: Vec<(&'v str, &'v Value)>,
+    index: HashMap<&'v str, usize>,
+}
+
+impl<'v> Named<'v> {
+    fn new(table: &'v Table) -> Self {
+        let mut named = Named {
+            entries: Vec::new(),
+            index: HashMap::new(),
+        };
+        for (k, v) in table {
+            let Some(k) = k.as_deref() else { continue };
+            match named.index.entry(k) {
+                Entry::Occupied(entry) => named.entries[*entry.get()].1 = v,
+                Entry::Vacant(entry) => {
+                    entry.insert(named.entries.len());
+                    named.entries.push((k, v));
+                }
+            }
+        }
+        named
+    }
+
+    fn get(&self, key: &str) -> Option<&'v Value> {
+        self.index.get(key).map(|&i| self.entries[i].1)
+    }
+}
+
+fn positional(table: &Table) -> impl Iterator<Item = &Value> {
+    table.iter().filter(|(k, _)| k.is_none()).map(|(_, v)| v)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diff::{diff, diff_within, Change};
+    use crate::parse::{parse, Value};
+
+    fn lua(s: &str) -> Value {
+        Value::Object(parse(s).unwrap())
+    }
+
+    #[test]
+    fn changes() {
+        assert_eq!(
+            Vec::<Change>::new(),
+            diff(&lua("{a = {1, 2}}"), &lua("{a = {1, 2}}"))
+        );
+        assert_eq!(
+            vec![
+                Change::Changed {
+                    path: "a.b".to_string(),
+                    old: Value::Float(1.),
+                    new: Value::String("x".to_string()),
+                },
+                Change::Removed {
+                    path: "gone".to_string(),
+                    value: Value::Bool(true),
+                },
+                Change::Removed {
+                    path: "[1]".to_string(),
+                    value: Value::Float(6.),
+                },
+                Change::Added {
+                    path: "new key".to_string(),
+                    value: lua("{}"),
+                },
+            ],
+            diff(
+                &lua(r#"{a = {b = 1, c = 2}, gone = true, 5, 6}"#),
+                &lua(r#"{a = {b = "x", c = 2}, 5, ["new key"] = {}}"#)
+            )
+        );
+        assert_eq!(
+            vec![Change::Added {
+                path: "a[2]".to_string(),
+                value: Value::Float(3.),
+            }],
+            diff(&lua("{a = {1, 2}}"), &lua("{a = {1, 2, 3}}"))
+        );
+        assert_eq!(
+            Vec::<Change>::new(),
+            diff(&lua("{0/0, b = 1, a = 2}"), &lua("{a = 2, 0/0, b = 1}"))
+        );
+        assert_eq!(
+            vec![Change::Changed {
+                path: "x".to_string(),
+                old: Value::Float(1.),
+                new: Value::Float(2.),
+            }],
+            diff(&lua("{x = 1}"), &lua("{x = 1, x = 2}"))
+        );
+        assert_eq!(1, diff(&lua("{0.3}"), &lua("{0.30000000000000004}")).len());
+        assert!(diff_within(&lua("{0.3}"), &lua("{0.30000000000000004}"), 1e-9).is_empty());
+    }
+
+    #[test]
+    fn structurally_eq() {
+        let a = lua(r#"{x = 1, y = {b = true, a = "s"}, "p", "q"}"#);
+        let b = lua(r#"{"p", y = {a = "s", b = true}, "q", x = 1}"#);
+        assert_ne!(a, b);
+        assert!(a.structurally_eq(&b));
+        assert!(!a.structurally_eq(&lua(r#"{x = 1, y = {b = true, a = "s"}, "q", "p"}"#)));
+        assert!(!a.structurally_eq(&lua(r#"{x = 1, y = {b = true}, "p", "q"}"#)));
+        assert!(!lua("{x = 1}").structurally_eq(&lua("{x = 1, y = 2}")));
+        assert!(!lua("{x = 1, x = 2}").structurally_eq(&lua("{x = 1}")));
+        assert!(lua("{x = 1, x = 2}").structurally_eq(&lua("{x = 2}")));
+        let raw = Value::Number {
+            value: 1.,
+            raw: "1.00".to_string(),
+        };
+        assert!(raw.structurally_eq(&Value::Float(1.)));
+
+        let (a, b) = (lua("{r = 0.30000000000000004}"), lua("{r = 0.3}"));
+        assert!(!a.structurally_eq(&b));
+        assert!(a.structurally_eq_within(&b, 1e-9));
+        assert!(!a.structurally_eq_within(&lua("{r = 0.4}"), 1e-9));
+    }
+}
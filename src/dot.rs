@@ -0,0 +1,162 @@
+use std::fmt::Write;
+
+use crate::parse::{Table, Value};
+
+/// Options for [`to_dot_with`].
+#[derive(Debug, Clone, Default)]
+pub struct DotOptions {
+    /// Draw tables nested more deeply than this, where the value itself is at depth zero, as a
+    /// single node saying how many entries they have, to keep large documents readable.
+    pub max_depth: Option<usize>,
+}
+
+/// The longest a string is drawn before it's cut short, in characters.
+const MAX_LABEL: usize = 32;
+
+/// Render the structure of `value` as a Graphviz DOT graph, for drawing with `dot -Tsvg`.
+///
+/// Each table is a box, with an edge to each of its values labelled with the entry's key, or
+/// its position like `[0]`, and each scalar is a leaf showing the value.
+pub fn to_dot(value: &Value) -> String {
+    to_dot_with(value, &DotOptions::default())
+}
+
+/// Like [`to_dot`], with `opts`.
+pub fn to_dot_with(value: &Value, opts: &DotOptions) -> String {
+    let mut graph = Graph {
+        out: String::from("digraph {\n    node [fontname = \"monospace\"];\n"),
+        nodes: 0,
+        opts,
+    };
+    graph.node(value, 0);
+    graph.out.push_str("}\n");
+    graph.out
+}
+
+struct Graph<'o> {
+    out: String,
+    nodes: usize,
+    opts: &'o DotOptions,
+}
+
+impl Graph<'_> {
+    /// Write the node for `value` and everything under it, returning its ID.
+    fn node(&mut self, value: &Value, depth: usize) -> usize {
+        let id = self.nodes;
+        self.nodes += 1;
+        let (shape, label) = match value {
+            Value::Object(table) if self.opts.max_depth.is_some_and(|max| depth >= max) => {
+                ("box, style = dashed", format!("{{...}} ({})", count(table)))
+            }
+            Value::Object(table) => {
+                let label = quote(&format!("table ({})", count(table)));
+                let _ = writeln!(self.out, "    n{id} [shape = box, label = {label}];");
+                self.entries(id, table, depth);
+                return id;
+            }
+            Value::String(s) => ("ellipse", format!("\"{}\"", shorten(s))),
+            Value::Bytes(b) => (
+                "ellipse",
+                format!("\"{}\"", shorten(&String::from_utf8_lossy(b))),
+            ),
+            Value::Float(f) => ("ellipse", f.to_string()),
+            Value::Number { raw, .. } => ("ellipse", raw.clone()),
+            Value::Bool(b) => ("ellipse", b.to_string()),
+            Value::Raw(raw) => ("note", shorten(raw)),
+        };
+        let _ = writeln!(
+            self.out,
+            "    n{id} [shape = {shape}, label = {}];",
+            quote(&label)
+        );
+        id
+    }
+
+    fn entries(&mut self, parent: usize, table: &Table, depth: usize) {
+        let mut index = 0;
+        for (k, v) in table {
+            let label = match k {
+                Some(k) => k.to_string(),
+                None => {
+                    index += 1;
+                    format!("[{}]", index - 1)
+                }
+            };
+            let child = self.node(v, depth + 1);
+            let _ = writeln!(
+                self.out,
+                "    n{parent} -> n{child} [label = {}];",
+                quote(&label)
+            );
+        }
+    }
+}
+
+fn count(table: &Table) -> String {
+    match table.len() {
+        1 => "1 entry".to_string(),
+        n => format!("{n} entries"),
+    }
+}
+
+/// `s`, cut short at [`MAX_LABEL`] characters.
+fn shorten(s: &str) -> String {
+    match s.char_indices().nth(MAX_LABEL) {
+        Some((at, _)) => format!("{}...", &s[..at]),
+        None => s.to_string(),
+    }
+}
+
+/// `s` as a quoted DOT ID.
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\r' => {}
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dot::{to_dot, to_dot_with, DotOptions};
+    use crate::parse::{parse, Value};
+
+    #[test]
+    fn graph() {
+        let v = Value::Object(parse(r#"{name = "a \"b\"", {1, {true}}}"#).unwrap());
+        assert_eq!(
+            r#"digraph {
+    node [fontname = "monospace"];
+    n0 [shape = box, label = "table (2 entries)"];
+    n1 [shape = ellipse, label = "\"a \"b\"\""];
+    n0 -> n1 [label = "name"];
+    n2 [shape = box, label = "table (2 entries)"];
+    n3 [shape = ellipse, label = "1"];
+    n2 -> n3 [label = "[0]"];
+    n4 [shape = box, label = "table (1 entry)"];
+    n5 [shape = ellipse, label = "true"];
+    n4 -> n5 [label = "[0]"];
+    n2 -> n4 [label = "[1]"];
+    n0 -> n2 [label = "[0]"];
+}
+"#,
+            to_dot(&v)
+        );
+
+        let opts = DotOptions { max_depth: Some(1) };
+        let dot = to_dot_with(&v, &opts);
+        assert!(dot.contains(r#"n2 [shape = box, style = dashed, label = "{...} (2 entries)"];"#));
+        assert!(!dot.contains("n3"));
+        assert!(to_dot(&Value::from("x".repeat(40).as_str())).contains(&("x".repeat(32) + "...")));
+    }
+}
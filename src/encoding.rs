@@ -0,0 +1,112 @@
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::string::String;
+use core::str::{self, FromStr};
+
+use crate::error::{Lua2JsonError, Result};
+
+/// How the bytes of a document are encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// ISO-8859-1, where every byte is the code point of the same value.
+    Latin1,
+    /// UTF-16 if there's a byte order mark for it, otherwise UTF-8 if it's valid, otherwise
+    /// Latin-1.
+    Auto,
+}
+
+impl FromStr for Encoding {
+    type Err = Lua2JsonError;
+    fn from_str(s: &str) -> Result<Encoding> {
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Encoding::Utf8,
+            "latin-1" | "latin1" | "iso-8859-1" => Encoding::Latin1,
+            "auto" => Encoding::Auto,
+            other => {
+                return Err(Lua2JsonError::Unsupported {
+                    construct: format!("encoding {other:?}, expected utf-8, latin-1 or auto"),
+                    span: 0..s.len(),
+                })
+            }
+        })
+    }
+}
+
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// Decode `bytes` as text, dropping any byte order mark.
+pub fn decode(bytes: &[u8], encoding: Encoding) -> Result<Cow<'_, str>> {
+    match encoding {
+        Encoding::Utf8 => {
+            let text = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+            str::from_utf8(text).map(Cow::Borrowed).map_err(|e| {
+                let at = bytes.len() - text.len() + e.valid_up_to();
+                let len = e.error_len().unwrap_or(text.len() - e.valid_up_to());
+                Lua2JsonError::parse("invalid UTF-8", at..at + len)
+            })
+        }
+        Encoding::Latin1 => Ok(match str::from_utf8(bytes) {
+            // ASCII is the same either way
+            Ok(s) if s.is_ascii() => Cow::Borrowed(s),
+            _ => Cow::Owned(bytes.iter().map(|&b| char::from(b)).collect()),
+        }),
+        Encoding::Auto => {
+            if let Some(rest) = bytes.strip_prefix(b"\xff\xfe") {
+                return utf16(rest, u16::from_le_bytes);
+            }
+            if let Some(rest) = bytes.strip_prefix(b"\xfe\xff") {
+                return utf16(rest, u16::from_be_bytes);
+            }
+            let bytes = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+            match str::from_utf8(bytes) {
+                Ok(s) => Ok(Cow::Borrowed(s)),
+                Err(_) => decode(bytes, Encoding::Latin1),
+            }
+        }
+    }
+}
+
+/// Decode the UTF-16 after a two byte byte order mark.
+fn utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> Result<Cow<'static, str>> {
+    if !bytes.len().is_multiple_of(2) {
+        let end = 2 + bytes.len();
+        return Err(Lua2JsonError::parse("truncated UTF-16", end - 1..end));
+    }
+    let units = bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]));
+    let mut out = String::with_capacity(bytes.len() / 2);
+    let mut at = 2;
+    for c in char::decode_utf16(units) {
+        let c = c.map_err(|_| Lua2JsonError::parse("unpaired UTF-16 surrogate", at..at + 2))?;
+        at += 2 * c.len_utf16();
+        out.push(c);
+    }
+    Ok(Cow::Owned(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encoding::{decode, Encoding};
+
+    #[test]
+    fn decoding() {
+        assert_eq!("caf\u{e9}", decode(b"caf\xe9", Encoding::Latin1).unwrap());
+        assert_eq!("caf\u{e9}", decode(b"caf\xe9", Encoding::Auto).unwrap());
+        assert_eq!(
+            "caf\u{e9}",
+            decode("café".as_bytes(), Encoding::Auto).unwrap()
+        );
+        assert!(decode(b"caf\xe9", Encoding::Utf8).is_err());
+        assert_eq!("{}", decode(b"\xef\xbb\xbf{}", Encoding::Utf8).unwrap());
+        assert_eq!("{}", decode(b"\xef\xbb\xbf{}", Encoding::Auto).unwrap());
+        assert_eq!("{}", decode(b"\xff\xfe{\0}\0", Encoding::Auto).unwrap());
+        assert_eq!("{}", decode(b"\xfe\xff\0{\0}", Encoding::Auto).unwrap());
+        let span = |bytes: &[u8], encoding| decode(bytes, encoding).unwrap_err().span();
+        assert_eq!(Some(4..5), span(b"\xfe\xff\0{\0", Encoding::Auto));
+        assert_eq!(Some(4..6), span(b"\xff\xfe{\0\0\xdc", Encoding::Auto));
+        assert_eq!(Some(5..6), span(b"\xef\xbb\xbf{}\xe9", Encoding::Utf8));
+        assert_eq!(Encoding::Latin1, "ISO-8859-1".parse().unwrap());
+        assert!("utf-7".parse::<Encoding>().is_err());
+    }
+}
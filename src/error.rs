@@ -0,0 +1,197 @@
+use alloc::string::String;
+use core::fmt;
+use core::ops::Range;
+
+/// Why parsing or converting a document failed.
+///
+/// This is a [`std::error::Error`], so `?` turns it into an `anyhow::Error`, from which
+/// [`anyhow::Error::downcast_ref`] gets it back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lua2JsonError {
+    /// The input isn't valid, where `span` is the byte range of the input which was rejected.
+    Parse {
+        message: String,
+        span: Range<usize>,
+    },
+    /// Valid Lua which this library doesn't handle, like an unknown string escape.
+    Unsupported {
+        construct: String,
+        span: Range<usize>,
+    },
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    KeyNotFound {
+        key: String,
+    },
+    IndexOutOfRange {
+        index: usize,
+        len: usize,
+    },
+    LimitExceeded(LimitExceeded),
+}
+
+pub type Result<T, E = Lua2JsonError> = core::result::Result<T, E>;
+
+impl Lua2JsonError {
+    pub(crate) fn parse(message: impl Into<String>, span: Range<usize>) -> Lua2JsonError {
+        Lua2JsonError::Parse {
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// The byte range of the input which caused the error, if it was caused by some of it.
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            Lua2JsonError::Parse { span, .. } | Lua2JsonError::Unsupported { span, .. } => {
+                Some(span.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// Move the span along by `by` bytes, for an error from part of a larger input.
+    pub(crate) fn offset(mut self, by: usize) -> Lua2JsonError {
+        if let Lua2JsonError::Parse { span, .. } | Lua2JsonError::Unsupported { span, .. } =
+            &mut self
+        {
+            *span = span.start + by..span.end + by;
+        }
+        self
+    }
+}
+
+impl fmt::Display for Lua2JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lua2JsonError::Parse { message, span } => {
+                write!(f, "{message} at byte {}", span.start)
+            }
+            Lua2JsonError::Unsupported { construct, .. } => write!(f, "unsupported {construct}"),
+            Lua2JsonError::TypeMismatch { expected, found } => {
+                write!(f, "expected {expected} but found {found}")
+            }
+            Lua2JsonError::KeyNotFound { key } => write!(f, "no matching key: {key:?}"),
+            Lua2JsonError::IndexOutOfRange { index, len } => {
+                write!(f, "no positional entry {index}, only {len} present")
+            }
+            Lua2JsonError::LimitExceeded(exceeded) => exceeded.fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for Lua2JsonError {}
+
+/// The line and column, counting from one, of byte `at` of `source`, for showing where a
+/// span starts. Columns are in bytes.
+pub fn line_column(source: &str, at: usize) -> (usize, usize) {
+    let before = &source.as_bytes()[..at.min(source.len())];
+    let line = before.iter().filter(|&&b| b'\n' == b).count() + 1;
+    let column = before.len()
+        - before
+            .iter()
+            .rposition(|&b| b'\n' == b)
+            .map_or(0, |n| n + 1)
+        + 1;
+    (line, column)
+}
+
+impl From<AccessError> for Lua2JsonError {
+    fn from(e: AccessError) -> Lua2JsonError {
+        match e {
+            AccessError::KeyNotFound { key } => Lua2JsonError::KeyNotFound { key },
+            AccessError::IndexOutOfRange { index, len } => {
+                Lua2JsonError::IndexOutOfRange { index, len }
+            }
+            AccessError::TypeMismatch { expected, found } => {
+                Lua2JsonError::TypeMismatch { expected, found }
+            }
+        }
+    }
+}
+
+impl From<LimitExceeded> for Lua2JsonError {
+    fn from(exceeded: LimitExceeded) -> Lua2JsonError {
+        Lua2JsonError::LimitExceeded(exceeded)
+    }
+}
+
+/// Something in the input which was parsed, but perhaps not as intended, like a value kept as
+/// [`Value::Raw`](crate::parse::Value::Raw) text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub message: String,
+    /// The byte range of the input which was affected.
+    pub span: Range<usize>,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.span.start)
+    }
+}
+
+/// Why looking something up in a [`Value`](crate::parse::Value) failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccessError {
+    KeyNotFound {
+        key: String,
+    },
+    IndexOutOfRange {
+        index: usize,
+        len: usize,
+    },
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+}
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccessError::KeyNotFound { key } => write!(f, "no matching key: {key:?}"),
+            AccessError::IndexOutOfRange { index, len } => {
+                write!(f, "no positional entry {index}, only {len} present")
+            }
+            AccessError::TypeMismatch { expected, found } => {
+                write!(f, "expected {expected} but found {found}")
+            }
+        }
+    }
+}
+
+impl core::error::Error for AccessError {}
+
+/// A document was rejected for exceeding one of the [`Limits`](crate::parse::Limits) it was
+/// parsed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LimitExceeded {
+    pub limit: Limit,
+    /// The value of the limit.
+    pub max: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    InputSize,
+    StringLength,
+    Entries,
+    Depth,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let max = self.max;
+        match self.limit {
+            Limit::InputSize => write!(f, "input is longer than {max} bytes"),
+            Limit::StringLength => write!(f, "string is longer than {max} bytes"),
+            Limit::Entries => write!(f, "more than {max} table entries"),
+            Limit::Depth => write!(f, "tables nested more than {max} deep"),
+        }
+    }
+}
+
+impl core::error::Error for LimitExceeded {}
@@ -0,0 +1,159 @@
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use anyhow::Result;
+
+use crate::encoding::{decode, Encoding};
+use crate::format::Indent;
+use crate::json::{to_json_string, JsonOptions};
+use crate::parse::{parse, Value};
+
+/// The result of a call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lua2JsonStatus {
+    Ok = 0,
+    /// A pointer which must not be null was.
+    InvalidArgument = 1,
+    /// The input couldn't be decoded or parsed.
+    ParseError = 2,
+    /// A bug in the library, which has been caught.
+    InternalError = 3,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', "\\0")).expect("no NULs");
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Convert the `len` bytes of a Lua table at `input` to JSON, indented if `pretty`, and store a
+/// pointer to the NUL-terminated JSON in `out`, which must be freed with [`lua2json_free`].
+///
+/// The input is UTF-16 if it starts with a byte order mark for it, otherwise UTF-8 if it's
+/// valid, otherwise Latin-1. On failure, `out` is set to null and
+/// [`lua2json_last_error_message`] describes what went wrong.
+///
+/// # Safety
+///
+/// `input` must point to `len` readable bytes, and `out` must be valid to write a pointer to.
+#[no_mangle]
+pub unsafe extern "C" fn lua2json_parse(
+    input: *const u8,
+    len: usize,
+    pretty: bool,
+    out: *mut *mut c_char,
+) -> Lua2JsonStatus {
+    if out.is_null() {
+        set_last_error("out is null".to_string());
+        return Lua2JsonStatus::InvalidArgument;
+    }
+    *out = ptr::null_mut();
+    if input.is_null() {
+        set_last_error("input is null".to_string());
+        return Lua2JsonStatus::InvalidArgument;
+    }
+    let input = std::slice::from_raw_parts(input, len);
+    match catch_unwind(AssertUnwindSafe(|| convert(input, pretty))) {
+        Ok(Ok(json)) => {
+            *out = CString::new(json).expect("JSON has no NULs").into_raw();
+            LAST_ERROR.with(|last| *last.borrow_mut() = None);
+            Lua2JsonStatus::Ok
+        }
+        Ok(Err(e)) => {
+            set_last_error(format!("{e:#}"));
+            Lua2JsonStatus::ParseError
+        }
+        Err(_) => {
+            set_last_error("internal error".to_string());
+            Lua2JsonStatus::InternalError
+        }
+    }
+}
+
+fn convert(input: &[u8], pretty: bool) -> Result<String> {
+    let table = parse(&decode(input, Encoding::Auto)?)?;
+    let opts = JsonOptions {
+        indent: if pretty {
+            Indent::Spaces(2)
+        } else {
+            Indent::None
+        },
+        ..JsonOptions::default()
+    };
+    to_json_string(&Value::Object(table), &opts)
+}
+
+/// Free JSON returned by [`lua2json_parse`]. Does nothing if `json` is null.
+///
+/// # Safety
+///
+/// `json` must be null or have been returned by [`lua2json_parse`], and not freed already.
+#[no_mangle]
+pub unsafe extern "C" fn lua2json_free(json: *mut c_char) {
+    if !json.is_null() {
+        drop(CString::from_raw(json));
+    }
+}
+
+/// A description of why the last call on this thread failed, or null if it succeeded. The
+/// message is owned by the library, and valid until the next call on this thread.
+#[no_mangle]
+pub extern "C" fn lua2json_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_deref().map_or(ptr::null(), CStr::as_ptr))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{c_char, CStr};
+    use std::ptr;
+
+    use crate::ffi::{lua2json_free, lua2json_last_error_message, lua2json_parse, Lua2JsonStatus};
+
+    fn parse(input: &[u8]) -> (Lua2JsonStatus, Option<String>) {
+        let mut out: *mut c_char = ptr::null_mut();
+        unsafe {
+            let status = lua2json_parse(input.as_ptr(), input.len(), false, &mut out);
+            if out.is_null() {
+                return (status, None);
+            }
+            let json = CStr::from_ptr(out).to_str().unwrap().to_string();
+            lua2json_free(out);
+            (status, Some(json))
+        }
+    }
+
+    fn last_error() -> Option<String> {
+        let message = lua2json_last_error_message();
+        (!message.is_null()).then(|| {
+            unsafe { CStr::from_ptr(message) }
+                .to_string_lossy()
+                .into_owned()
+        })
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(
+            (Lua2JsonStatus::Ok, Some(r#"{"a":[1.0,"é"]}"#.to_string())),
+            parse(b"{a = {1, \"\xe9\"}}")
+        );
+        assert_eq!(None, last_error());
+        assert_eq!((Lua2JsonStatus::ParseError, None), parse(b"{a = "));
+        assert!(last_error().is_some());
+        unsafe {
+            let mut out = ptr::null_mut();
+            assert_eq!(
+                Lua2JsonStatus::InvalidArgument,
+                lua2json_parse(ptr::null(), 0, false, &mut out)
+            );
+            assert_eq!(Some("input is null".into()), last_error());
+            lua2json_free(ptr::null_mut());
+        }
+    }
+}
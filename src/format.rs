@@ -0,0 +1,396 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt::Write;
+
+use crate::parse::{Key, Table, Value};
+
+/// How [`to_lua`] lays out its output.
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    pub indent: Indent,
+    pub quote: Quote,
+    /// Write every key in brackets, like `["name"]`, not only those which aren't identifiers.
+    pub quote_keys: bool,
+    /// Put a comma after the last entry of multi-line tables.
+    pub trailing_commas: bool,
+    pub key_order: KeyOrder,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            indent: Indent::Spaces(2),
+            quote: Quote::Double,
+            quote_keys: false,
+            trailing_commas: true,
+            key_order: KeyOrder::Preserve,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    /// Everything on one line.
+    None,
+    Spaces(usize),
+    Tab,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quote {
+    Double,
+    Single,
+}
+
+/// The order to write the entries of tables in. Apart from [`KeyOrder::Preserve`], positional
+/// entries come first, in their original order, then named entries ordered by key.
+#[derive(Debug, Clone, Copy)]
+pub enum KeyOrder {
+    Preserve,
+    /// By the bytes of the keys.
+    Sorted,
+    /// Like [`KeyOrder::Sorted`], but with runs of digits compared as numbers, so `item2`
+    /// comes before `item10`.
+    Natural,
+    Custom(fn(&str, &str) -> Ordering),
+}
+
+impl KeyOrder {
+    /// Compare two keys; everything is equal under [`KeyOrder::Preserve`].
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        match self {
+            KeyOrder::Preserve => Ordering::Equal,
+            KeyOrder::Sorted => a.cmp(b),
+            KeyOrder::Natural => natural(a, b),
+            KeyOrder::Custom(compare) => compare(a, b),
+        }
+    }
+
+    /// Sort entries into this order, keeping the order of positional entries and equal keys.
+    pub(crate) fn sort<T>(&self, entries: &mut [T], key: impl Fn(&T) -> Option<&str>) {
+        if let KeyOrder::Preserve = self {
+            return;
+        }
+        entries.sort_by(|a, b| match (key(a), key(b)) {
+            (Some(a), Some(b)) => self.compare(a, b),
+            (a, b) => a.is_some().cmp(&b.is_some()),
+        });
+    }
+}
+
+fn natural(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let split = |s: &str, digits: bool| {
+            s.find(|c: char| c.is_ascii_digit() != digits)
+                .unwrap_or(s.len())
+        };
+        let digits = match (a.chars().next(), b.chars().next()) {
+            (None, None) => return Ordering::Equal,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => true,
+            (None, _) | (_, None) => return a.cmp(b),
+            _ => false,
+        };
+        let (run_a, rest_a) = a.split_at(split(a, digits).max(1));
+        let (run_b, rest_b) = b.split_at(split(b, digits).max(1));
+        let order = if digits {
+            let (x, y) = (run_a.trim_start_matches('0'), run_b.trim_start_matches('0'));
+            x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+        } else {
+            run_a.cmp(run_b)
+        };
+        if Ordering::Equal != order {
+            return order;
+        }
+        (a, b) = (rest_a, rest_b);
+    }
+}
+
+impl Value {
+    /// Reorder the entries of this table, and every table inside it, into `order`.
+    pub fn sort_keys(&mut self, order: KeyOrder) {
+        if let Value::Object(table) = self {
+            order.sort(table, |(k, _)| k.as_deref());
+            for (_, v) in table {
+                v.sort_keys(order);
+            }
+        }
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+/// Render `value` as a Lua expression.
+pub fn to_lua(value: &Value, opts: &FormatOptions) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value, opts, 0);
+    out
+}
+
+fn write_value(out: &mut String, value: &Value, opts: &FormatOptions, depth: usize) {
+    match value {
+        Value::Object(table) => write_table(out, table, opts, depth),
+        Value::String(s) => write_string(out, s, opts.quote),
+        Value::Bytes(b) => write_bytes(out, b, opts.quote),
+        Value::Float(f) => write_number(out, *f),
+        Value::Number { raw, .. } | Value::Raw(raw) => out.push_str(raw),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+    }
+}
+
+fn write_table(out: &mut String, table: &Table, opts: &FormatOptions, depth: usize) {
+    if table.is_empty() {
+        out.push_str("{}");
+        return;
+    }
+    let mut entries = table.iter().collect::<Vec<_>>();
+    opts.key_order.sort(&mut entries, |(k, _)| k.as_deref());
+
+    let indent = match opts.indent {
+        Indent::None => None,
+        Indent::Spaces(n) => Some(" ".repeat(n)),
+        Indent::Tab => Some("\t".to_string()),
+    };
+    out.push('{');
+    for (i, (k, v)) in entries.iter().enumerate() {
+        match &indent {
+            Some(indent) => {
+                out.push('\n');
+                out.push_str(&indent.repeat(depth + 1));
+            }
+            None if i > 0 => out.push(' '),
+            None => {}
+        }
+        if let Some(k) = k {
+            if let Key::Integer(digits) = k {
+                write_integer_key(out, digits);
+            } else if opts.quote_keys {
+                write_bracketed(out, k, opts.quote);
+            } else {
+                write_key(out, k, opts.quote);
+            }
+            out.push_str(" = ");
+        }
+        write_value(out, v, opts, depth + 1);
+        let last = i + 1 == entries.len();
+        if !last || (indent.is_some() && opts.trailing_commas) {
+            out.push(',');
+        }
+    }
+    if let Some(indent) = &indent {
+        out.push('\n');
+        out.push_str(&indent.repeat(depth));
+    }
+    out.push('}');
+}
+
+pub(crate) fn write_key(out: &mut String, key: &str, quote: Quote) {
+    let identifier = key.starts_with(|c: char| c.is_ascii_alphabetic() || '_' == c)
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || '_' == c)
+        && !KEYWORDS.contains(&key);
+    if identifier {
+        out.push_str(key);
+    } else {
+        write_bracketed(out, key, quote);
+    }
+}
+
+fn write_integer_key(out: &mut String, digits: &str) {
+    out.push('[');
+    out.push_str(digits);
+    out.push(']');
+}
+
+fn write_bracketed(out: &mut String, key: &str, quote: Quote) {
+    out.push('[');
+    write_string(out, key, quote);
+    out.push(']');
+}
+
+fn quote_char(quote: Quote) -> char {
+    match quote {
+        Quote::Double => '"',
+        Quote::Single => '\'',
+    }
+}
+
+fn write_string(out: &mut String, s: &str, quote: Quote) {
+    let quote = quote_char(quote);
+    out.push(quote);
+    write_escaped(out, s, quote);
+    out.push(quote);
+}
+
+/// Write the valid UTF-8 as it is, and the rest as decimal escapes.
+fn write_bytes(out: &mut String, bytes: &[u8], quote: Quote) {
+    let quote = quote_char(quote);
+    out.push(quote);
+    for chunk in bytes.utf8_chunks() {
+        write_escaped(out, chunk.valid(), quote);
+        for b in chunk.invalid() {
+            write!(out, "\\{b:03}").expect("infallible");
+        }
+    }
+    out.push(quote);
+}
+
+fn write_escaped(out: &mut String, s: &str, quote: char) {
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if c.is_ascii_control() => write!(out, "\\{:03}", c as u32).expect("infallible"),
+            c => out.push(c),
+        }
+    }
+}
+
+fn write_number(out: &mut String, f: f64) {
+    if f.is_nan() {
+        out.push_str("0/0");
+    } else if f.is_infinite() {
+        out.push_str(if f > 0. { "math.huge" } else { "-math.huge" });
+    } else if f != 0. && !(1e-6..1e21).contains(&f.abs()) {
+        // where JavaScript switches too, rather than writing out hundreds of digits
+        write!(out, "{f:e}").expect("infallible");
+    } else {
+        write!(out, "{f}").expect("infallible");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::{to_lua, FormatOptions, Indent, KeyOrder, Quote};
+    use crate::parse::{parse, Value};
+
+    fn lua(s: &str) -> Value {
+        Value::Object(parse(s).unwrap())
+    }
+
+    #[test]
+    fn pretty() {
+        let v = lua(r#"{b = 1.5, a = {"x", true}, e = {}, ["not ident"] = 2, ["end"] = 3, "y"}"#);
+        assert_eq!(
+            r#"{
+  b = 1.5,
+  a = {
+    "x",
+    true,
+  },
+  e = {},
+  ["not ident"] = 2,
+  ["end"] = 3,
+  "y",
+}"#,
+            to_lua(&v, &FormatOptions::default())
+        );
+        let v2 = lua(&to_lua(&v, &FormatOptions::default()));
+        assert_eq!(v, v2);
+    }
+
+    #[test]
+    fn integer_keys() {
+        let v = lua(r#"{[1] = "a", ["2"] = "b", [03] = "c"}"#);
+        let opts = FormatOptions {
+            indent: Indent::None,
+            ..FormatOptions::default()
+        };
+        let text = to_lua(&v, &opts);
+        assert_eq!(r#"{[1] = "a", ["2"] = "b", [03] = "c"}"#, text);
+        assert_eq!(v, lua(&text));
+        assert_ne!(v, lua(r#"{["1"] = "a", ["2"] = "b", [03] = "c"}"#));
+    }
+
+    #[test]
+    fn options() {
+        let v = lua(r#"{b = "it's", a = {1, 2}, "y"}"#);
+        let opts = FormatOptions {
+            indent: Indent::None,
+            quote: Quote::Single,
+            key_order: KeyOrder::Sorted,
+            ..FormatOptions::default()
+        };
+        assert_eq!(r#"{'y', a = {1, 2}, b = 'it\'s'}"#, to_lua(&v, &opts));
+        let opts = FormatOptions {
+            quote_keys: true,
+            ..opts
+        };
+        assert_eq!(
+            r#"{'y', ['a'] = {1, 2}, ['b'] = 'it\'s'}"#,
+            to_lua(&v, &opts)
+        );
+        let opts = FormatOptions {
+            key_order: KeyOrder::Preserve,
+            ..opts
+        };
+        assert_eq!(v, lua(&to_lua(&v, &opts)));
+
+        let opts = FormatOptions {
+            indent: Indent::Tab,
+            trailing_commas: false,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            "{\n\tb = \"it's\",\n\ta = {\n\t\t1,\n\t\t2\n\t},\n\t\"y\"\n}",
+            to_lua(&v, &opts)
+        );
+    }
+
+    #[test]
+    fn key_order() {
+        let mut v = lua(r#"{b10 = 1, a = {z = 1, y = 2}, "x", b9 = 2, b09 = 3, B = 4, "w"}"#);
+        fn keys(v: &Value) -> Vec<&str> {
+            v.keys().map(|k| k.unwrap_or("_")).collect()
+        }
+        v.sort_keys(KeyOrder::Natural);
+        assert_eq!(vec!["_", "_", "B", "a", "b9", "b09", "b10"], keys(&v));
+        assert_eq!(vec!["y", "z"], keys(&v["a"]));
+        assert_eq!(Some("x"), v[0].as_str());
+        v.sort_keys(KeyOrder::Sorted);
+        assert_eq!(vec!["_", "_", "B", "a", "b09", "b10", "b9"], keys(&v));
+        v.sort_keys(KeyOrder::Custom(|a, b| b.cmp(a)));
+        assert_eq!(vec!["_", "_", "b9", "b10", "b09", "a", "B"], keys(&v));
+        assert_eq!(vec!["z", "y"], keys(&v["a"]));
+    }
+
+    #[test]
+    fn scalars() {
+        let opts = FormatOptions::default();
+        assert_eq!(
+            r#""a\\b\n\"\001""#,
+            to_lua(&Value::String("a\\b\n\"\u{1}".to_string()), &opts)
+        );
+        assert_eq!(
+            r#""a\255\n""#,
+            to_lua(&Value::Bytes(b"a\xff\n".to_vec()), &opts)
+        );
+        assert_eq!("-0.25", to_lua(&Value::Float(-0.25), &opts));
+        assert_eq!("100000000000000000000", to_lua(&Value::Float(1e20), &opts));
+        assert_eq!("1e21", to_lua(&Value::Float(1e21), &opts));
+        assert_eq!("-1.5e300", to_lua(&Value::Float(-1.5e300), &opts));
+        assert_eq!("0.000001", to_lua(&Value::Float(1e-6), &opts));
+        assert_eq!("2.5e-7", to_lua(&Value::Float(2.5e-7), &opts));
+        assert_eq!("5e-324", to_lua(&Value::Float(5e-324), &opts));
+        let v = lua("{1e300, -2.5e-7}");
+        assert_eq!(v, lua(&to_lua(&v, &opts)));
+        assert_eq!("math.huge", to_lua(&Value::Float(f64::INFINITY), &opts));
+        assert_eq!("0/0", to_lua(&Value::Float(f64::NAN), &opts));
+        let raw = Value::Number {
+            value: 1.,
+            raw: "1.00".to_string(),
+        };
+        assert_eq!("1.00", to_lua(&raw, &opts));
+        let raw = Value::Raw("function() end".to_string());
+        assert_eq!("function() end", to_lua(&raw, &opts));
+    }
+}
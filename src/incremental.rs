@@ -0,0 +1,232 @@
+use std::ops::Range;
+
+use anyhow::{bail, Result};
+
+use crate::error::{Limit, LimitExceeded};
+use crate::parse::{parse_node, Node, ParseOptions, Table, Value};
+
+/// A table parsed from text which is being edited, like in an editor, which is kept up to
+/// date by reparsing only the innermost table around each edit, rather than all of the text.
+///
+/// ```
+/// use lua2json::incremental::Tree;
+///
+/// let mut tree = Tree::parse(r#"{a = {hp = 1}, b = "x"}"#, &Default::default()).unwrap();
+/// tree.edit(11..12, "20").unwrap();
+/// assert_eq!(r#"{a = {hp = 20}, b = "x"}"#, tree.as_str());
+/// assert_eq!(lua2json::parse::parse(tree.as_str()).unwrap(), *tree.table());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Tree {
+    source: String,
+    opts: ParseOptions,
+    table: Table,
+    /// `None` when the text didn't parse after the last edit, so has to be parsed again in
+    /// full.
+    root: Option<Node>,
+}
+
+impl Tree {
+    /// Parse `source` like [`parse_with`](crate::parse::parse_with) does.
+    pub fn parse(source: impl Into<String>, opts: &ParseOptions) -> Result<Tree> {
+        let source = source.into();
+        check_size(&source, opts)?;
+        let (table, root) = parse_node(&source, opts, 0, 0)?;
+        Ok(Tree {
+            source,
+            opts: opts.clone(),
+            table,
+            root: Some(root),
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// The table, as of the last time the text parsed.
+    pub fn table(&self) -> &Table {
+        &self.table
+    }
+
+    pub fn into_table(self) -> Table {
+        self.table
+    }
+
+    /// Replace `range` of the text, in bytes, with `text`, and reparse the innermost table
+    /// which the edit is inside of, or its parent if that doesn't parse on its own, and so on
+    /// up to the whole text.
+    ///
+    /// If the edited text doesn't parse, the error is returned, but the edit is still made to
+    /// the text: [`table`](Tree::table) is left as it was, until an edit makes the text parse
+    /// again.
+    pub fn edit(&mut self, range: Range<usize>, text: &str) -> Result<()> {
+        if range.start > range.end || range.end > self.source.len() {
+            bail!(
+                "edit of {range:?} is outside of the text, of length {}",
+                self.source.len()
+            );
+        }
+        if !self.source.is_char_boundary(range.start) || !self.source.is_char_boundary(range.end) {
+            bail!("edit of {range:?} is inside of a character");
+        }
+        self.source.replace_range(range.clone(), text);
+        // as which entries are kept depends on the tables around them
+        let (Some(mut root), true) = (self.root.take(), self.opts.only.is_empty()) else {
+            return self.reparse();
+        };
+        check_size(&self.source, &self.opts)?;
+
+        // the innermost node around the edit, and the indexes of the entries down to it
+        let mut path = Vec::new();
+        let mut node = &root;
+        while let Some((i, child)) = node.children.iter().enumerate().find_map(|(i, child)| {
+            let child = child.as_ref()?;
+            (child.span.start < range.start && range.end < child.span.end).then_some((i, child))
+        }) {
+            path.push(i);
+            node = child;
+        }
+        if !(node.span.start < range.start && range.end < node.span.end) {
+            return self.reparse();
+        }
+
+        let moved = |at: usize| match at >= range.end {
+            true => at - range.len() + text.len(),
+            false => at,
+        };
+        let outside = root.entries;
+        while !path.is_empty() {
+            let node = descend(&root, &path);
+            let span = node.span.start..moved(node.span.end);
+            let entries = outside - node.entries;
+            if let Ok((table, mut new)) =
+                parse_node(&self.source[span.clone()], &self.opts, path.len(), entries)
+            {
+                let change = new.entries as isize - node.entries as isize;
+                shift(&mut new, &|at| at + span.start);
+                shift_after(&mut root, range.end, &moved);
+                let mut parent = &mut root;
+                let mut value = &mut self.table;
+                for &i in &path[..path.len() - 1] {
+                    parent = parent.children[i].as_mut().expect("a node");
+                    value = value[i].1.as_table_mut().expect("a table");
+                }
+                let last = path[path.len() - 1];
+                parent.children[last] = Some(new);
+                value[last].1 = Value::Object(table);
+                // the entries of the tables around it
+                let mut node = &mut root;
+                for &i in &path {
+                    node.entries = node.entries.saturating_add_signed(change);
+                    node = node.children[i].as_mut().expect("a node");
+                }
+                self.root = Some(root);
+                return Ok(());
+            }
+            path.pop();
+        }
+        self.reparse()
+    }
+
+    fn reparse(&mut self) -> Result<()> {
+        check_size(&self.source, &self.opts)?;
+        let (table, root) = parse_node(&self.source, &self.opts, 0, 0)?;
+        self.table = table;
+        self.root = Some(root);
+        Ok(())
+    }
+}
+
+fn check_size(source: &str, opts: &ParseOptions) -> Result<()> {
+    match opts.limits.max_input {
+        Some(max) if source.len() > max => Err(LimitExceeded {
+            limit: Limit::InputSize,
+            max,
+        }
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+fn descend<'n>(mut node: &'n Node, path: &[usize]) -> &'n Node {
+    for &i in path {
+        node = node.children[i].as_ref().expect("a node");
+    }
+    node
+}
+
+fn shift(node: &mut Node, moved: &impl Fn(usize) -> usize) {
+    node.span = moved(node.span.start)..moved(node.span.end);
+    for child in node.children.iter_mut().flatten() {
+        shift(child, moved);
+    }
+}
+
+/// Move the nodes which end at or after `at`, as the ones before it haven't moved.
+fn shift_after(node: &mut Node, at: usize, moved: &impl Fn(usize) -> usize) {
+    if node.span.end < at {
+        return;
+    }
+    node.span = moved(node.span.start)..moved(node.span.end);
+    for child in node.children.iter_mut().flatten() {
+        shift_after(child, at, moved);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::incremental::Tree;
+    use crate::parse::{parse_with, ParseOptions};
+
+    #[test]
+    fn edits() {
+        let opts = ParseOptions::default();
+        let mut tree =
+            Tree::parse(r#"{a = {b = {1, 2}, c = "x"}, d = {3}, e = 4}"#, &opts).unwrap();
+        let edits = [
+            // inside of the innermost table
+            ("1,", "1, 10, 11,"),
+            // which moves the tables after it
+            ("{3}", "{{5}}"),
+            ("4", "4, f = {6, {7}}"),
+            ("{7}", "{7, 8}"),
+            // which doesn't parse until the next edit closes the table again
+            ("{6", "6"),
+            ("6", "{6"),
+            // of a whole table, inside of its parent
+            ("{{5}}", "5"),
+            ("d = 5", "[\"end\"] = true"),
+            ("10, 11", ""),
+        ];
+        let mut source = tree.as_str().to_string();
+        for (old, new) in edits {
+            let at = source.find(old).unwrap();
+            source.replace_range(at..at + old.len(), new);
+            let result = tree.edit(at..at + old.len(), new);
+            assert_eq!(source, tree.as_str());
+            match parse_with(&source, &opts) {
+                Ok(table) => {
+                    result.unwrap();
+                    assert_eq!(table, *tree.table(), "{source}");
+                }
+                Err(_) => assert!(result.is_err(), "{source}"),
+            }
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let mut tree = Tree::parse("{a = {1}}", &ParseOptions::default()).unwrap();
+        assert!(tree.edit(3..20, "").is_err());
+        assert_eq!("{a = {1}}", tree.as_str());
+        assert!(tree.edit(7..8, "").is_err());
+        assert_eq!("{a = {1}", tree.as_str());
+        assert_eq!(1, tree.table().len());
+        tree.edit(8..8, ", b = 2}").unwrap();
+        assert_eq!(2, tree.table().len());
+        tree.edit(6..7, "}, c = {").unwrap();
+        assert_eq!("{a = {}, c = {}, b = 2}", tree.as_str());
+        assert_eq!(3, tree.table().len());
+    }
+}
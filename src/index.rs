@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+use crate::parse::{Table, Value};
+
+/// A view of a table with constant time lookups, for when a large table is looked up in many
+/// times, which would be linear time each with [`Value::get`].
+///
+/// ```
+/// use lua2json::index::IndexedTable;
+///
+/// let table = lua2json::parse::parse(r#"{a = 1, "x", b = 2}"#).unwrap();
+/// let indexed = IndexedTable::new(&table);
+/// assert_eq!(Some(2.), indexed.get("b").and_then(|v| v.as_f64()));
+/// assert_eq!(Some("x"), indexed.index(0).and_then(|v| v.as_str()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct IndexedTable<'a> {
+    table: &'a Table,
+    /// The first entry with each name, like [`Value::get`] finds.
+    named: HashMap<&'a str, usize>,
+    positional: Vec<usize>,
+}
+
+impl<'a> IndexedTable<'a> {
+    /// Index `table`, which takes linear time once.
+    pub fn new(table: &'a Table) -> IndexedTable<'a> {
+        let mut named = HashMap::with_capacity(table.len());
+        let mut positional = Vec::new();
+        for (i, (k, _)) in table.iter().enumerate() {
+            match k {
+                Some(k) => {
+                    named.entry(k.as_str()).or_insert(i);
+                }
+                None => positional.push(i),
+            }
+        }
+        IndexedTable {
+            table,
+            named,
+            positional,
+        }
+    }
+
+    /// The value of the first entry named `key`.
+    pub fn get(&self, key: &str) -> Option<&'a Value> {
+        self.named.get(key).map(|&i| &self.table[i].1)
+    }
+
+    /// The nth positional (unnamed) entry, counting from zero.
+    pub fn index(&self, i: usize) -> Option<&'a Value> {
+        self.positional.get(i).map(|&at| &self.table[at].1)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.named.contains_key(key)
+    }
+
+    /// The number of positional entries.
+    pub fn positional_len(&self) -> usize {
+        self.positional.len()
+    }
+
+    /// The table being indexed, in its original order.
+    pub fn table(&self) -> &'a Table {
+        self.table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index::IndexedTable;
+    use crate::parse::{parse, Value};
+
+    #[test]
+    fn lookups() {
+        let table = parse(r#"{a = 1, "x", a = 2, b = {3}, "y"}"#).unwrap();
+        let indexed = IndexedTable::new(&table);
+        let v = Value::Object(table.clone());
+        assert_eq!(v.get_opt("a"), indexed.get("a"));
+        assert_eq!(Some(&Value::from(vec![Value::from(3.)])), indexed.get("b"));
+        assert_eq!(None, indexed.get("c"));
+        assert!(indexed.contains_key("b"));
+        assert_eq!(Some(&Value::from("y")), indexed.index(1));
+        assert_eq!(None, indexed.index(2));
+        assert_eq!(2, indexed.positional_len());
+        assert_eq!(&table, indexed.table());
+    }
+}
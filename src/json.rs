@@ -0,0 +1,633 @@
+use std::borrow::Cow;
+use std::io::Write;
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+use crate::format::{Indent, KeyOrder};
+use crate::parse::{Key, Table, Value};
+
+/// How [`to_json`], [`to_json_string`] and [`write_json`] convert and lay out their output.
+#[derive(Debug, Clone)]
+pub struct JsonOptions {
+    /// [`Indent::None`] for compact output on one line, otherwise pretty output indented
+    /// by this.
+    pub indent: Indent,
+    /// The order to write the keys of objects in, which include the indexes of positional
+    /// entries in tables with named entries.
+    pub key_order: KeyOrder,
+    pub arrays: ArrayPolicy,
+    pub holes: Holes,
+    /// End the output with a newline.
+    pub trailing_newline: bool,
+    pub non_finite: NonFinite,
+    pub non_utf8: NonUtf8,
+    pub floats: Floats,
+    /// Write text which is the same for any two values which are
+    /// [`Value::structurally_eq`], for hashing or diffing, when writing text with
+    /// [`to_json_string`] or [`write_json`]. It's compact, with the keys of objects sorted by
+    /// their UTF-8 bytes and only the last of each, as `structurally_eq` counts them, strings
+    /// and keys in Unicode Normalization Form C, and numbers in the shortest form which reads
+    /// back as the same float, with `-0` written as `0.0`. `indent`, `key_order` and `floats`
+    /// are ignored.
+    ///
+    /// The exception is objects from tables mixing named and positional entries, whose
+    /// positional entries are keyed by their index among all the entries, so moving a named
+    /// entry past a positional one changes the text.
+    pub canonical: bool,
+}
+
+impl Default for JsonOptions {
+    fn default() -> Self {
+        JsonOptions {
+            indent: Indent::None,
+            key_order: KeyOrder::Preserve,
+            arrays: ArrayPolicy::Auto,
+            holes: Holes::Object,
+            trailing_newline: false,
+            non_finite: NonFinite::Null,
+            non_utf8: NonUtf8::Lossy,
+            floats: Floats::Shortest,
+            canonical: false,
+        }
+    }
+}
+
+/// Convert `value` to JSON.
+///
+/// Tables become arrays as decided by [`JsonOptions::arrays`], otherwise objects, with
+/// positional entries keyed by their index among all the entries.
+pub fn to_json(value: &Value, opts: &JsonOptions) -> Result<serde_json::Value> {
+    Ok(match value {
+        Value::Float(f) if f.is_finite() => opts.floats.to_json(*f),
+        Value::Float(f) => opts.non_finite.to_json(*f)?,
+        // JSON is stricter about the spelling of numbers than Lua, e.g. about leading zeros
+        Value::Number { value, raw } => match raw.parse() {
+            Ok(n) => serde_json::Value::Number(n),
+            Err(_) => opts.non_finite.to_json(*value)?,
+        },
+        Value::String(s) | Value::Raw(s) => serde_json::Value::String(s.clone()),
+        Value::Bytes(b) => opts.non_utf8.to_json(b)?,
+        Value::Object(t) => table_to_json(t, opts)?,
+        Value::Bool(b) => serde_json::Value::Bool(*b),
+    })
+}
+
+fn table_to_json(table: &Table, opts: &JsonOptions) -> Result<serde_json::Value> {
+    if let Some(items) = opts.arrays.items(table, opts.holes)? {
+        return Ok(serde_json::Value::Array(
+            items
+                .into_iter()
+                .map(|v| v.map_or(Ok(serde_json::Value::Null), |v| to_json(v, opts)))
+                .collect::<Result<_>>()?,
+        ));
+    }
+    let mut entries = table
+        .iter()
+        .enumerate()
+        .map(|(i, (k, v))| (k.clone().map_or_else(|| i.to_string(), Key::into_string), v))
+        .collect::<Vec<_>>();
+    opts.key_order.sort(&mut entries, |(k, _)| Some(k.as_str()));
+    let mut obj = serde_json::Map::new();
+    for (k, v) in entries {
+        obj.insert(k, to_json(v, opts)?);
+    }
+    Ok(serde_json::Value::Object(obj))
+}
+
+/// Render `value` as JSON text.
+pub fn to_json_string(value: &Value, opts: &JsonOptions) -> Result<String> {
+    let mut out = Vec::new();
+    write_json(&mut out, value, opts)?;
+    Ok(String::from_utf8(out).expect("JSON is UTF-8"))
+}
+
+/// Write `value` as JSON text.
+pub fn write_json(writer: impl Write, value: &Value, opts: &JsonOptions) -> Result<()> {
+    let mut writer = writer;
+    if opts.canonical {
+        let mut out = String::new();
+        write_canonical(value, opts, &mut out)?;
+        writer.write_all(out.as_bytes())?;
+        if opts.trailing_newline {
+            writer.write_all(b"\n")?;
+        }
+        return Ok(());
+    }
+    let json = to_json(value, opts)?;
+    let indent = match opts.indent {
+        Indent::None => None,
+        Indent::Spaces(n) => Some(" ".repeat(n)),
+        Indent::Tab => Some("\t".to_string()),
+    };
+    match indent {
+        None => serde_json::to_writer(&mut writer, &json)?,
+        Some(indent) => {
+            let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+            json.serialize(&mut serde_json::Serializer::with_formatter(
+                &mut writer,
+                formatter,
+            ))?;
+        }
+    }
+    if opts.trailing_newline {
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Write `value` as [`JsonOptions::canonical`] JSON.
+fn write_canonical(value: &Value, opts: &JsonOptions, out: &mut String) -> Result<()> {
+    match value {
+        // adding 0 turns -0 into 0
+        Value::Float(f) | Value::Number { value: f, .. } if f.is_finite() => {
+            out.push_str(ryu::Buffer::new().format_finite(f + 0.))
+        }
+        Value::Float(f) | Value::Number { value: f, .. } => {
+            out.push_str(&opts.non_finite.to_json(*f)?.to_string())
+        }
+        Value::String(s) | Value::Raw(s) => write_canonical_str(s, out),
+        Value::Bytes(b) => {
+            let s = opts.non_utf8.to_json(b)?;
+            write_canonical_str(s.as_str().expect("a string"), out);
+        }
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Object(table) => match opts.arrays.items(table, opts.holes)? {
+            Some(items) => {
+                out.push('[');
+                for (i, item) in items.into_iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    match item {
+                        Some(v) => write_canonical(v, opts, out)?,
+                        None => out.push_str("null"),
+                    }
+                }
+                out.push(']');
+            }
+            None => {
+                let mut entries = table
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (k, v))| match k {
+                        Some(k) => (nfc(k).into_owned(), v),
+                        None => (i.to_string(), v),
+                    })
+                    .collect::<Vec<_>>();
+                entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                // keeping the last value of each key, which the sort left in order
+                entries.dedup_by(|(later, v), (earlier, kept)| {
+                    let same = later == earlier;
+                    if same {
+                        *kept = *v;
+                    }
+                    same
+                });
+                out.push('{');
+                for (i, (k, v)) in entries.into_iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    write_canonical_str(&k, out);
+                    out.push(':');
+                    write_canonical(v, opts, out)?;
+                }
+                out.push('}');
+            }
+        },
+    }
+    Ok(())
+}
+
+fn nfc(s: &str) -> Cow<'_, str> {
+    match is_nfc(s) {
+        true => Cow::Borrowed(s),
+        false => Cow::Owned(s.nfc().collect()),
+    }
+}
+
+fn write_canonical_str(s: &str, out: &mut String) {
+    out.push_str(&serde_json::to_string(&nfc(s)).expect("strings always serialize"));
+}
+
+/// [`to_json`] with the default options.
+#[cfg(feature = "json-value")]
+impl From<&Value> for serde_json::Value {
+    fn from(value: &Value) -> serde_json::Value {
+        to_json(value, &JsonOptions::default()).expect("the default options never fail")
+    }
+}
+
+/// The same as deserializing a [`Value`] from JSON text: arrays become tables of positional
+/// entries and objects tables of named entries, and nulls are only allowed as the values of
+/// objects, where they're left out.
+#[cfg(feature = "json-value")]
+impl TryFrom<&serde_json::Value> for Value {
+    type Error = serde_json::Error;
+    fn try_from(json: &serde_json::Value) -> Result<Value, serde_json::Error> {
+        serde::Deserialize::deserialize(json)
+    }
+}
+
+/// Which tables to write as arrays rather than objects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArrayPolicy {
+    /// Tables with only positional entries, including empty ones.
+    #[default]
+    Auto,
+    /// None, so every table is an object.
+    AlwaysObject,
+    /// Also tables whose keys are the integers `1` to `n`, like `{[1] = "a", [2] = "b"}`, in
+    /// any order and mixed with positional entries, which are numbered as they are in Lua.
+    IntegerKeys,
+}
+
+impl ArrayPolicy {
+    /// The values of `table` in order, if it's written as an array, with `None` for the holes
+    /// which `holes` fills.
+    fn items(self, table: &Table, holes: Holes) -> Result<Option<Vec<Option<&Value>>>> {
+        match self {
+            ArrayPolicy::AlwaysObject => return Ok(None),
+            _ if table.iter().all(|(k, _)| k.is_none()) => {
+                return Ok(Some(table.iter().map(|(_, v)| Some(v)).collect()))
+            }
+            ArrayPolicy::Auto => return Ok(None),
+            ArrayPolicy::IntegerKeys => {}
+        }
+        let mut keyed = Vec::new();
+        let mut positional = Vec::new();
+        for (k, v) in table {
+            match k {
+                // not `[01]`, which Lua reads as `[1]` but JSON readers wouldn't, nor `["1"]`,
+                // which is a string
+                Some(Key::Integer(k)) => {
+                    match k.parse::<usize>().ok().filter(|i| *k == i.to_string()) {
+                        Some(index) if index > 0 => keyed.push((index, v)),
+                        _ => return Ok(None),
+                    }
+                }
+                Some(_) => return Ok(None),
+                None => positional.push((positional.len() + 1, v)),
+            }
+        }
+        let mut indexes = keyed
+            .iter()
+            .chain(&positional)
+            .map(|(i, _)| *i)
+            .collect::<Vec<_>>();
+        indexes.sort_unstable();
+        indexes.dedup();
+        let len = indexes.last().copied().unwrap_or(0);
+        if indexes.len() < len {
+            match holes {
+                Holes::Object => return Ok(None),
+                // so a key like `[99999999999999]` can't make an enormous array
+                Holes::Null if len > MAX_HOLES_FACTOR * table.len() => return Ok(None),
+                Holes::Null => {}
+                Holes::Error => {
+                    let hole = (1..).zip(&indexes).find(|(i, index)| i != *index);
+                    let hole = hole.map_or(len, |(i, _)| i);
+                    bail!("table has entries up to [{len}], but none at [{hole}]")
+                }
+            }
+        }
+        let mut items = vec![None; len];
+        // positional entries win over keyed ones for the same index, as they're assigned last
+        for (i, v) in keyed.into_iter().chain(positional) {
+            items[i - 1] = Some(v);
+        }
+        Ok(Some(items))
+    }
+}
+
+/// How many times longer than the number of entries in a table [`Holes::Null`] lets its array
+/// be.
+const MAX_HOLES_FACTOR: usize = 4;
+
+/// What to do with tables whose integer keys skip some indexes, like `{[1] = "a", [3] = "c"}`,
+/// when [`ArrayPolicy::IntegerKeys`] makes arrays of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Holes {
+    /// Write them as objects instead.
+    #[default]
+    Object,
+    /// Write `null` for the missing entries, like `["a",null,"c"]`, unless that would make the
+    /// array more than four times as long as the table has entries, when it's written as an
+    /// object instead.
+    Null,
+    /// Fail the conversion.
+    Error,
+}
+
+/// What to write for strings which aren't valid UTF-8, which JSON strings must be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonUtf8 {
+    /// Replace the invalid parts with U+FFFD.
+    #[default]
+    Lossy,
+    /// The whole string as standard base64.
+    Base64,
+    /// Fail the conversion.
+    Error,
+}
+
+impl NonUtf8 {
+    /// `bytes` as a JSON string, following this policy if they're not valid UTF-8.
+    pub fn to_json(self, bytes: &[u8]) -> Result<serde_json::Value> {
+        use base64::Engine;
+
+        if let Ok(s) = std::str::from_utf8(bytes) {
+            return Ok(s.into());
+        }
+        Ok(match self {
+            NonUtf8::Lossy => String::from_utf8_lossy(bytes).into(),
+            NonUtf8::Base64 => base64::engine::general_purpose::STANDARD
+                .encode(bytes)
+                .into(),
+            NonUtf8::Error => bail!("string is not valid UTF-8: {bytes:?}"),
+        })
+    }
+}
+
+/// What to write for infinities and NaN, which JSON has no numbers for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonFinite {
+    /// `null`, like `JSON.stringify`.
+    #[default]
+    Null,
+    /// The strings `"Infinity"`, `"-Infinity"` and `"NaN"`.
+    String,
+    /// Fail the conversion.
+    Error,
+}
+
+impl NonFinite {
+    /// `f` as JSON, following this policy if it's not finite.
+    pub fn to_json(self, f: f64) -> Result<serde_json::Value> {
+        if f.is_finite() {
+            return Ok(serde_json::json!(f));
+        }
+        Ok(match self {
+            NonFinite::Null => serde_json::Value::Null,
+            NonFinite::String if f.is_nan() => "NaN".into(),
+            NonFinite::String if f > 0. => "Infinity".into(),
+            NonFinite::String => "-Infinity".into(),
+            NonFinite::Error => bail!("{f} can't be represented in JSON"),
+        })
+    }
+}
+
+/// How to write finite [`Value::Float`]s. [`Value::Number`]s keep their source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Floats {
+    /// The shortest text which reads back as the same float, like `5.0` or `0.1`.
+    #[default]
+    Shortest,
+    /// Rounded to this many digits after the decimal point, like `5.00` for 2, or `5` for 0.
+    Fixed(usize),
+    /// Like `Shortest`, but without a decimal point for whole numbers, like `5`, for parsers
+    /// which expect integers. Whole numbers outside of the range of 64-bit integers are still
+    /// written like `1e300`.
+    Integers,
+}
+
+impl Floats {
+    /// The finite `f` as JSON, written this way.
+    pub fn to_json(self, f: f64) -> serde_json::Value {
+        match self {
+            Floats::Fixed(places) => format!("{f:.places$}")
+                .parse::<serde_json::Number>()
+                .expect("a decimal is a JSON number")
+                .into(),
+            // the bounds are -2^63 and 2^63, which convert exactly
+            Floats::Integers
+                if 0. == f.fract() && (i64::MIN as f64..i64::MAX as f64).contains(&f) =>
+            {
+                (f as i64).into()
+            }
+            Floats::Shortest | Floats::Integers => serde_json::json!(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::{Indent, KeyOrder};
+    use crate::json::{to_json_string, ArrayPolicy, Floats, Holes, JsonOptions, NonFinite};
+    use crate::parse::{parse, Value};
+
+    fn json(s: &str, opts: &JsonOptions) -> String {
+        to_json_string(&Value::Object(parse(s).unwrap()), opts).unwrap()
+    }
+
+    #[test]
+    fn layout() {
+        let input = r#"{b = 1, a = {1, "x"}, {}}"#;
+        let opts = JsonOptions::default();
+        assert_eq!(r#"{"b":1.0,"a":[1.0,"x"],"2":[]}"#, json(input, &opts));
+        let opts = JsonOptions {
+            key_order: KeyOrder::Sorted,
+            trailing_newline: true,
+            ..JsonOptions::default()
+        };
+        assert_eq!(
+            "{\"2\":[],\"a\":[1.0,\"x\"],\"b\":1.0}\n",
+            json(input, &opts)
+        );
+        let opts = JsonOptions {
+            key_order: KeyOrder::Natural,
+            ..JsonOptions::default()
+        };
+        assert_eq!(
+            r#"{"1":1.0,"9":2.0,"10":3.0,"a":4.0}"#,
+            json(r#"{a = 4, 1, ["9"] = 2, ["10"] = 3}"#, &opts)
+        );
+        let opts = JsonOptions {
+            indent: Indent::Spaces(4),
+            ..JsonOptions::default()
+        };
+        assert_eq!(
+            "{\n    \"b\": 1.0,\n    \"a\": [\n        1.0,\n        \"x\"\n    ],\n    \"2\": []\n}",
+            json(input, &opts)
+        );
+        let opts = JsonOptions {
+            indent: Indent::Tab,
+            ..JsonOptions::default()
+        };
+        assert_eq!("[\n\t1.0\n]", json("{1}", &opts));
+    }
+
+    #[test]
+    fn canonical() {
+        let opts = JsonOptions {
+            canonical: true,
+            // ignored
+            indent: Indent::Spaces(2),
+            ..JsonOptions::default()
+        };
+        assert_eq!(
+            r#"{"0":0.0,"a":[1.0,1e21,0.1,"x\n"],"b":2.0,"é":"é"}"#,
+            json(
+                "{-0, b = 1, a = {1, 1e21, 0.1, \"x\\n\"}, [\"e\\u{301}\"] = \"e\\u{301}\", b = 2}",
+                &opts
+            )
+        );
+        let same = |a: &str, b: &str| assert_eq!(json(a, &opts), json(b, &opts));
+        same("{a = 1.50, b = {}}", "{b = {}, a = 1.5}");
+        same("{1e1}", "{10.0}");
+        let opts = JsonOptions {
+            canonical: true,
+            trailing_newline: true,
+            ..JsonOptions::default()
+        };
+        assert_eq!("[null]\n", json("{0/0}", &opts));
+
+        // the same text exactly when the values are structurally equal
+        let opts = JsonOptions {
+            canonical: true,
+            ..JsonOptions::default()
+        };
+        for (a, b, eq) in [
+            ("{x = 1, x = 2}", "{x = 2}", true),
+            ("{x = 1, x = 2}", "{x = 1}", false),
+            ("{y = {2, -0}, x = 1, x = 3}", "{x = 3, y = {2.0, 0}}", true),
+            ("{a = 0/0, b = 1e21}", "{b = 1e21, a = 0/0}", true),
+            ("{a = {b = 1}}", "{a = {b = 1, c = 2}}", false),
+        ] {
+            let (a, b) = (
+                Value::Object(parse(a).unwrap()),
+                Value::Object(parse(b).unwrap()),
+            );
+            assert_eq!(eq, a.structurally_eq(&b), "{a:?} {b:?}");
+            let text = |v| to_json_string(v, &opts).unwrap();
+            assert_eq!(eq, text(&a) == text(&b), "{a:?} {b:?}");
+        }
+    }
+
+    #[test]
+    fn policies() {
+        let opts = JsonOptions {
+            non_finite: NonFinite::String,
+            ..JsonOptions::default()
+        };
+        assert_eq!(
+            r#"["-Infinity","a�"]"#,
+            json(r#"{-math.huge, "a\xff"}"#, &opts)
+        );
+        let opts = JsonOptions {
+            non_finite: NonFinite::Error,
+            ..JsonOptions::default()
+        };
+        assert!(to_json_string(&Value::Float(f64::NAN), &opts).is_err());
+    }
+
+    #[test]
+    fn floats() {
+        let input = "{5, -0.5, 0.125, 1e20, -0, 0/0}";
+        let with = |floats| {
+            let opts = JsonOptions {
+                floats,
+                ..JsonOptions::default()
+            };
+            json(input, &opts)
+        };
+        assert_eq!("[5.0,-0.5,0.125,1e20,-0.0,null]", with(Floats::Shortest));
+        assert_eq!("[5,-0.5,0.125,1e20,0,null]", with(Floats::Integers));
+        assert_eq!(
+            "[5.00,-0.50,0.12,100000000000000000000.00,-0.00,null]",
+            with(Floats::Fixed(2))
+        );
+        assert_eq!(
+            "[5,0,0,100000000000000000000,0,null]",
+            with(Floats::Fixed(0))
+        );
+        // numbers kept as written aren't changed
+        let opts = JsonOptions {
+            floats: Floats::Integers,
+            ..JsonOptions::default()
+        };
+        let v = crate::parse::parse_with(
+            "{5.0}",
+            &crate::parse::ParseOptions {
+                raw_numbers: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!("[5.0]", to_json_string(&Value::Object(v), &opts).unwrap());
+    }
+
+    #[test]
+    fn arrays() {
+        let opts = JsonOptions::default();
+        assert_eq!(
+            r#"{"2":"b","1":"a"}"#,
+            json(r#"{[2] = "b", [1] = "a"}"#, &opts)
+        );
+        let opts = JsonOptions {
+            arrays: ArrayPolicy::IntegerKeys,
+            ..JsonOptions::default()
+        };
+        assert_eq!(r#"["a","b"]"#, json(r#"{[2] = "b", [1] = "a"}"#, &opts));
+        assert_eq!(r#"["a","b",[]]"#, json(r#"{"a", [3] = {}, "b"}"#, &opts));
+        for object in [
+            r#"{[1] = "a", [3] = "c"}"#,
+            r#"{[0] = "a"}"#,
+            r#"{[01] = "a"}"#,
+            r#"{[1] = "a", x = "b"}"#,
+            r#"{["1"] = "a", ["2"] = "b"}"#,
+        ] {
+            assert!(json(object, &opts).starts_with('{'), "{object}");
+        }
+        assert_eq!(r#"["a"]"#, json(r#"{"a", [1] = "b"}"#, &opts));
+
+        let holes = r#"{[1] = "a", [3] = "c", [5] = "e"}"#;
+        let opts = JsonOptions {
+            arrays: ArrayPolicy::IntegerKeys,
+            holes: Holes::Null,
+            ..JsonOptions::default()
+        };
+        assert_eq!(r#"["a",null,"c",null,"e"]"#, json(holes, &opts));
+        // too sparse to be an array
+        assert_eq!(
+            r#"{"99999999999999":1.0}"#,
+            json("{[99999999999999] = 1}", &opts)
+        );
+        assert_eq!(
+            r#"{"1":"a","9":"b"}"#,
+            json(r#"{[1] = "a", [9] = "b"}"#, &opts)
+        );
+        assert_eq!(r#"[null,"b"]"#, json(r#"{[2] = "b"}"#, &opts));
+        let opts = JsonOptions {
+            holes: Holes::Error,
+            ..opts
+        };
+        let e = to_json_string(&Value::Object(parse(holes).unwrap()), &opts).unwrap_err();
+        assert_eq!(
+            "table has entries up to [5], but none at [2]",
+            e.to_string()
+        );
+        assert_eq!(r#"["a","b"]"#, json(r#"{"a", [2] = "b"}"#, &opts));
+        let opts = JsonOptions {
+            arrays: ArrayPolicy::AlwaysObject,
+            ..JsonOptions::default()
+        };
+        assert_eq!(r#"{"0":"a","1":{}}"#, json(r#"{"a", {}}"#, &opts));
+    }
+
+    #[cfg(feature = "json-value")]
+    #[test]
+    fn json_values() {
+        let v = Value::Object(parse(r#"{a = {1, "x"}, b = {c = true}, 1.5}"#).unwrap());
+        let json = serde_json::Value::from(&v);
+        assert_eq!(
+            serde_json::json!({"a": [1.0, "x"], "b": {"c": true}, "2": 1.5}),
+            json
+        );
+        let back = Value::try_from(&serde_json::json!({"a": [1, "x"], "n": null})).unwrap();
+        assert_eq!(Value::Object(parse(r#"{a = {1, "x"}}"#).unwrap()), back);
+        assert!(Value::try_from(&serde_json::json!([null])).is_err());
+    }
+}
@@ -0,0 +1,253 @@
+//! Reading relaxed, hand-edited JSON in the style of JSON5, for converting it to Lua.
+//!
+//! ```
+//! use lua2json::format::{to_lua, FormatOptions, Indent};
+//! use lua2json::json5::from_json5;
+//!
+//! let v = from_json5("{name: 'a', // the name\n tags: [1, 2,],}").unwrap();
+//! let opts = FormatOptions {
+//!     indent: Indent::None,
+//!     ..FormatOptions::default()
+//! };
+//! assert_eq!(r#"{name = "a", tags = {1, 2}}"#, to_lua(&v, &opts));
+//! ```
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::parse::Value;
+
+/// Read `text` like [`Value`]'s `Deserialize` does from JSON, also accepting `//` and `/* */`
+/// comments, trailing commas, keys which are names rather than strings, single quoted
+/// strings, lines continued with `\` inside strings, hexadecimal integers, and numbers with a
+/// `+` sign or with nothing before or after a decimal point, like `.5` or `5.`.
+///
+/// `Infinity` and `NaN` aren't accepted, as they can't be passed through JSON.
+pub fn from_json5(text: &str) -> Result<Value> {
+    let json = to_json(text)?;
+    serde_json::from_str(&json).context("parsing JSON5")
+}
+
+/// Rewrite `text` as strict JSON, for `serde_json`.
+pub fn to_json(text: &str) -> Result<String> {
+    let bytes = text.as_bytes();
+    let mut out = String::with_capacity(text.len());
+    let mut comma = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c.is_ascii_whitespace() {
+            out.push(c as char);
+            i += 1;
+            continue;
+        }
+        if b'/' == c {
+            i = comment(text, i)?;
+            out.push(' ');
+            continue;
+        }
+        // a comma is only written once it's known not to be trailing
+        if std::mem::take(&mut comma) && !matches!(c, b'}' | b']') {
+            out.push(',');
+        }
+        match c {
+            b',' => {
+                comma = true;
+                i += 1;
+            }
+            b'"' | b'\'' => i = string(text, i, &mut out)?,
+            b'+' | b'-' | b'.' | b'0'..=b'9' => i = number(text, i, &mut out)?,
+            c if c.is_ascii_alphabetic() || b'_' == c || b'$' == c => {
+                let len = text[i..]
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || '_' == c || '$' == c))
+                    .unwrap_or(text.len() - i);
+                let name = &text[i..i + len];
+                i += len;
+                match name {
+                    "true" | "false" | "null" => out.push_str(name),
+                    _ if b':' == bytes[skip(text, i)?..].first().copied().unwrap_or(0) => {
+                        out.push('"');
+                        out.push_str(name);
+                        out.push('"');
+                    }
+                    "Infinity" | "NaN" => bail!("{name} at {} has no JSON equivalent", i - len),
+                    _ => bail!("unexpected {name:?} at {}", i - len),
+                }
+            }
+            _ => {
+                let c = text[i..].chars().next().expect("not at the end");
+                out.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// The index after the comment at `at`.
+fn comment(text: &str, at: usize) -> Result<usize> {
+    let rest = &text[at..];
+    if rest.starts_with("//") {
+        Ok(rest.find('\n').map_or(text.len(), |end| at + end))
+    } else if let Some(body) = rest.strip_prefix("/*") {
+        let end = body
+            .find("*/")
+            .ok_or_else(|| anyhow!("unterminated comment at {at}"))?;
+        Ok(at + 2 + end + 2)
+    } else {
+        bail!("unexpected '/' at {at}")
+    }
+}
+
+/// The index of the next character after `at` which isn't whitespace or in a comment.
+fn skip(text: &str, mut at: usize) -> Result<usize> {
+    let bytes = text.as_bytes();
+    while let Some(&c) = bytes.get(at) {
+        match c {
+            b'/' => at = comment(text, at)?,
+            c if c.is_ascii_whitespace() => at += 1,
+            _ => break,
+        }
+    }
+    Ok(at)
+}
+
+/// Write the string at `at` with double quotes, returning the index after it.
+fn string(text: &str, at: usize, out: &mut String) -> Result<usize> {
+    let quote = text[at..].chars().next().expect("a quote");
+    out.push('"');
+    let mut chars = text[at + 1..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, '\n')) => {}
+                Some((j, '\r')) => {
+                    if text[at + 1 + j + 1..].starts_with('\n') {
+                        chars.next();
+                    }
+                }
+                Some((_, '\'')) => out.push('\''),
+                Some((j, 'x')) => {
+                    let start = at + 1 + j + 1;
+                    let digits = text
+                        .get(start..start + 2)
+                        .filter(|d| d.bytes().all(|c| c.is_ascii_hexdigit()))
+                        .ok_or_else(|| anyhow!("invalid \\x escape at {}", start - 2))?;
+                    out.push_str("\\u00");
+                    out.push_str(digits);
+                    chars.next();
+                    chars.next();
+                }
+                Some((_, c)) => {
+                    out.push('\\');
+                    out.push(c);
+                }
+                None => break,
+            },
+            '"' if '"' != quote => out.push_str("\\\""),
+            c if c == quote => {
+                out.push('"');
+                return Ok(at + 1 + i + 1);
+            }
+            c => out.push(c),
+        }
+    }
+    bail!("unterminated string at {at}")
+}
+
+/// Write the number at `at` as JSON spells it, returning the index after it.
+fn number(text: &str, at: usize, out: &mut String) -> Result<usize> {
+    let mut i = at;
+    let bytes = text.as_bytes();
+    let negative = b'-' == bytes[i];
+    if matches!(bytes[i], b'+' | b'-') {
+        i += 1;
+    }
+    let rest = &text[i..];
+    if let Some(hex) = rest.strip_prefix("0x").or_else(|| rest.strip_prefix("0X")) {
+        let len = hex
+            .find(|c: char| !c.is_ascii_hexdigit())
+            .unwrap_or(hex.len());
+        let n = u128::from_str_radix(&hex[..len], 16)
+            .map_err(|_| anyhow!("invalid hexadecimal number at {at}"))?;
+        if negative {
+            out.push('-');
+        }
+        out.push_str(&n.to_string());
+        return Ok(i + 2 + len);
+    }
+    if rest.starts_with("Infinity") || rest.starts_with("NaN") {
+        bail!("non-finite number at {at} has no JSON equivalent");
+    }
+    let mut len = 0;
+    while let Some(&c) = rest.as_bytes().get(len) {
+        let sign =
+            matches!(c, b'+' | b'-') && len > 0 && matches!(rest.as_bytes()[len - 1], b'e' | b'E');
+        if !(c.is_ascii_digit() || matches!(c, b'.' | b'e' | b'E') || sign) {
+            break;
+        }
+        len += 1;
+    }
+    let digits = &rest[..len];
+    if negative {
+        out.push('-');
+    }
+    if digits.starts_with('.') {
+        out.push('0');
+    }
+    match digits.split_once('.') {
+        Some((whole, fraction)) if fraction.is_empty() || fraction.starts_with(['e', 'E']) => {
+            out.push_str(whole);
+            out.push_str(".0");
+            out.push_str(fraction);
+        }
+        _ => out.push_str(digits),
+    }
+    Ok(i + len)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json5::{from_json5, to_json};
+    use crate::parse::{parse, Value};
+
+    #[test]
+    fn relaxed() {
+        let text = r#"// settings
+        {
+            name: 'it\'s "here"', /* inline */ $id: 0x1F,
+            ratios: [.5, 5., +1, -2.5e+3, 1E2,],
+            long: 'a\
+b', esc: "\x41\n",
+        }"#;
+        assert_eq!(
+            Value::Object(
+                parse(
+                    r#"{name = "it's \"here\"", ["$id"] = 31, ratios = {0.5, 5, 1, -2500, 100},
+                    long = "ab", esc = "A\n"}"#
+                )
+                .unwrap()
+            ),
+            from_json5(text).unwrap()
+        );
+        assert_eq!(r#"{"a":[1 ,2 ]}"#, to_json("{a:[1 ,2 ,]}").unwrap());
+        // names and commas inside strings are left alone
+        assert_eq!(r#"["x: y,]"]"#, to_json(r#"["x: y,]"]"#).unwrap());
+    }
+
+    #[test]
+    fn errors() {
+        let message = |s| to_json(s).unwrap_err().to_string();
+        assert_eq!("unexpected \"b\" at 4", message("{a: b}"));
+        assert_eq!(
+            "Infinity at 4 has no JSON equivalent",
+            message("{a: Infinity}")
+        );
+        assert_eq!(
+            "non-finite number at 4 has no JSON equivalent",
+            message("{a: -Infinity}")
+        );
+        assert_eq!("unterminated comment at 1", message("[/* 1]"));
+        assert_eq!("unterminated string at 1", message("['a]"));
+        assert!(from_json5("{a: [1, null]}").is_err());
+    }
+}
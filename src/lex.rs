@@ -0,0 +1,252 @@
+//! Splitting Lua into tokens with their spans, for syntax highlighting.
+//!
+//! This is more forgiving than the parser: it covers all of Lua's tokens, including comments
+//! and operators, and never fails, so a document can be highlighted while it's being edited.
+//!
+//! ```
+//! use lua2json::lex::{tokens, TokenKind};
+//!
+//! let input = "{hp = 10} -- max";
+//! let kinds = tokens(input).map(|t| (t.kind, &input[t.span])).collect::<Vec<_>>();
+//! assert_eq!(
+//!     vec![
+//!         (TokenKind::Punctuation, "{"),
+//!         (TokenKind::Key, "hp"),
+//!         (TokenKind::Punctuation, "="),
+//!         (TokenKind::Number, "10"),
+//!         (TokenKind::Punctuation, "}"),
+//!         (TokenKind::Comment, "-- max"),
+//!     ],
+//!     kinds
+//! );
+//! ```
+
+use core::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// Brackets, separators and operators, like `{`, `,`, `=` or `..`.
+    Punctuation,
+    /// A string, including its quotes: `"..."`, `'...'` or `[[...]]`.
+    String,
+    /// A number, like `10`, `1.5e3` or `0xff`, without any sign, which is punctuation.
+    Number,
+    /// A name which names a table entry, as it's followed by `=`.
+    Key,
+    /// Any other name, like `true`, `nil` or a variable.
+    Name,
+    /// A `--` comment, to the end of the line, or `--[[...]]`.
+    Comment,
+    /// Something which isn't a token, like a stray character or an unterminated string.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// The byte range of the token in the input.
+    pub span: Range<usize>,
+}
+
+/// The tokens of `input`, in order, skipping whitespace.
+pub fn tokens(input: &str) -> Tokens<'_> {
+    Tokens { input, pos: 0 }
+}
+
+/// The iterator returned by [`tokens`].
+#[derive(Debug, Clone)]
+pub struct Tokens<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        let bytes = self.input.as_bytes();
+        while bytes.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+            self.pos += 1;
+        }
+        let start = self.pos;
+        let rest = &self.input[start..];
+        let c = rest.chars().next()?;
+        let (kind, len) = match c {
+            '-' if rest.starts_with("--") => comment(rest),
+            '"' | '\'' => quoted(rest, c),
+            '[' if long_bracket(rest).is_some() => {
+                match long_bracket(rest).and_then(|level| close_long_bracket(rest, level)) {
+                    Some(len) => (TokenKind::String, len),
+                    None => (TokenKind::Error, rest.len()),
+                }
+            }
+            '0'..='9' => (TokenKind::Number, number(rest)),
+            '.' if rest[1..].starts_with(|c: char| c.is_ascii_digit()) => {
+                (TokenKind::Number, number(rest))
+            }
+            c if c.is_alphabetic() || '_' == c => {
+                let len = rest
+                    .find(|c: char| !(c.is_alphanumeric() || '_' == c))
+                    .unwrap_or(rest.len());
+                let after = rest[len..].trim_start();
+                let key = after.starts_with('=') && !after.starts_with("==");
+                (if key { TokenKind::Key } else { TokenKind::Name }, len)
+            }
+            _ => match punctuation(rest) {
+                Some(len) => (TokenKind::Punctuation, len),
+                None => (TokenKind::Error, c.len_utf8()),
+            },
+        };
+        self.pos += len;
+        Some(Token {
+            kind,
+            span: start..self.pos,
+        })
+    }
+}
+
+/// The level of the long bracket, like `[==[`, which `s` starts with, if it does.
+fn long_bracket(s: &str) -> Option<usize> {
+    let level = s[1..].bytes().take_while(|&c| b'=' == c).count();
+    (Some(&b'[') == s.as_bytes().get(1 + level)).then_some(level)
+}
+
+/// The length of `s` up to the end of the long bracket of `level` which it starts with.
+fn close_long_bracket(s: &str, level: usize) -> Option<usize> {
+    let open = level + 2;
+    let close = ["]", &"=".repeat(level), "]"].concat();
+    s[open..].find(&close).map(|at| open + at + close.len())
+}
+
+/// The comment which `s` starts with, which is an error if it's a long one which isn't closed.
+pub(crate) fn comment(s: &str) -> (TokenKind, usize) {
+    let body = &s[2..];
+    if body.starts_with('[') {
+        if let Some(level) = long_bracket(body) {
+            return match close_long_bracket(body, level) {
+                Some(len) => (TokenKind::Comment, 2 + len),
+                None => (TokenKind::Error, s.len()),
+            };
+        }
+    }
+    (TokenKind::Comment, s.find('\n').unwrap_or(s.len()))
+}
+
+fn quoted(s: &str, quote: char) -> (TokenKind, usize) {
+    let mut chars = s.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '\n' => return (TokenKind::Error, i),
+            c if c == quote => return (TokenKind::String, i + 1),
+            _ => {}
+        }
+    }
+    (TokenKind::Error, s.len())
+}
+
+fn number(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let hex = s.starts_with("0x") || s.starts_with("0X");
+    let mut i = if hex { 2 } else { 0 };
+    while let Some(&c) = bytes.get(i) {
+        let exponent = if hex { b"pP" } else { b"eE" };
+        if exponent.contains(&c) && bytes.get(i + 1).is_some_and(|&d| b'-' == d || b'+' == d) {
+            i += 2;
+        } else if c.is_ascii_alphanumeric() || b'.' == c && bytes.get(i + 1) != Some(&b'.') {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    i
+}
+
+fn punctuation(s: &str) -> Option<usize> {
+    const LONG: [&str; 9] = ["...", "..", "==", "~=", "<=", ">=", "//", "::", "<<"];
+    if let Some(op) = LONG.iter().find(|op| s.starts_with(**op)) {
+        return Some(op.len());
+    }
+    if s.starts_with(">>") {
+        return Some(2);
+    }
+    s.starts_with(|c| "{}[]()=,;:.+-*/%^#<>&|~".contains(c))
+        .then_some(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lex::{tokens, TokenKind};
+
+    fn lex(input: &str) -> Vec<(TokenKind, &str)> {
+        tokens(input).map(|t| (t.kind, &input[t.span])).collect()
+    }
+
+    #[test]
+    fn kinds() {
+        use TokenKind::*;
+
+        let input = r#"--[==[ header ]==]
+            {["a b"] = 'it\'s', n = -1.5e-3, h = 0xff, [[long]], on = f(x) .. "y" ~= nil}"#;
+        assert_eq!(
+            vec![
+                (Comment, "--[==[ header ]==]"),
+                (Punctuation, "{"),
+                (Punctuation, "["),
+                (String, r#""a b""#),
+                (Punctuation, "]"),
+                (Punctuation, "="),
+                (String, r"'it\'s'"),
+                (Punctuation, ","),
+                (Key, "n"),
+                (Punctuation, "="),
+                (Punctuation, "-"),
+                (Number, "1.5e-3"),
+                (Punctuation, ","),
+                (Key, "h"),
+                (Punctuation, "="),
+                (Number, "0xff"),
+                (Punctuation, ","),
+                (String, "[[long]]"),
+                (Punctuation, ","),
+                (Key, "on"),
+                (Punctuation, "="),
+                (Name, "f"),
+                (Punctuation, "("),
+                (Name, "x"),
+                (Punctuation, ")"),
+                (Punctuation, ".."),
+                (String, r#""y""#),
+                (Punctuation, "~="),
+                (Name, "nil"),
+                (Punctuation, "}"),
+            ],
+            lex(input)
+        );
+    }
+
+    #[test]
+    fn errors() {
+        use TokenKind::*;
+
+        assert_eq!(
+            vec![(Key, "größe"), (Punctuation, "="), (Number, "1")],
+            lex("größe = 1")
+        );
+        assert_eq!(
+            vec![(Name, "a"), (Punctuation, "=="), (Number, "1")],
+            lex("a == 1")
+        );
+        assert_eq!(
+            vec![(Number, "1"), (Punctuation, ".."), (Number, "2")],
+            lex("1..2")
+        );
+        assert_eq!(vec![(Error, "\"abc"), (Name, "d")], lex("\"abc\nd"));
+        assert_eq!(vec![(Error, "[[open")], lex("[[open"));
+        assert_eq!(vec![(Error, "@"), (Error, "€")], lex("@ €"));
+        assert_eq!(vec![(Comment, "-- x"), (Name, "y")], lex("-- x\ny"));
+        assert!(lex("").is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+// for `lua_value!` to use without `std`
+pub use alloc::string::{String, ToString};
+pub use alloc::vec;
+
+use crate::parse::Value;
+
+/// Construct a [`Value`](crate::parse::Value) with Lua table constructor syntax.
+///
+/// ```
+/// use lua2json::lua_value;
+///
+/// let name = "app";
+/// let value = lua_value!({
+///     name = name,
+///     ["max users"] = 10,
+///     tags = {"a", "b"},
+///     ratio = -1.5;
+///     true,
+/// });
+/// assert_eq!(Some("app"), value.get_opt("name").and_then(|v| v.as_str()));
+/// ```
+///
+/// Entries are `key = value`, `["key"] = value` or positional, separated by commas or
+/// semicolons. Values are nested tables or Rust expressions of strings, numbers, booleans or
+/// `Value`s.
+#[macro_export]
+macro_rules! lua_value {
+    (@table [$($out:expr,)*]) => {
+        $crate::macros::vec![$($out,)*]
+    };
+    (@table [$($out:expr,)*] $key:ident = $($rest:tt)*) => {
+        $crate::lua_value!(@value [$($out,)*] ($crate::macros::String::from(stringify!($key))) () $($rest)*)
+    };
+    (@table [$($out:expr,)*] [$key:expr] = $($rest:tt)*) => {
+        $crate::lua_value!(@value [$($out,)*] ($crate::macros::ToString::to_string(&$key)) () $($rest)*)
+    };
+    (@table [$($out:expr,)*] $($rest:tt)+) => {
+        $crate::lua_value!(@value [$($out,)*] () () $($rest)+)
+    };
+
+    // munch the tokens of a value up to the next separator
+    (@value [$($out:expr,)*] ($($key:expr)?) ($($v:tt)+) $(, $($rest:tt)*)?) => {
+        $crate::lua_value!(@table [$($out,)* $crate::lua_value!(@entry ($($key)?) ($($v)+)),] $($($rest)*)?)
+    };
+    (@value [$($out:expr,)*] ($($key:expr)?) ($($v:tt)+) ; $($rest:tt)*) => {
+        $crate::lua_value!(@table [$($out,)* $crate::lua_value!(@entry ($($key)?) ($($v)+)),] $($rest)*)
+    };
+    (@value [$($out:expr,)*] ($($key:expr)?) ($($v:tt)*) $next:tt $($rest:tt)*) => {
+        $crate::lua_value!(@value [$($out,)*] ($($key)?) ($($v)* $next) $($rest)*)
+    };
+
+    (@entry () ($($v:tt)+)) => {
+        (::core::option::Option::None, $crate::lua_value!($($v)+))
+    };
+    (@entry ($key:expr) ($($v:tt)+)) => {
+        (::core::option::Option::Some($crate::parse::Key::Name($key)), $crate::lua_value!($($v)+))
+    };
+
+    ({ $($tt:tt)* }) => {
+        $crate::parse::Value::Object($crate::lua_value!(@table [] $($tt)*))
+    };
+    ($e:expr) => {
+        $crate::macros::Leaf::into_value($e)
+    };
+}
+
+/// The Rust types [`lua_value!`] takes as values.
+#[doc(hidden)]
+pub trait Leaf {
+    fn into_value(self) -> Value;
+}
+
+impl Leaf for Value {
+    fn into_value(self) -> Value {
+        self
+    }
+}
+
+impl Leaf for bool {
+    fn into_value(self) -> Value {
+        Value::Bool(self)
+    }
+}
+
+impl Leaf for &str {
+    fn into_value(self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+impl Leaf for String {
+    fn into_value(self) -> Value {
+        Value::String(self)
+    }
+}
+
+macro_rules! number_leaf {
+    ($($t:ty),*) => {
+        $(impl Leaf for $t {
+            fn into_value(self) -> Value {
+                Value::Float(self as f64)
+            }
+        })*
+    };
+}
+
+number_leaf!(f64, f32, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{parse, Value};
+
+    #[test]
+    fn construction() {
+        let hp = 10;
+        assert_eq!(
+            Value::Object(
+                parse(r#"{a = 5, b = {"x", true}, ["c d"] = -1.5, {}, hp = 10, s = "y"}"#).unwrap()
+            ),
+            lua_value!({a = 5, b = {"x", true}, ["c d"] = -1.5; {}, hp = hp, s = String::from("y"),})
+        );
+        assert_eq!(Value::Object(Vec::new()), lua_value!({}));
+        assert_eq!(Value::Float(2.), lua_value!(1 + 1));
+        assert_eq!(
+            Value::Object(parse("{{1, {}}, {{2}}}").unwrap()),
+            lua_value!({{1, {}}, {{lua_value!(2)}}})
+        );
+    }
+}
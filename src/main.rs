@@ -1,55 +1,934 @@
-mod parse;
-
 use std::fs;
-use std::io::{BufRead, Write};
+use std::io::{BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use lua2json::cst::Document;
+use lua2json::csv::{to_csv, CsvOptions};
+use lua2json::diff::{diff_within, Change};
+use lua2json::dot::{to_dot_with, DotOptions};
+use lua2json::encoding::{decode, Encoding};
+use lua2json::error::{line_column, Lua2JsonError, Warning};
+use lua2json::format::{to_lua, FormatOptions, Indent, KeyOrder, Quote};
+use lua2json::json::{
+    to_json_string, write_json, ArrayPolicy, Floats, Holes, JsonOptions, NonFinite, NonUtf8,
+};
+use lua2json::json5::from_json5;
+use lua2json::parallel::{parse_multi_parallel, parse_parallel_with_warnings};
+use lua2json::parse;
+use lua2json::query::Query;
+use lua2json::redact::{RedactMode, Redaction};
+use lua2json::schema::{infer_schema, validate, Schema};
+use lua2json::sort::SortBy;
+use lua2json::stats::Stats;
+use lua2json::stream::{transcode, TranscodeOptions};
+use lua2json::toml::to_toml;
+use lua2json::transform::{Case, DropKeys, Rename, Transform};
+use lua2json::xml::{to_xml, XmlOptions};
+use notify::{EventKind, RecursiveMode, Watcher};
+use rayon::prelude::*;
+
+/// Options for the default mode, which converts each line of stdin.
+struct LineOptions {
+    /// Print each value selected by this from each line, instead of the whole line.
+    query: Option<Query>,
+    parse: parse::ParseOptions,
+    /// Applied to the names of entries after parsing, in order.
+    keys: Vec<Box<dyn Transform + Send + Sync>>,
+    /// Applied after the keys are transformed.
+    redaction: Option<Redaction>,
+    /// Applied after redaction.
+    sort: Option<SortBy>,
+    /// Applied after sorting, so the first elements of each array are kept.
+    limit: Option<usize>,
+    json: JsonOptions,
+    encoding: Encoding,
+    format: OutputFormat,
+    /// Stream stdin as one document, writing each entry of its root array on its own line.
+    ndjson: bool,
+    /// Read stdin as a sequence of documents, written out together.
+    multi: Option<Multi>,
+    /// Write the [`Stats`] of each table as JSON, instead of the table.
+    stats: bool,
+    /// Parse each table, or the documents of `multi`, in parts on every core.
+    parallel: bool,
+}
+
+/// How `--multi` writes out the documents it reads.
+#[derive(Clone, Copy)]
+enum Multi {
+    /// By name, as an object.
+    Object,
+    /// In order, as an array, with the names dropped.
+    Array,
+}
+
+#[derive(Default, Clone, Copy)]
+enum OutputFormat {
+    #[default]
+    Json,
+    Toml,
+    Xml,
+    #[cfg(feature = "msgpack")]
+    MsgPack,
+}
+
+impl OutputFormat {
+    const ALL: &'static [OutputFormat] = &[
+        OutputFormat::Json,
+        OutputFormat::Toml,
+        OutputFormat::Xml,
+        #[cfg(feature = "msgpack")]
+        OutputFormat::MsgPack,
+    ];
+
+    /// The format named `name` for `--format`, which is its extension.
+    fn from_name(name: &str) -> Result<OutputFormat> {
+        if let Some(format) = Self::ALL.iter().find(|f| name == f.extension()) {
+            return Ok(*format);
+        }
+        let names = Self::ALL.iter().map(|f| f.extension()).collect::<Vec<_>>();
+        let (last, rest) = names.split_last().expect("formats");
+        bail!(
+            "--format must be {} or {last}, not {name:?}",
+            rest.join(", ")
+        )
+    }
+
+    /// The file extension for documents in this format.
+    fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Json => "json",
+            OutputFormat::Toml => "toml",
+            OutputFormat::Xml => "xml",
+            #[cfg(feature = "msgpack")]
+            OutputFormat::MsgPack => "msgpack",
+        }
+    }
+}
+
+impl Default for LineOptions {
+    fn default() -> Self {
+        LineOptions {
+            query: None,
+            parse: parse::ParseOptions::default(),
+            keys: Vec::new(),
+            redaction: None,
+            sort: None,
+            limit: None,
+            json: JsonOptions {
+                trailing_newline: true,
+                ..JsonOptions::default()
+            },
+            encoding: Encoding::default(),
+            format: OutputFormat::default(),
+            ndjson: false,
+            multi: None,
+            stats: false,
+            parallel: false,
+        }
+    }
+}
+
+impl LineOptions {
+    fn from_args(args: &[String]) -> Result<LineOptions> {
+        let mut opts = LineOptions::default();
+        let mut redact = Vec::new();
+        let mut redact_mode = RedactMode::default();
+        let mut sort_by = None;
+        let mut descending = false;
+        let mut holes = false;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| anyhow!("{arg} requires a value"));
+            match arg.as_str() {
+                "--query" => opts.query = Some(value()?.parse()?),
+                "--raw-numbers" => opts.parse.raw_numbers = true,
+                "--arithmetic" => opts.parse.arithmetic = true,
+                "--lenient" => opts.parse.lenient = true,
+                "--unicode-names" => opts.parse.unicode_names = true,
+                "--lowercase-keys" => opts.parse.lowercase_keys = true,
+                "--intern-keys" => opts.parse.intern_keys = true,
+                "--only" => opts.parse.only.push(value()?.clone()),
+                "--drop-keys" => opts.keys.push(Box::new(DropKeys(value()?.parse()?))),
+                "--rename" => {
+                    let (old, new) = value()?
+                        .split_once('=')
+                        .ok_or_else(|| anyhow!("--rename requires OLD=NEW"))?;
+                    let map = [(old.to_string(), new.to_string())].into();
+                    opts.keys.push(Box::new(Rename(map)));
+                }
+                "--key-case" => opts.keys.push(Box::new(match value()?.as_str() {
+                    "camel" => Case::Camel,
+                    "snake" => Case::Snake,
+                    other => bail!("--key-case must be camel or snake, not {other:?}"),
+                })),
+                "--redact" => redact.push(value()?.clone()),
+                "--redact-mode" => {
+                    redact_mode = match value()?.as_str() {
+                        "replace" => RedactMode::Replace,
+                        "remove" => RedactMode::Remove,
+                        other => bail!("--redact-mode must be replace or remove, not {other:?}"),
+                    }
+                }
+                "--sort-by" => sort_by = Some(value()?.clone()),
+                "--desc" => descending = true,
+                "--limit" => opts.limit = Some(value()?.parse()?),
+                "--ndjson" => opts.ndjson = true,
+                "--stats" => opts.stats = true,
+                "--parallel" => opts.parallel = true,
+                "--multi" => {
+                    opts.multi = Some(match value()?.as_str() {
+                        "object" => Multi::Object,
+                        "array" => Multi::Array,
+                        other => bail!("--multi must be object or array, not {other:?}"),
+                    })
+                }
+                "--encoding" => opts.encoding = value()?.parse()?,
+                "--pretty" => opts.json.indent = Indent::Spaces(2),
+                "--indent" => {
+                    opts.json.indent = match value()?.parse()? {
+                        0 => Indent::None,
+                        n => Indent::Spaces(n),
+                    }
+                }
+                "--tabs" => opts.json.indent = Indent::Tab,
+                "--sort-keys" => opts.json.key_order = KeyOrder::Sorted,
+                "--natural-sort-keys" => opts.json.key_order = KeyOrder::Natural,
+                "--no-trailing-newline" => opts.json.trailing_newline = false,
+                "--canonical" => opts.json.canonical = true,
+                "--format" => opts.format = OutputFormat::from_name(value()?)?,
+                "--arrays" => {
+                    opts.json.arrays = match value()?.as_str() {
+                        "auto" => ArrayPolicy::Auto,
+                        "object" => ArrayPolicy::AlwaysObject,
+                        "integer-keys" => ArrayPolicy::IntegerKeys,
+                        other => {
+                            bail!("--arrays must be auto, object or integer-keys, not {other:?}")
+                        }
+                    }
+                }
+                "--holes" => {
+                    holes = true;
+                    opts.json.holes = match value()?.as_str() {
+                        "object" => Holes::Object,
+                        "null" => Holes::Null,
+                        "error" => Holes::Error,
+                        other => bail!("--holes must be object, null or error, not {other:?}"),
+                    }
+                }
+                "--non-finite" => {
+                    opts.json.non_finite = match value()?.as_str() {
+                        "null" => NonFinite::Null,
+                        "string" => NonFinite::String,
+                        "error" => NonFinite::Error,
+                        other => bail!("--non-finite must be null, string or error, not {other:?}"),
+                    }
+                }
+                "--floats" => {
+                    opts.json.floats = match value()?.as_str() {
+                        "shortest" => Floats::Shortest,
+                        "integers" => Floats::Integers,
+                        places => match places.parse() {
+                            Ok(places) => Floats::Fixed(places),
+                            Err(_) => bail!(
+                                "--floats must be shortest, integers or a number of decimal places, not {places:?}"
+                            ),
+                        },
+                    }
+                }
+                "--non-utf8" => {
+                    opts.json.non_utf8 = match value()?.as_str() {
+                        "lossy" => NonUtf8::Lossy,
+                        "base64" => NonUtf8::Base64,
+                        "error" => NonUtf8::Error,
+                        other => bail!("--non-utf8 must be lossy, base64 or error, not {other:?}"),
+                    }
+                }
+                other => bail!("unrecognised argument: {other:?}"),
+            }
+        }
+        if !redact.is_empty() {
+            opts.redaction = Some(Redaction::new(redact, redact_mode)?);
+        }
+        match sort_by {
+            Some(path) => opts.sort = Some(SortBy::new(&path, descending)?),
+            None if descending => bail!("--desc requires --sort-by"),
+            None => {}
+        }
+        if holes && ArrayPolicy::IntegerKeys != opts.json.arrays {
+            bail!("--holes requires --arrays integer-keys");
+        }
+        Ok(opts)
+    }
+
+    fn parse_table(&self, text: &str) -> lua2json::error::Result<(parse::Table, Vec<Warning>)> {
+        match self.parallel {
+            true => parse_parallel_with_warnings(text, &self.parse),
+            false => parse::parse_with_warnings(text, &self.parse),
+        }
+    }
+
+    fn parse_documents(&self, text: &str) -> lua2json::error::Result<parse::Table> {
+        match self.parallel {
+            true => parse_multi_parallel(text, &self.parse),
+            false => parse::parse_multi(text, &self.parse),
+        }
+    }
+
+    /// XML laid out like the JSON would be.
+    fn xml(&self) -> XmlOptions {
+        XmlOptions {
+            indent: self.json.indent,
+            ..XmlOptions::default()
+        }
+    }
 
-use anyhow::{anyhow, Context, Result};
-use serde_json as json;
-use serde_json::json;
+    /// Make the changes to a parsed table which are asked for before it's written.
+    fn prepare(&self, mut value: parse::Value) -> parse::Value {
+        value.transform_keys(&self.keys);
+        if let Some(redaction) = &self.redaction {
+            value.redact(redaction);
+        }
+        if let Some(sort) = &self.sort {
+            value.sort_arrays(sort);
+        }
+        if let Some(max) = self.limit {
+            value.limit_arrays(max);
+        }
+        value
+    }
+}
 
 fn main() -> Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.first().map(|s| s.as_str()) {
+        None => lines(&LineOptions::default()),
+        Some(flag) if flag.starts_with("--") => {
+            let opts = LineOptions::from_args(&args)?;
+            if opts.ndjson {
+                ndjson(&opts)
+            } else if let Some(multi) = opts.multi {
+                documents(multi, &opts)
+            } else {
+                lines(&opts)
+            }
+        }
+        Some("diff") => match &args[1..] {
+            [a, b] => diff_files(a, b, 0.),
+            [a, b, flag, epsilon] if "--epsilon" == flag => diff_files(a, b, epsilon.parse()?),
+            _ => bail!("usage: lua2json diff A.lua B.lua [--epsilon E]"),
+        },
+        Some("check") => check_files(&args[1..]),
+        Some("fmt") => fmt_file(&args[1..]),
+        Some("from-json") => from_json_file(&args[1..]),
+        Some("csv") => csv_file(&args[1..]),
+        Some("graph") => graph_file(&args[1..]),
+        Some("convert") => convert_dir(&args[1..]),
+        Some("watch") => watch(&args[1..]),
+        Some("schema") if args.len() > 1 => {
+            let samples = args[1..]
+                .iter()
+                .map(read_table)
+                .collect::<Result<Vec<_>>>()?;
+            let stdout = std::io::stdout();
+            let mut stdout = stdout.lock();
+            serde_json::to_writer_pretty(&mut stdout, &infer_schema(&samples))?;
+            stdout.write_all(b"\n")?;
+            Ok(())
+        }
+        Some("schema") => bail!("usage: lua2json schema SAMPLE.lua..."),
+        Some(other) => bail!("unrecognised argument: {other:?}"),
+    }
+}
+
+fn read_table(path: impl AsRef<Path>) -> Result<parse::Value> {
+    read_table_with(path, &parse::ParseOptions::default())
+}
+
+fn read_table_with(path: impl AsRef<Path>, opts: &parse::ParseOptions) -> Result<parse::Value> {
+    read_table_by(path, |text| parse::parse_with_warnings(text, opts))
+}
+
+/// Read a file's table with `parse`, printing any warnings.
+fn read_table_by(
+    path: impl AsRef<Path>,
+    parse: impl FnOnce(&str) -> lua2json::error::Result<(parse::Table, Vec<Warning>)>,
+) -> Result<parse::Value> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).with_context(|| anyhow!("reading {path:?}"))?;
+    let text = decode(&bytes, Encoding::Auto).with_context(|| anyhow!("decoding {path:?}"))?;
+    let (table, warnings) = parse(&text).with_context(|| anyhow!("parsing {path:?}"))?;
+    for warning in warnings {
+        eprintln!("warning: {path:?}: {warning}");
+    }
+    Ok(parse::Value::Object(table))
+}
+
+/// Convert every `.lua` file under a directory into the same place under another, in parallel,
+/// carrying on past files which fail and exiting with a failure status at the end if any did.
+fn convert_dir(args: &[String]) -> Result<()> {
+    let usage = "usage: lua2json convert DIR --out OUTDIR [--jobs N] [OPTIONS]";
+    let (dir, args) = match args.split_first() {
+        Some((dir, args)) if !dir.starts_with("--") => (Path::new(dir), args),
+        _ => bail!(usage),
+    };
+    let mut out = None;
+    let mut jobs = 0;
+    // everything else is passed on, to choose the output like in the default mode
+    let mut rest = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| anyhow!("{arg} requires a value"));
+        match arg.as_str() {
+            "--out" => out = Some(PathBuf::from(value()?)),
+            "--jobs" => jobs = value()?.parse()?,
+            _ => rest.push(arg.clone()),
+        }
+    }
+    let out = out.ok_or_else(|| anyhow!(usage))?;
+    let opts = LineOptions::from_args(&rest)?;
+    if opts.query.is_some() || opts.ndjson || opts.multi.is_some() || opts.stats {
+        bail!("convert doesn't support --query, --ndjson, --multi or --stats");
+    }
+
+    let mut files = Vec::new();
+    lua_files(dir, &mut files).with_context(|| anyhow!("listing {dir:?}"))?;
+    files.sort();
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build()?;
+    let results = pool.install(|| {
+        files
+            .par_iter()
+            .map(|file| {
+                let target = out
+                    .join(file.strip_prefix(dir).expect("under dir"))
+                    .with_extension(opts.format.extension());
+                convert_file(file, &target, &opts)
+            })
+            .collect::<Vec<_>>()
+    });
+
+    let mut failed = 0;
+    for e in results.into_iter().filter_map(Result::err) {
+        eprintln!("{e:#}");
+        failed += 1;
+    }
+    eprintln!(
+        "converted {} of {} files",
+        files.len() - failed,
+        files.len()
+    );
+    if failed > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Convert a file again whenever it changes, once it's been left alone for a moment, optionally
+/// running a command after each conversion.
+fn watch(args: &[String]) -> Result<()> {
+    let usage = "usage: lua2json watch INPUT.lua -o OUTPUT [--debounce MS] [--exec CMD] [OPTIONS]";
+    let (input, args) = match args.split_first() {
+        Some((input, args)) if !input.starts_with("--") => (Path::new(input), args),
+        _ => bail!(usage),
+    };
+    let mut output = None;
+    let mut debounce = Duration::from_millis(200);
+    let mut exec = None;
+    let mut rest = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| anyhow!("{arg} requires a value"));
+        match arg.as_str() {
+            "-o" | "--out" => output = Some(PathBuf::from(value()?)),
+            "--debounce" => debounce = Duration::from_millis(value()?.parse()?),
+            "--exec" => exec = Some(value()?.clone()),
+            _ => rest.push(arg.clone()),
+        }
+    }
+    let output = output.ok_or_else(|| anyhow!(usage))?;
+    let opts = LineOptions::from_args(&rest)?;
+    if opts.query.is_some() || opts.ndjson || opts.multi.is_some() || opts.stats {
+        bail!("watch doesn't support --query, --ndjson, --multi or --stats");
+    }
+
+    let regenerate = || {
+        if let Err(e) = convert_file(input, &output, &opts) {
+            eprintln!("{e:#}");
+            return;
+        }
+        eprintln!("wrote {}", output.display());
+        if let Some(exec) = &exec {
+            match hook(exec) {
+                Ok(status) if status.success() => {}
+                Ok(status) => eprintln!("{exec:?} failed: {status}"),
+                Err(e) => eprintln!("running {exec:?}: {e}"),
+            }
+        }
+    };
+
+    // editors often save by replacing the file, so watch the directory it's in
+    let dir = match input.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher
+        .watch(dir, RecursiveMode::NonRecursive)
+        .with_context(|| anyhow!("watching {dir:?}"))?;
+    let relevant = |event: &notify::Event| {
+        matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+            && event
+                .paths
+                .iter()
+                .any(|path| path.file_name() == input.file_name())
+    };
+
+    regenerate();
+    loop {
+        let event = rx.recv()??;
+        if !relevant(&event) {
+            continue;
+        }
+        // wait for the changes to stop
+        loop {
+            match rx.recv_timeout(debounce) {
+                Ok(event) => drop(event?),
+                Err(mpsc::RecvTimeoutError::Timeout) => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        regenerate();
+    }
+}
+
+/// Run a command with the shell.
+fn hook(command: &str) -> std::io::Result<ExitStatus> {
+    if cfg!(windows) {
+        Command::new("cmd").args(["/C", command]).status()
+    } else {
+        Command::new("sh").args(["-c", command]).status()
+    }
+}
+
+/// The `.lua` files under `dir`, recursively.
+fn lua_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            lua_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| "lua" == ext) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn convert_file(file: &Path, target: &Path, opts: &LineOptions) -> Result<()> {
+    let value = opts.prepare(read_table_by(file, |text| opts.parse_table(text))?);
+    let output = render(&value, opts).with_context(|| anyhow!("converting {file:?}"))?;
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).with_context(|| anyhow!("creating {parent:?}"))?;
+    }
+    fs::write(target, output).with_context(|| anyhow!("writing {target:?}"))
+}
+
+/// A whole document in the output format.
+fn render(value: &parse::Value, opts: &LineOptions) -> Result<Vec<u8>> {
+    match opts.format {
+        OutputFormat::Json => to_json_string(value, &opts.json).map(String::into_bytes),
+        OutputFormat::Toml => to_toml(value).map(String::into_bytes),
+        OutputFormat::Xml => to_xml(value, &opts.xml()).map(String::into_bytes),
+        #[cfg(feature = "msgpack")]
+        OutputFormat::MsgPack => Ok(lua2json::msgpack::to_msgpack(value)),
+    }
+}
+
+/// Read all of stdin as a sequence of documents, and write them out as one.
+fn documents(multi: Multi, opts: &LineOptions) -> Result<()> {
+    if opts.query.is_some() {
+        bail!("--multi can't be combined with --query");
+    }
+    let mut input = Vec::new();
+    std::io::stdin().lock().read_to_end(&mut input)?;
+    let input = decode(&input, opts.encoding).context("decoding stdin")?;
+    let documents = opts.parse_documents(&input).context("parsing stdin")?;
+    let documents = documents
+        .into_iter()
+        .map(|(name, document)| {
+            let name = match multi {
+                Multi::Object => name,
+                Multi::Array => None,
+            };
+            (name, opts.prepare(document))
+        })
+        .collect();
+    let documents = parse::Value::Object(documents);
+    let mut stdout = std::io::stdout().lock();
+    if opts.stats {
+        writeln!(stdout, "{}", stats_json(&documents.stats()))?;
+        return Ok(());
+    }
+    stdout.write_all(&render(&documents, opts)?)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Print the changes from `a` to `b`, exiting with a failure status if there are any.
+fn diff_files(a: &str, b: &str, epsilon: f64) -> Result<()> {
+    let changes = diff_within(&read_table(a)?, &read_table(b)?, epsilon);
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    for change in &changes {
+        let line = match change {
+            Change::Added { path, value } => {
+                format!("+ {path}: {}", compact(value)?)
+            }
+            Change::Removed { path, value } => {
+                format!("- {path}: {}", compact(value)?)
+            }
+            Change::Changed { path, old, new } => {
+                format!("~ {path}: {} -> {}", compact(old)?, compact(new)?)
+            }
+        };
+        writeln!(stdout, "{line}")?;
+    }
+    stdout.flush()?;
+    if !changes.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Parse files, and validate them against a schema if one is given, printing every problem
+/// with the line and column it's at, and exiting with a failure status if there were any.
+fn check_files(args: &[String]) -> Result<()> {
+    let usage = "usage: lua2json check [--schema SCHEMA.json] [OPTIONS] FILE.lua...";
+    let mut opts = parse::ParseOptions::default();
+    let mut schema = None;
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--schema" => {
+                let path = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--schema requires a value"))?;
+                let json = fs::read(path).with_context(|| anyhow!("reading {path:?}"))?;
+                let json =
+                    serde_json::from_slice(&json).with_context(|| anyhow!("parsing {path:?}"))?;
+                schema = Some(Schema::from_json(&json).with_context(|| anyhow!("in {path:?}"))?);
+            }
+            "--arithmetic" => opts.arithmetic = true,
+            "--lenient" => opts.lenient = true,
+            "--unicode-names" => opts.unicode_names = true,
+            flag if flag.starts_with("--") => bail!("unrecognised argument: {flag:?}"),
+            file => files.push(Path::new(file)),
+        }
+    }
+    if files.is_empty() {
+        bail!(usage);
+    }
+
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    let mut failed = 0;
+    for file in &files {
+        match check_file(file, &opts, schema.as_ref(), &mut stdout) {
+            Ok(true) => {}
+            Ok(false) => failed += 1,
+            Err(e) => {
+                writeln!(stdout, "{}: error: {e:#}", file.display())?;
+                failed += 1;
+            }
+        }
+    }
+    stdout.flush()?;
+    if failed > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Print the problems with one file, returning whether there weren't any which fail it.
+fn check_file(
+    file: &Path,
+    opts: &parse::ParseOptions,
+    schema: Option<&Schema>,
+    out: &mut impl Write,
+) -> Result<bool> {
+    let bytes = fs::read(file).context("reading")?;
+    let text = decode(&bytes, Encoding::Auto).context("decoding")?;
+    let mut report = |at: Option<usize>, level: &str, message: &dyn std::fmt::Display| {
+        let file = file.display();
+        match at.map(|at| line_column(&text, at)) {
+            Some((line, column)) => writeln!(out, "{file}:{line}:{column}: {level}: {message}"),
+            None => writeln!(out, "{file}: {level}: {message}"),
+        }
+    };
+    let (table, warnings) = match parse::parse_with_warnings(&text, opts) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            let at = e.span().map(|span| span.start);
+            match &e {
+                Lua2JsonError::Parse { message, .. } => report(at, "error", message)?,
+                e => report(at, "error", e)?,
+            }
+            return Ok(false);
+        }
+    };
+    for warning in &warnings {
+        report(Some(warning.span.start), "warning", &warning.message)?;
+    }
+    let Some(schema) = schema else {
+        return Ok(true);
+    };
+    let violations = validate(&parse::Value::Object(table), schema);
+    // only for finding where the violations are, so it can fail on what it doesn't support
+    let doc = Document::parse(&text).ok();
+    for violation in &violations {
+        let at = doc.as_ref().and_then(|doc| doc.span(&violation.path).ok());
+        report(at.map(|span| span.start), "error", violation)?;
+    }
+    Ok(violations.is_empty())
+}
+
+/// Print a file's table back out as formatted Lua.
+fn fmt_file(args: &[String]) -> Result<()> {
+    let mut opts = FormatOptions::default();
+    let mut parse_opts = parse::ParseOptions::default();
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--indent" => {
+                let n = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--indent requires a value"))?;
+                opts.indent = match n.parse()? {
+                    0 => Indent::None,
+                    n => Indent::Spaces(n),
+                };
+            }
+            "--tabs" => opts.indent = Indent::Tab,
+            "--single-quotes" => opts.quote = Quote::Single,
+            "--no-trailing-commas" => opts.trailing_commas = false,
+            "--quote-keys" => opts.quote_keys = true,
+            "--sort-keys" => opts.key_order = KeyOrder::Sorted,
+            "--natural-sort-keys" => opts.key_order = KeyOrder::Natural,
+            "--lenient" => parse_opts.lenient = true,
+            flag if flag.starts_with("--") => bail!("unrecognised argument: {flag:?}"),
+            file if path.is_none() => path = Some(file),
+            _ => bail!("usage: lua2json fmt [OPTIONS] FILE.lua"),
+        }
+    }
+    let path = path.ok_or_else(|| anyhow!("usage: lua2json fmt [OPTIONS] FILE.lua"))?;
+    println!("{}", to_lua(&read_table_with(path, &parse_opts)?, &opts));
+    Ok(())
+}
+
+/// Print a JSON file as Lua.
+fn from_json_file(args: &[String]) -> Result<()> {
+    let mut json5 = false;
+    let mut path = None;
+    for arg in args {
+        match arg.as_str() {
+            "--json5" => json5 = true,
+            flag if flag.starts_with("--") => bail!("unrecognised argument: {flag:?}"),
+            file if path.is_none() => path = Some(file),
+            _ => bail!("usage: lua2json from-json [--json5] FILE.json"),
+        }
+    }
+    let path = path.ok_or_else(|| anyhow!("usage: lua2json from-json [--json5] FILE.json"))?;
+    let text = fs::read_to_string(path).with_context(|| anyhow!("reading {path:?}"))?;
+    let value = match json5 {
+        true => from_json5(&text),
+        false => serde_json::from_str(&text).map_err(Into::into),
+    }
+    .with_context(|| anyhow!("parsing {path:?}"))?;
+    println!("{}", to_lua(&value, &FormatOptions::default()));
+    Ok(())
+}
+
+/// Print a file's array of records as CSV.
+fn csv_file(args: &[String]) -> Result<()> {
+    let mut opts = CsvOptions::default();
+    let mut path = None;
+    for arg in args {
+        match arg.as_str() {
+            "--flatten" => opts.flatten = true,
+            flag if flag.starts_with("--") => bail!("unrecognised argument: {flag:?}"),
+            file if path.is_none() => path = Some(file),
+            _ => bail!("usage: lua2json csv [--flatten] FILE.lua"),
+        }
+    }
+    let path = path.ok_or_else(|| anyhow!("usage: lua2json csv [--flatten] FILE.lua"))?;
+    print!("{}", to_csv(&read_table(path)?, &opts)?);
+    Ok(())
+}
+
+/// Print the structure of a file as a DOT graph.
+fn graph_file(args: &[String]) -> Result<()> {
+    const USAGE: &str = "usage: lua2json graph [--depth N] FILE.lua";
+    let mut opts = DotOptions::default();
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--depth" => {
+                let depth = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--depth requires a value"))?;
+                opts.max_depth = Some(depth.parse()?);
+            }
+            flag if flag.starts_with("--") => bail!("unrecognised argument: {flag:?}"),
+            file if path.is_none() => path = Some(file),
+            _ => bail!(USAGE),
+        }
+    }
+    let path = path.ok_or_else(|| anyhow!(USAGE))?;
+    print!("{}", to_dot_with(&read_table(path)?, &opts));
+    Ok(())
+}
+
+/// Stream stdin as NDJSON, which doesn't need each table to be on its own line.
+fn ndjson(opts: &LineOptions) -> Result<()> {
+    if opts.query.is_some() || !matches!(opts.format, OutputFormat::Json) || opts.stats {
+        bail!("--ndjson can't be combined with --query, --format or --stats");
+    }
+    if !matches!(opts.json.key_order, KeyOrder::Preserve)
+        || Indent::None != opts.json.indent
+        || opts.json.canonical
+    {
+        bail!("--ndjson output can't be sorted, pretty-printed or canonical");
+    }
+    if !opts.keys.is_empty() || opts.redaction.is_some() {
+        bail!("--ndjson output can't have its keys changed or redacted");
+    }
+    if opts.sort.is_some() || opts.limit.is_some() {
+        bail!("--ndjson output can't be sorted by --sort-by or cut short by --limit");
+    }
+    let parse = &opts.parse;
+    if parse.raw_numbers
+        || parse.arithmetic
+        || parse.lenient
+        || parse.unicode_names
+        || parse.lowercase_keys
+        || parse.intern_keys
+        || !parse.only.is_empty()
+        || opts.parallel
+    {
+        bail!(
+            "--ndjson can't be combined with --raw-numbers, --arithmetic, --lenient, \
+             --unicode-names, --lowercase-keys, --intern-keys, --only or --parallel"
+        );
+    }
+    if Encoding::Utf8 != opts.encoding {
+        bail!("--ndjson input must be UTF-8");
+    }
+    if ArrayPolicy::Auto != opts.json.arrays || Holes::Object != opts.json.holes {
+        bail!("--ndjson can't be combined with --arrays or --holes");
+    }
+    let opts = TranscodeOptions {
+        multiple: true,
+        non_finite: opts.json.non_finite,
+        non_utf8: opts.json.non_utf8,
+        floats: opts.json.floats,
+        ndjson: true,
+        ..TranscodeOptions::default()
+    };
+    transcode(std::io::stdin().lock(), std::io::stdout().lock(), &opts)
+}
+
+fn lines(opts: &LineOptions) -> Result<()> {
     let stdin = std::io::stdin();
     let stdin = stdin.lock();
     let stdout = std::io::stdout();
     let mut stdout = stdout.lock();
+    let mut separate = false;
 
-    for (n, line) in stdin.lines().enumerate() {
+    for (n, line) in stdin.split(b'\n').enumerate() {
         let line = line?;
-        let obj =
-            parse::parse(&line).with_context(|| anyhow!("parsing line {}: {line:?}", n + 1))?;
-        serde_json::to_writer(&mut stdout, &to_json(&obj)?)?;
+        let line = line.strip_suffix(b"\r").unwrap_or(&line);
+        let line =
+            decode(line, opts.encoding).with_context(|| anyhow!("decoding line {}", n + 1))?;
+        let (obj, warnings) = opts
+            .parse_table(&line)
+            .with_context(|| anyhow!("parsing line {}: {line:?}", n + 1))?;
+        for warning in warnings {
+            eprintln!("warning: line {}: {warning}", n + 1);
+        }
+        let obj = parse::Value::Object(obj);
+        if opts.stats {
+            writeln!(stdout, "{}", stats_json(&obj.stats()))?;
+            continue;
+        }
+        let obj = opts.prepare(obj);
+        let values = match &opts.query {
+            Some(query) => query.eval(&obj),
+            None => vec![&obj],
+        };
+        for value in values {
+            match opts.format {
+                OutputFormat::Json => {
+                    if std::mem::replace(&mut separate, true) {
+                        stdout.write_all(b"\n")?;
+                    }
+                    let opts = JsonOptions {
+                        trailing_newline: false,
+                        ..opts.json.clone()
+                    };
+                    write_json(&mut stdout, value, &opts)?;
+                }
+                OutputFormat::Toml => {
+                    let toml = to_toml(value).with_context(|| anyhow!("on line {}", n + 1))?;
+                    // documents are separated by a blank line
+                    if std::mem::replace(&mut separate, true) {
+                        stdout.write_all(b"\n")?;
+                    }
+                    stdout.write_all(toml.as_bytes())?;
+                }
+                OutputFormat::Xml => {
+                    let xml =
+                        to_xml(value, &opts.xml()).with_context(|| anyhow!("on line {}", n + 1))?;
+                    // one document after another, each ending with a newline
+                    stdout.write_all(xml.trim_end().as_bytes())?;
+                    stdout.write_all(b"\n")?;
+                }
+                #[cfg(feature = "msgpack")]
+                OutputFormat::MsgPack => {
+                    stdout.write_all(&lua2json::msgpack::to_msgpack(value))?;
+                }
+            }
+        }
+    }
+    if separate && opts.json.trailing_newline && matches!(opts.format, OutputFormat::Json) {
         stdout.write_all(b"\n")?;
     }
     Ok(())
 }
 
-fn to_json(table: &parse::Table) -> Result<json::Value> {
-    let array = table.iter().all(|(k, _)| k.is_none());
-    Ok(if array {
-        json::Value::Array(
-            table
-                .into_iter()
-                .map(|(_, v)| val_to_json(v))
-                .collect::<Result<_>>()?,
-        )
-    } else {
-        let mut obj = serde_json::Map::new();
-        for (i, (k, v)) in table.into_iter().enumerate() {
-            let k = k
-                .as_ref()
-                .map(|s| s.to_string())
-                .unwrap_or_else(|| i.to_string());
-            obj.insert(k, val_to_json(v)?);
-        }
-        json::Value::Object(obj)
+fn stats_json(stats: &Stats) -> serde_json::Value {
+    serde_json::json!({
+        "tables": stats.tables,
+        "entries": stats.entries,
+        "max_depth": stats.max_depth,
+        "strings": stats.strings,
+        "string_bytes": stats.string_bytes,
+        "key_bytes": stats.key_bytes,
+        "numbers": stats.numbers,
+        "booleans": stats.booleans,
+        "raw": stats.raw,
+        "heap_bytes": stats.heap_bytes,
     })
 }
 
-fn val_to_json(v: &parse::Value) -> Result<json::Value> {
-    Ok(match v {
-        parse::Value::Float(f) => json!(*f),
-        parse::Value::String(s) => json::Value::String(s.clone()),
-        parse::Value::Object(t) => to_json(&t)?,
-        parse::Value::Bool(b) => json!(*b),
-    })
+/// A value as compact JSON.
+fn compact(value: &parse::Value) -> Result<String> {
+    to_json_string(value, &JsonOptions::default())
 }
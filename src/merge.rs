@@ -0,0 +1,150 @@
+use anyhow::{bail, Result};
+
+use crate::parse::Value;
+use crate::path;
+
+/// How [`Value::merge`] combines two tables.
+#[derive(Debug, Clone, Default)]
+pub struct MergeStrategy {
+    pub positional: PositionalMerge,
+    pub conflicts: ConflictMerge,
+}
+
+/// What happens to positional entries when merging.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PositionalMerge {
+    /// Keep the existing entries, and add the other table's after them.
+    #[default]
+    Append,
+    /// Drop the existing entries in favour of the other table's.
+    Replace,
+}
+
+/// What happens when the same key holds different types in each table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictMerge {
+    /// The other table's value wins, as it does for values of the same type.
+    #[default]
+    Overwrite,
+    /// The existing value is kept.
+    Keep,
+    /// Merging fails.
+    Error,
+}
+
+impl Value {
+    /// Merge `other` over this value: named entries present in both tables are merged
+    /// recursively, and scalars in `other` replace those here.
+    pub fn merge(&mut self, other: &Value, strategy: &MergeStrategy) -> Result<()> {
+        merge_at(self, other, strategy, &mut String::new())
+    }
+}
+
+fn merge_at(
+    into: &mut Value,
+    other: &Value,
+    strategy: &MergeStrategy,
+    at: &mut String,
+) -> Result<()> {
+    if let (Value::Object(table), Value::Object(others)) = (&mut *into, other) {
+        if PositionalMerge::Replace == strategy.positional {
+            table.retain(|(k, _)| k.is_some());
+        }
+        for (k, v) in others {
+            let key = match k {
+                Some(key) => key,
+                None => {
+                    table.push((None, v.clone()));
+                    continue;
+                }
+            };
+            match table.iter_mut().find(|(k, _)| k.as_ref() == Some(key)) {
+                Some((_, existing)) => {
+                    let len = at.len();
+                    path::push_key(at, key);
+                    merge_at(existing, v, strategy, at)?;
+                    at.truncate(len);
+                }
+                None => table.push((Some(key.clone()), v.clone())),
+            }
+        }
+        return Ok(());
+    }
+
+    if into.type_name() == other.type_name() {
+        *into = other.clone();
+        return Ok(());
+    }
+    match strategy.conflicts {
+        ConflictMerge::Overwrite => *into = other.clone(),
+        ConflictMerge::Keep => (),
+        ConflictMerge::Error => bail!(
+            "can't merge {} over {} at {at:?}",
+            other.type_name(),
+            into.type_name()
+        ),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::merge::{ConflictMerge, MergeStrategy, PositionalMerge};
+    use crate::parse::{parse, Value};
+
+    fn merged(base: &str, over: &str, strategy: &MergeStrategy) -> anyhow::Result<Value> {
+        let mut base = Value::Object(parse(base)?);
+        base.merge(&Value::Object(parse(over)?), strategy)?;
+        Ok(base)
+    }
+
+    fn lua(s: &str) -> Value {
+        Value::Object(parse(s).unwrap())
+    }
+
+    #[test]
+    fn deep() {
+        let base = r#"{w = 1, ui = {scale = 1, theme = "dark", "a"}, "x"}"#;
+        let over = r#"{ui = {scale = 2, font = "mono", "b"}, h = 3, "y"}"#;
+        assert_eq!(
+            lua(
+                r#"{w = 1, ui = {scale = 2, theme = "dark", "a", font = "mono", "b"}, "x", h = 3, "y"}"#
+            ),
+            merged(base, over, &MergeStrategy::default()).unwrap()
+        );
+
+        let replace = MergeStrategy {
+            positional: PositionalMerge::Replace,
+            ..MergeStrategy::default()
+        };
+        assert_eq!(
+            lua(r#"{w = 1, ui = {scale = 2, theme = "dark", font = "mono", "b"}, h = 3, "y"}"#),
+            merged(base, over, &replace).unwrap()
+        );
+    }
+
+    #[test]
+    fn conflicts() {
+        let base = r#"{a = {b = {c = 1}}}"#;
+        let over = r#"{a = {b = "flat"}}"#;
+        let with = |conflicts| MergeStrategy {
+            conflicts,
+            ..MergeStrategy::default()
+        };
+        assert_eq!(
+            lua(over),
+            merged(base, over, &with(ConflictMerge::Overwrite)).unwrap()
+        );
+        assert_eq!(
+            lua(base),
+            merged(base, over, &with(ConflictMerge::Keep)).unwrap()
+        );
+        let err = merged(base, over, &with(ConflictMerge::Error)).unwrap_err();
+        assert!(err.to_string().contains("\"a.b\""), "{err}");
+
+        assert_eq!(
+            lua("{a = 2}"),
+            merged("{a = 1}", "{a = 2}", &with(ConflictMerge::Error)).unwrap()
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use rmp::encode;
+
+use crate::parse::{Table, Value};
+
+/// Encode `value` as MessagePack.
+///
+/// Tables are arrays or maps by the same rule as JSON: arrays if every entry is positional,
+/// otherwise maps, with positional entries keyed by their index among all the entries as a
+/// string. Whole numbers are written as integers, which is more compact, and strings which
+/// aren't valid UTF-8 as binary.
+pub fn to_msgpack(value: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_value(&mut out, value);
+    out
+}
+
+fn write_value(out: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Object(table) => write_table(out, table),
+        Value::String(s) | Value::Raw(s) => encode::write_str(out, s).expect("infallible"),
+        Value::Bytes(b) => encode::write_bin(out, b).expect("infallible"),
+        Value::Float(f) | Value::Number { value: f, .. } => {
+            if 0. == f.fract() && f.abs() < 2f64.powi(63) {
+                encode::write_sint(out, *f as i64).expect("infallible");
+            } else {
+                encode::write_f64(out, *f).expect("infallible");
+            }
+        }
+        Value::Bool(b) => encode::write_bool(out, *b).expect("infallible"),
+    }
+}
+
+fn write_table(out: &mut Vec<u8>, table: &Table) {
+    let len = u32::try_from(table.len()).expect("fewer than 2^32 entries");
+    if table.iter().all(|(k, _)| k.is_none()) {
+        encode::write_array_len(out, len).expect("infallible");
+        for (_, v) in table {
+            write_value(out, v);
+        }
+        return;
+    }
+    encode::write_map_len(out, len).expect("infallible");
+    for (i, (k, v)) in table.iter().enumerate() {
+        match k {
+            Some(k) => encode::write_str(out, k),
+            None => encode::write_str(out, &i.to_string()),
+        }
+        .expect("infallible");
+        write_value(out, v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::msgpack::to_msgpack;
+    use crate::parse::{parse, Value};
+
+    #[test]
+    fn encoding() {
+        let v = Value::Object(parse(r#"{a = 1, {1.5, true}, ["b"] = "\xff"}"#).unwrap());
+        assert_eq!(
+            b"\x83\xa1a\x01\xa11\x92\xcb\x3f\xf8\0\0\0\0\0\0\xc3\xa1b\xc4\x01\xff".to_vec(),
+            to_msgpack(&v)
+        );
+        assert_eq!(b"\x90".to_vec(), to_msgpack(&Value::Object(Vec::new())));
+        assert_eq!(b"\xd0\x80".to_vec(), to_msgpack(&Value::Float(-128.)));
+    }
+}
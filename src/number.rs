@@ -0,0 +1,94 @@
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+use crate::parse::Value;
+
+/// Exact numbers, for values which don't fit in an `f64`. These are only exact for numbers
+/// parsed with [`ParseOptions::raw_numbers`](crate::parse::ParseOptions::raw_numbers), which
+/// keeps their source text; a [`Value::Float`] is only as exact as the `f64` it holds.
+impl Value {
+    /// An integer exactly, e.g. `9007199254740993` or `7.0`, but not `7.5`.
+    pub fn as_i128(&self) -> Option<i128> {
+        match self {
+            Value::Number { raw, .. } => {
+                let (whole, fraction) = raw.split_once('.').unwrap_or((raw, ""));
+                if !fraction.bytes().all(|c| b'0' == c) {
+                    return None;
+                }
+                whole.parse().ok()
+            }
+            Value::Float(f) if 0. == f.fract() && f.abs() < 2f64.powi(127) => Some(*f as i128),
+            _ => None,
+        }
+    }
+
+    /// A number exactly, if it has at most 28 significant digits.
+    pub fn as_decimal(&self) -> Option<Decimal> {
+        match self {
+            Value::Number { raw, .. } => Decimal::from_str_exact(raw).ok(),
+            Value::Float(f) => Decimal::try_from(*f).ok(),
+            _ => None,
+        }
+    }
+
+    /// A number which will be written out exactly as `n`.
+    pub fn integer(n: i128) -> Value {
+        Value::Number {
+            value: n as f64,
+            raw: n.to_string(),
+        }
+    }
+
+    /// A number which will be written out exactly as `d`.
+    pub fn decimal(d: Decimal) -> Value {
+        Value::Number {
+            value: d.to_f64().unwrap_or(f64::NAN),
+            raw: d.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use rust_decimal::Decimal;
+
+    use crate::parse::{parse_with, ParseOptions, Value};
+
+    #[test]
+    fn exact() {
+        let opts = ParseOptions {
+            raw_numbers: true,
+            ..ParseOptions::default()
+        };
+        let v = Value::Object(
+            parse_with(
+                "{a = 170141183460469231731687303715884105727, b = 0.1000000000000000000000000001, c = 7.00, d = 7.5}",
+                &opts,
+            )
+            .unwrap(),
+        );
+        assert_eq!(Some(i128::MAX), v["a"].as_i128());
+        assert_eq!(
+            Some(Decimal::from_str("0.1000000000000000000000000001").unwrap()),
+            v["b"].as_decimal()
+        );
+        assert_eq!(Some(7), v["c"].as_i128());
+        assert_eq!(None, v["d"].as_i128());
+        assert_eq!(Some(-2), Value::Float(-2.).as_i128());
+        assert_eq!(None, Value::Bool(true).as_decimal());
+
+        assert_eq!(
+            Value::Number {
+                value: 12.,
+                raw: "12".to_string()
+            },
+            Value::integer(12)
+        );
+        assert_eq!(
+            Some(Decimal::new(125, 2)),
+            Value::decimal(Decimal::new(125, 2)).as_decimal()
+        );
+    }
+}
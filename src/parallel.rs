@@ -0,0 +1,226 @@
+//! Parsing huge inputs on every core, by splitting them between their top-level entries.
+//!
+//! Files like game saves are often a few enormous tables which don't depend on each other,
+//! so they can be parsed separately: the input is split with a quick scan for where the
+//! top-level entries end, only matching up braces and skipping strings, and the parts are
+//! parsed on the [`rayon`] thread pool. If any part doesn't parse, like when the scan split
+//! it somewhere that isn't between two entries, the whole input is parsed again in one go,
+//! so the result and any error is always the same as without splitting it.
+
+use rayon::prelude::*;
+
+use crate::error::{Result, Warning};
+use crate::lex::{self, TokenKind};
+use crate::parse::{parse_entries, parse_multi, parse_with_warnings, ParseOptions, Table};
+
+/// The smallest part worth parsing on its own, in bytes.
+const MIN_PART: usize = 1 << 16;
+
+/// Parse a table like [`parse_with_warnings`], splitting it between its entries to parse them
+/// in parallel.
+///
+/// With [`Limits::max_entries`](crate::parse::Limits::max_entries), which counts the entries
+/// of the whole input, it isn't split.
+pub fn parse_parallel_with_warnings(s: &str, opts: &ParseOptions) -> Result<(Table, Vec<Warning>)> {
+    let parts = match splittable(s, opts) {
+        true => table_parts(s, part_len(s)),
+        false => None,
+    };
+    let Some(parts) = parts.filter(|parts| parts.len() > 1) else {
+        return parse_with_warnings(s, opts);
+    };
+    let parsed = parts
+        .par_iter()
+        .map(|&(at, len)| {
+            let (table, mut warnings) = parse_entries(&s[at..at + len], opts)?;
+            for warning in &mut warnings {
+                warning.span = warning.span.start + at..warning.span.end + at;
+            }
+            Ok((table, warnings))
+        })
+        .collect::<Result<Vec<_>>>();
+    let Ok(parsed) = parsed else {
+        return parse_with_warnings(s, opts);
+    };
+    let mut table = Table::with_capacity(parsed.iter().map(|(t, _)| t.len()).sum());
+    let mut warnings = Vec::new();
+    for (part, part_warnings) in parsed {
+        table.extend(part);
+        warnings.extend(part_warnings);
+    }
+    Ok((table, warnings))
+}
+
+/// Parse a table like [`parse_with`](crate::parse::parse_with), in parallel, like
+/// [`parse_parallel_with_warnings`].
+pub fn parse_parallel(s: &str, opts: &ParseOptions) -> Result<Table> {
+    parse_parallel_with_warnings(s, opts).map(|(t, _)| t)
+}
+
+/// Parse a sequence of documents like [`parse_multi`], splitting them between lines to parse
+/// them in parallel.
+pub fn parse_multi_parallel(s: &str, opts: &ParseOptions) -> Result<Table> {
+    let parts = match splittable(s, opts) {
+        true => document_parts(s, part_len(s)),
+        false => None,
+    };
+    let Some(parts) = parts.filter(|parts| parts.len() > 1) else {
+        return parse_multi(s, opts);
+    };
+    let parsed = parts
+        .par_iter()
+        .map(|&(at, len)| parse_multi(&s[at..at + len], opts))
+        .collect::<Result<Vec<_>>>();
+    match parsed {
+        Ok(parsed) => Ok(parsed.into_iter().flatten().collect()),
+        Err(_) => parse_multi(s, opts),
+    }
+}
+
+/// Whether the limits allow `s` to be parsed in parts, as they're otherwise failed with the
+/// input as a whole.
+fn splittable(s: &str, opts: &ParseOptions) -> bool {
+    let limits = &opts.limits;
+    limits.max_entries.is_none() && limits.max_input.is_none_or(|max| s.len() <= max)
+}
+
+/// How long to make each part of `s`, for a few of them for each thread.
+fn part_len(s: &str) -> usize {
+    (s.len() / (4 * rayon::current_num_threads())).max(MIN_PART)
+}
+
+/// The index of the quote ending the string which starts at `at`.
+fn string_end(bytes: &[u8], at: usize) -> Option<usize> {
+    let quote = bytes[at];
+    let mut i = at;
+    loop {
+        i += 1 + memchr::memchr2(quote, b'\\', &bytes[i + 1..])?;
+        if quote == bytes[i] {
+            return Some(i);
+        }
+        i += 1;
+    }
+}
+
+/// Where the entries of the table `s` are and how long they are, in parts of at least `len`
+/// bytes split at commas between its entries, or `None` if the braces don't match up.
+fn table_parts(s: &str, len: usize) -> Option<Vec<(usize, usize)>> {
+    let bytes = s.as_bytes();
+    let open = s.len() - s.trim_start().len();
+    if Some(&b'{') != bytes.get(open) {
+        return None;
+    }
+    let mut parts = Vec::new();
+    let mut start = open + 1;
+    let mut depth = 1usize;
+    let mut i = start;
+    while let Some(&c) = bytes.get(i) {
+        match c {
+            b'"' | b'\'' => i = string_end(bytes, i)?,
+            b'{' => depth += 1,
+            b'}' if 1 == depth => {
+                // the rest is left to the parser, which fails on anything but whitespace
+                parts.push((start, i - start));
+                return s[i + 1..].trim().is_empty().then_some(parts);
+            }
+            b'}' => depth -= 1,
+            b',' if 1 == depth && i - start >= len => {
+                parts.push((start, i - start));
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Where the parts of the documents `s` are and how long they are, of at least `len` bytes
+/// split at the ends of lines outside of any table, or `None` if the braces don't match up.
+fn document_parts(s: &str, len: usize) -> Option<Vec<(usize, usize)>> {
+    let bytes = s.as_bytes();
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+    let mut i = 0;
+    while let Some(&c) = bytes.get(i) {
+        match c {
+            b'"' | b'\'' => i = string_end(bytes, i)?,
+            b'{' => depth += 1,
+            b'}' => depth = depth.checked_sub(1)?,
+            b'-' if 0 == depth && s[i..].starts_with("--") => match lex::comment(&s[i..]) {
+                (TokenKind::Error, _) => return None,
+                (_, comment) => {
+                    i += comment;
+                    continue;
+                }
+            },
+            b'\n' if 0 == depth && i + 1 - start >= len => {
+                parts.push((start, i + 1 - start));
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    parts.push((start, s.len() - start));
+    Some(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parallel::{
+        document_parts, parse_multi_parallel, parse_parallel_with_warnings, table_parts,
+    };
+    use crate::parse::{parse_multi, parse_with_warnings, ParseOptions};
+
+    #[test]
+    fn parts() {
+        let s = r#" {a = {1, 2}, b = "x,}", {3}, c = 'y,}',} "#;
+        let parts = table_parts(s, 1).unwrap();
+        let parts = parts.iter().map(|&(at, len)| &s[at..at + len]);
+        assert_eq!(
+            vec!["a = {1, 2}", r#" b = "x,}""#, " {3}", " c = 'y,}'", ""],
+            parts.collect::<Vec<_>>()
+        );
+        assert_eq!(Some(vec![(1, 9)]), table_parts("{ a = {1} }", 100));
+        assert_eq!(None, table_parts("{a = {1}", 1));
+        assert_eq!(None, table_parts("{a = 1} x", 1));
+        assert_eq!(None, table_parts("a = 1", 1));
+
+        let s = "A = {\n1}\n-- {\"\nB = {}\n\nC = 2";
+        let parts = document_parts(s, 1).unwrap();
+        let parts = parts.iter().map(|&(at, len)| &s[at..at + len]);
+        assert_eq!(
+            vec!["A = {\n1}\n", "-- {\"\n", "B = {}\n", "\n", "C = 2"],
+            parts.collect::<Vec<_>>()
+        );
+        assert_eq!(None, document_parts("A = {}}", 1));
+    }
+
+    #[test]
+    fn same_as_serial() {
+        let opts = ParseOptions {
+            lenient: true,
+            ..ParseOptions::default()
+        };
+        let entry = r#"players = {{name = "a", on = function() return "}" end}, {name = "b"}}"#;
+        let s = format!("{{{}}}", vec![entry; 10_000].join(",\n"));
+        let (table, warnings) = parse_parallel_with_warnings(&s, &opts).unwrap();
+        assert_eq!(parse_with_warnings(&s, &opts).unwrap(), (table, warnings));
+
+        // parts which don't parse are parsed again together, for the same error
+        let opts = ParseOptions::default();
+        let broken = s.replacen("function() return \"}\" end", "1 2", 1);
+        assert_eq!(
+            parse_with_warnings(&broken, &opts).unwrap_err(),
+            parse_parallel_with_warnings(&broken, &opts).unwrap_err()
+        );
+
+        let docs = vec!["AccountData = {n = 1, t = {\"x\"}}\n-- saved\n"; 10_000].concat();
+        assert_eq!(
+            parse_multi(&docs, &opts).unwrap(),
+            parse_multi_parallel(&docs, &opts).unwrap()
+        );
+    }
+}
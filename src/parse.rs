@@ -1,14 +1,139 @@
-use anyhow::{anyhow, bail, Result};
+use alloc::borrow::Cow;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::{self, Vec};
+use core::cell::{Cell, RefCell};
+use core::fmt;
+use core::ops::Range;
+use core::str::{self, FromStr};
+
 use nom::branch::alt;
-use nom::bytes::complete::{escaped_transform, tag, take_while1};
-use nom::character::complete::{alpha1, char, digit1, multispace0, none_of};
-use nom::character::is_alphabetic;
-use nom::combinator::{map, opt, recognize};
+use nom::bytes::complete::{tag, take_while, take_while1};
+use nom::character::complete::{char, one_of};
+use nom::combinator::{map, map_opt, opt, recognize};
+use nom::error::{Error, ErrorKind};
 use nom::multi::separated_list0;
-use nom::sequence::{delimited, pair, terminated, tuple};
+use nom::sequence::{delimited, pair, preceded, terminated};
 use nom::IResult;
 
-pub type Table = Vec<(Option<String>, Value)>;
+use crate::encoding::{decode, Encoding};
+use crate::error::{AccessError, Limit, LimitExceeded, Lua2JsonError, Result, Warning};
+use crate::format::{to_lua, FormatOptions, Indent};
+use crate::lex::{self, TokenKind};
+
+pub type Table = Vec<(Option<Key>, Value)>;
+
+/// The key of a named entry. It derefs to the key as a string, which is how it's looked up
+/// and written as JSON, so `[1]` and `["1"]` are both found by `"1"`, but they're different
+/// keys in Lua, so they aren't equal.
+#[derive(Debug, Clone)]
+pub enum Key {
+    /// A name, like `a` in `a = 1` or `["a b"]` in `["a b"] = 1`.
+    Name(String),
+    /// An integer, like `[1]` in `[1] = "a"`, kept as its digits.
+    Integer(String),
+    /// A name which shares its allocation with the other names spelled the same, from
+    /// [`ParseOptions::intern_keys`]. It's equal to the [`Key::Name`] with the same string.
+    Shared(Arc<str>),
+}
+
+impl Key {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Key::Name(s) | Key::Integer(s) => s,
+            Key::Shared(s) => s,
+        }
+    }
+
+    pub fn into_string(self) -> String {
+        match self {
+            Key::Name(s) | Key::Integer(s) => s,
+            Key::Shared(s) => s.to_string(),
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(self, Key::Integer(_))
+    }
+}
+
+impl core::ops::Deref for Key {
+    type Target = str;
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Key {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Key) -> bool {
+        self.as_str() == other.as_str() && self.is_integer() == other.is_integer()
+    }
+}
+
+impl Eq for Key {}
+
+impl core::hash::Hash for Key {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        (self.as_str(), self.is_integer()).hash(state)
+    }
+}
+
+/// By the string, then with names before integers.
+impl Ord for Key {
+    fn cmp(&self, other: &Key) -> core::cmp::Ordering {
+        (self.as_str(), self.is_integer()).cmp(&(other.as_str(), other.is_integer()))
+    }
+}
+
+impl PartialOrd for Key {
+    fn partial_cmp(&self, other: &Key) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq<str> for Key {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Key {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<String> for Key {
+    fn from(name: String) -> Key {
+        Key::Name(name)
+    }
+}
+
+impl From<&str> for Key {
+    fn from(name: &str) -> Key {
+        Key::Name(name.to_string())
+    }
+}
+
+impl From<Key> for String {
+    fn from(key: Key) -> String {
+        key.into_string()
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
@@ -16,178 +141,1592 @@ pub enum Value {
     String(String),
     Float(f64),
     Bool(bool),
+    /// A string which isn't valid UTF-8, from escapes like `\xff` or the raw bytes of
+    /// the input.
+    Bytes(Vec<u8>),
+    /// A number along with the text it was written as, from [`parse_with`] with
+    /// [`ParseOptions::raw_numbers`], so it can be written back out exactly. This is not equal to
+    /// the [`Value::Float`] with the same value.
+    Number {
+        value: f64,
+        raw: String,
+    },
+    /// The source of a value which couldn't be parsed, like a function, kept by
+    /// [`ParseOptions::lenient`].
+    Raw(String),
+}
+
+/// Options for [`parse_with`] and [`parse_document`].
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Parse numbers as [`Value::Number`], keeping their source text, instead of [`Value::Float`].
+    pub raw_numbers: bool,
+    /// Evaluate arithmetic on numbers, like `60 * 15` or `-(10 + 2)`, with `+ - * / % ^`, unary
+    /// minus and parentheses, as Lua would. Results are [`Value::Float`]s.
+    pub arithmetic: bool,
+    /// Called with each bare name in the place of a value, like `W` in `{width = W}`, for the
+    /// value it stands for, or `None` if there isn't one, which is an error. Names assigned
+    /// earlier in a [`parse_document`] are found without calling this.
+    pub resolve: Option<fn(&str) -> Option<Value>>,
+    /// Keep values which can't be parsed, like functions or `require(...)` calls, as
+    /// [`Value::Raw`] text up to the end of their entry, with a [`Warning`] from
+    /// [`parse_with_warnings`], rather than failing. Undefined names are kept too.
+    pub lenient: bool,
+    /// Accept names made of Unicode letters and digits, like `größe`, as some builds of Lua
+    /// do, rather than only ASCII ones.
+    pub unicode_names: bool,
+    /// Lowercase the names of entries and trim any whitespace around them, so `Width`, `WIDTH`
+    /// and `[" width "]` are all `width`, for configs written by hand which aren't consistent
+    /// about it. Paths in [`only`](Self::only) are matched against the lowercased names.
+    pub lowercase_keys: bool,
+    /// Only keep the entries at these paths, like `AccountData` or `AccountData.realms`, made
+    /// of names separated by `.`, along with the tables leading to them. Other entries are
+    /// skipped over, with tables only having their brackets matched up rather than being
+    /// parsed, which is much faster for a few entries of a large document, but leaves syntax
+    /// errors inside them unnoticed. Empty keeps everything. For [`parse_document`] and
+    /// [`parse_multi`], the names assigned are the first part of the paths, and other tables
+    /// can't refer to the ones skipped.
+    pub only: Vec<String>,
+    /// Make the names of entries [`Key::Shared`], so each name is only allocated once however
+    /// many tables it's in, which saves memory on arrays of records with the same fields, at
+    /// the cost of looking each name up as it's parsed.
+    pub intern_keys: bool,
+    pub limits: Limits,
+}
+
+/// Caps on how much a document can make the parser do, for input which isn't trusted. `None`
+/// is unlimited, which is the default.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    /// The length of the whole document, in bytes.
+    pub max_input: Option<usize>,
+    /// The length of each string value, in bytes once its escapes are decoded.
+    pub max_string: Option<usize>,
+    /// The number of entries in all the tables of the document together.
+    pub max_entries: Option<usize>,
+    /// How deeply tables, and parentheses with [`ParseOptions::arithmetic`], can be nested, where
+    /// the root table is at depth one.
+    pub max_depth: Option<usize>,
+}
+
+/// The state of one parse, which the parsers share.
+struct Context<'o> {
+    opts: &'o ParseOptions,
+    /// The length of the whole input, which the remaining input is the end of.
+    len: usize,
+    /// The entries parsed so far.
+    entries: Cell<usize>,
+    /// The tables being parsed.
+    depth: Cell<usize>,
+    /// What stopped the parse, if it was more specific than a syntax error.
+    error: Cell<Option<Lua2JsonError>>,
+    /// The furthest into the input a value failed to parse, which is where a syntax error is,
+    /// rather than the start of the entry the parsers backtracked to.
+    furthest: Cell<usize>,
+    /// The names assigned so far, latest last, when parsing a document.
+    variables: Option<RefCell<Vec<(String, Value)>>>,
+    /// What [`ParseOptions::lenient`] let through.
+    warnings: RefCell<Vec<Warning>>,
+    /// The nodes of the tables parsed so far, when they're being recorded: one for each entry
+    /// of the tables being parsed, then one for each table.
+    nodes: Option<RefCell<Vec<Option<Node>>>>,
+    /// The parts of each of [`ParseOptions::only`].
+    only: Vec<Vec<&'o str>>,
+    /// Which of `only` lead through the table being parsed, and how many of their parts that
+    /// is, or `None` when everything in it is kept.
+    selecting: RefCell<Option<(Vec<usize>, usize)>>,
+    names: RefCell<Names>,
+}
+
+/// The names of entries made so far, for sharing them with [`ParseOptions::intern_keys`].
+#[derive(Default)]
+pub(crate) struct Names(BTreeSet<Arc<str>>);
+
+impl Names {
+    /// The key for the name of an entry, lowercased with [`ParseOptions::lowercase_keys`], and
+    /// shared with the earlier ones spelled the same if they're being interned.
+    pub(crate) fn key(&mut self, opts: &ParseOptions, name: Cow<str>) -> Key {
+        let name = match opts.lowercase_keys {
+            true => Cow::Owned(name.trim().to_lowercase()),
+            false => name,
+        };
+        if !opts.intern_keys {
+            return Key::Name(name.into_owned());
+        }
+        if let Some(shared) = self.0.get(&*name) {
+            return Key::Shared(shared.clone());
+        }
+        let shared: Arc<str> = name.into();
+        self.0.insert(shared.clone());
+        Key::Shared(shared)
+    }
+}
+
+/// Where a table is in the input, and where the tables in its entries are, for reparsing
+/// only part of a document once it's been edited.
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+pub(crate) struct Node {
+    /// From its `{` to its `}`.
+    pub(crate) span: Range<usize>,
+    /// The number of entries in the table and all the tables inside it.
+    pub(crate) entries: usize,
+    /// For each entry, the node of its value, if that's a table written out in the input
+    /// rather than, say, one a variable stands for.
+    pub(crate) children: Vec<Option<Node>>,
+}
+
+impl Context<'_> {
+    fn new<'o>(opts: &'o ParseOptions, input: &str) -> Context<'o> {
+        Context {
+            opts,
+            len: input.len(),
+            entries: Cell::new(0),
+            depth: Cell::new(0),
+            error: Cell::new(None),
+            furthest: Cell::new(0),
+            variables: None,
+            warnings: RefCell::new(Vec::new()),
+            nodes: None,
+            only: opts
+                .only
+                .iter()
+                .map(|path| path.split('.').collect())
+                .collect(),
+            selecting: RefCell::new(
+                (!opts.only.is_empty()).then(|| ((0..opts.only.len()).collect(), 0)),
+            ),
+            names: RefCell::new(Names::default()),
+        }
+    }
+
+    fn name(&self, name: Cow<str>) -> Key {
+        self.names.borrow_mut().key(self.opts, name)
+    }
+
+    /// Record the node of an entry parsed since `mark`, if it was parsed and kept.
+    fn record_entry(&self, mark: usize, entry: Option<&Value>) {
+        let Some(nodes) = &self.nodes else {
+            return;
+        };
+        let mut nodes = nodes.borrow_mut();
+        let node = match nodes.len() - mark {
+            1 if matches!(entry, Some(Value::Object(_))) => nodes.pop().flatten(),
+            _ => None,
+        };
+        nodes.truncate(mark);
+        if entry.is_some() {
+            nodes.push(node);
+        }
+    }
+
+    /// How many nodes have been recorded, which are the ones from before what's parsed next.
+    fn mark(&self) -> usize {
+        self.nodes.as_ref().map_or(0, |nodes| nodes.borrow().len())
+    }
+
+    /// How far into the whole input the remaining `input` starts.
+    fn offset(&self, input: &str) -> usize {
+        self.len - input.len()
+    }
+
+    /// Stop parsing with `error`, whose span is relative to the remaining `input`.
+    fn fail<'a, T>(&self, input: &'a str, error: Lua2JsonError) -> IResult<&'a str, T> {
+        self.error.set(Some(error.offset(self.offset(input))));
+        Err(nom::Err::Failure(Error::new(input, ErrorKind::Fail)))
+    }
+
+    /// Stop parsing at `input` as its `limit` of `max` has been exceeded.
+    fn exceed<'a, T>(&self, input: &'a str, limit: Limit, max: usize) -> IResult<&'a str, T> {
+        self.fail(input, LimitExceeded { limit, max }.into())
+    }
+
+    fn error(&self, e: nom::Err<Error<&str>>) -> Lua2JsonError {
+        match e {
+            nom::Err::Error(e) | nom::Err::Failure(e) => self.syntax_error(e.input),
+            nom::Err::Incomplete(_) => unreachable!("complete parsers"),
+        }
+    }
+
+    /// The error for a parse which stopped at `input`, or which left `input` over.
+    fn syntax_error(&self, input: &str) -> Lua2JsonError {
+        if let Some(error) = self.error.take() {
+            return error;
+        }
+        let at = self.offset(input).max(self.furthest.get());
+        let input = &input[at - self.offset(input)..];
+        match input.chars().next() {
+            Some(c) => Lua2JsonError::parse(format!("unexpected {c:?}"), at..at + c.len_utf8()),
+            None => Lua2JsonError::parse("unexpected end of input", at..at),
+        }
+    }
 }
 
 impl Value {
-    pub fn table(&self) -> Result<&Table> {
+    pub fn as_table(&self) -> Option<&Table> {
         match self {
-            Value::Object(t) => Ok(t),
-            _ => Err(anyhow!("expected table but found {self:?}")),
+            Value::Object(t) => Some(t),
+            _ => None,
         }
     }
-    pub fn string(&self) -> Result<String> {
+    pub fn as_table_mut(&mut self) -> Option<&mut Table> {
         match self {
-            Value::String(t) => Ok(t.clone()),
-            _ => Err(anyhow!("expected string but found {self:?}")),
+            Value::Object(t) => Some(t),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+    /// The bytes of a string, whether or not it's valid UTF-8.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::String(s) => Some(s.as_bytes()),
+            Value::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Float(f) | Value::Number { value: f, .. } => Some(*f),
+            _ => None,
+        }
+    }
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+    /// The Lua name for the type of this value.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Object(_) => "table",
+            Value::String(_) | Value::Bytes(_) => "string",
+            Value::Float(_) | Value::Number { .. } => "number",
+            Value::Bool(_) => "boolean",
+            Value::Raw(_) => "raw",
+        }
+    }
+    fn mismatch(&self, expected: &'static str) -> AccessError {
+        AccessError::TypeMismatch {
+            expected,
+            found: self.type_name(),
+        }
+    }
+    pub fn table(&self) -> Result<&Table, AccessError> {
+        self.as_table().ok_or_else(|| self.mismatch("table"))
+    }
+    pub fn table_mut(&mut self) -> Result<&mut Table, AccessError> {
+        let found = self.type_name();
+        self.as_table_mut().ok_or(AccessError::TypeMismatch {
+            expected: "table",
+            found,
+        })
+    }
+    pub fn string(&self) -> Result<String, AccessError> {
+        self.as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| self.mismatch("string"))
+    }
+    pub fn f64(&self) -> Result<f64, AccessError> {
+        self.as_f64().ok_or_else(|| self.mismatch("number"))
+    }
+    /// The value of the first entry named `key`, if this is a table and there is one.
+    pub fn get_opt(&self, key: &str) -> Option<&Value> {
+        self.as_table()?
+            .iter()
+            .find(|(k, _)| k.as_deref() == Some(key))
+            .map(|(_, v)| v)
+    }
+    /// The value of the first entry named `key`, or if there isn't one, of the first entry whose
+    /// name is `key` ignoring case and any whitespace around either of them, like `Width` or
+    /// `" width"` for `width`.
+    pub fn get_ci(&self, key: &str) -> Option<&Value> {
+        let fold = |s: &str| {
+            s.trim()
+                .chars()
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        };
+        let key_folded = fold(key);
+        self.get_opt(key).or_else(|| {
+            self.as_table()?
+                .iter()
+                .find(|(k, _)| k.as_deref().is_some_and(|k| fold(k) == key_folded))
+                .map(|(_, v)| v)
+        })
+    }
+    pub fn get(&self, key: &str) -> Result<Value, AccessError> {
+        self.lookup(key).cloned()
+    }
+    fn lookup(&self, key: &str) -> Result<&Value, AccessError> {
+        self.table()?;
+        self.get_opt(key).ok_or_else(|| AccessError::KeyNotFound {
+            key: key.to_string(),
+        })
+    }
+    pub fn get_mut(&mut self, key: &str) -> Result<&mut Value, AccessError> {
+        self.table_mut()?
+            .iter_mut()
+            .find(|(k, _)| k.as_deref() == Some(key))
+            .map(|(_, v)| v)
+            .ok_or_else(|| AccessError::KeyNotFound {
+                key: key.to_string(),
+            })
+    }
+    /// Replace the value of the first entry named `key`, as found by `get`, or add a new entry
+    /// at the end, returning the value it replaced.
+    pub fn set(&mut self, key: &str, value: Value) -> Result<Option<Value>, AccessError> {
+        if let Ok(existing) = self.get_mut(key) {
+            return Ok(Some(core::mem::replace(existing, value)));
+        }
+        self.table_mut()?.push((Some(key.into()), value));
+        Ok(None)
+    }
+    /// Remove the first entry named `key`, returning its value.
+    pub fn remove(&mut self, key: &str) -> Result<Option<Value>, AccessError> {
+        let table = self.table_mut()?;
+        Ok(table
+            .iter()
+            .position(|(k, _)| k.as_deref() == Some(key))
+            .map(|i| table.remove(i).1))
+    }
+    /// Add a positional entry at the end.
+    pub fn push(&mut self, value: Value) -> Result<(), AccessError> {
+        self.table_mut()?.push((None, value));
+        Ok(())
+    }
+    /// Add a positional entry so it becomes the ith, shifting later positional entries along.
+    pub fn insert(&mut self, i: usize, value: Value) -> Result<(), AccessError> {
+        let count = self.positional_count();
+        let table = self.table_mut()?;
+        let at = match table
+            .iter()
+            .enumerate()
+            .filter(|(_, (k, _))| k.is_none())
+            .nth(i)
+        {
+            Some((at, _)) => at,
+            None if i == count => table.len(),
+            None => {
+                return Err(AccessError::IndexOutOfRange {
+                    index: i,
+                    len: count,
+                })
+            }
+        };
+        table.insert(at, (None, value));
+        Ok(())
+    }
+    /// The nth positional (unnamed) entry, counting from zero.
+    pub fn index(&self, i: usize) -> Result<&Value, AccessError> {
+        let mut positional = self.table()?.iter().filter(|(k, _)| k.is_none());
+        positional
+            .nth(i)
+            .map(|(_, v)| v)
+            .ok_or_else(|| AccessError::IndexOutOfRange {
+                index: i,
+                len: self.positional_count(),
+            })
+    }
+    fn positional_count(&self) -> usize {
+        self.as_table()
+            .map_or(0, |t| t.iter().filter(|(k, _)| k.is_none()).count())
+    }
+    /// The number of entries in a table, or zero for anything else.
+    pub fn len(&self) -> usize {
+        self.as_table().map_or(0, |t| t.len())
+    }
+    pub fn is_empty(&self) -> bool {
+        0 == self.len()
+    }
+    /// A table with no named entries, which is emitted as a JSON array.
+    pub fn is_array_like(&self) -> bool {
+        self.as_table()
+            .is_some_and(|t| t.iter().all(|(k, _)| k.is_none()))
+    }
+    pub fn as_array(&self) -> Option<Vec<&Value>> {
+        match self.as_table() {
+            Some(t) if self.is_array_like() => Some(t.iter().map(|(_, v)| v).collect()),
+            _ => None,
+        }
+    }
+    /// The entries of a table in order, with `None` keys for positional entries; empty for
+    /// anything else.
+    pub fn entries(&self) -> Entries<'_> {
+        Entries(self.as_table().map_or([].iter(), |t| t.iter()))
+    }
+    pub fn keys(&self) -> impl Iterator<Item = Option<&str>> {
+        self.entries().map(|(k, _)| k)
+    }
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.entries().map(|(_, v)| v)
+    }
+    /// The named entries of a table which has no positional entries, where the last of any
+    /// entries with the same name wins, as it would in Lua.
+    pub fn to_map(&self) -> Result<BTreeMap<String, Value>, AccessError> {
+        match self.table()? {
+            t if t.iter().all(|(k, _)| k.is_some()) => Ok(t
+                .iter()
+                .map(|(k, v)| {
+                    (
+                        k.clone().expect("only named entries").into_string(),
+                        v.clone(),
+                    )
+                })
+                .collect()),
+            _ => Err(self.mismatch("table with named entries")),
+        }
+    }
+    /// The positional entries of a table in order, and its named entries; both empty for
+    /// anything else.
+    pub fn split(&self) -> (Vec<Value>, BTreeMap<String, Value>) {
+        let mut positional = Vec::new();
+        let mut named = BTreeMap::new();
+        for (k, v) in self.entries() {
+            match k {
+                Some(k) => {
+                    named.insert(k.to_string(), v.clone());
+                }
+                None => positional.push(v.clone()),
+            }
+        }
+        (positional, named)
+    }
+    pub fn into_entries(self) -> vec::IntoIter<(Option<Key>, Value)> {
+        match self {
+            Value::Object(t) => t.into_iter(),
+            _ => Table::new().into_iter(),
+        }
+    }
+}
+
+impl FromStr for Value {
+    type Err = Lua2JsonError;
+    fn from_str(s: &str) -> Result<Value> {
+        parse_value(s)
+    }
+}
+
+/// Lua text for the value, which [`parse_value`] reads back: on one line, or laid out like
+/// [`FormatOptions::default`] with `{:#}`.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let opts = if f.alternate() {
+            FormatOptions::default()
+        } else {
+            FormatOptions {
+                indent: Indent::None,
+                ..FormatOptions::default()
+            }
+        };
+        f.write_str(&to_lua(self, &opts))
+    }
+}
+
+pub struct Entries<'a>(core::slice::Iter<'a, (Option<Key>, Value)>);
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = (Option<&'a str>, &'a Value);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, v)| (k.as_deref(), v))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a> IntoIterator for &'a Value {
+    type Item = (Option<&'a str>, &'a Value);
+    type IntoIter = Entries<'a>;
+    fn into_iter(self) -> Entries<'a> {
+        self.entries()
+    }
+}
+
+impl IntoIterator for Value {
+    type Item = (Option<Key>, Value);
+    type IntoIter = vec::IntoIter<(Option<Key>, Value)>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_entries()
+    }
+}
+
+impl core::ops::Index<&str> for Value {
+    type Output = Value;
+    fn index(&self, key: &str) -> &Value {
+        self.lookup(key).unwrap_or_else(|e| panic!("{e}"))
+    }
+}
+
+impl core::ops::IndexMut<&str> for Value {
+    fn index_mut(&mut self, key: &str) -> &mut Value {
+        self.get_mut(key).unwrap_or_else(|e| panic!("{e}"))
+    }
+}
+
+impl core::ops::Index<usize> for Value {
+    type Output = Value;
+    fn index(&self, i: usize) -> &Value {
+        Value::index(self, i).unwrap_or_else(|e| panic!("{e}"))
+    }
+}
+
+impl core::ops::IndexMut<usize> for Value {
+    fn index_mut(&mut self, i: usize) -> &mut Value {
+        let count = self.positional_count();
+        let table = self.table_mut().unwrap_or_else(|e| panic!("{e}"));
+        match table.iter_mut().filter(|(k, _)| k.is_none()).nth(i) {
+            Some((_, v)) => v,
+            None => panic!("no positional entry {i}, only {count} present"),
+        }
+    }
+}
+
+// atom: number or string
+// table: { (label? value), * }
+// value = atom | table
+
+fn ws(input: &str) -> IResult<&str, &str> {
+    let len = input
+        .bytes()
+        .take_while(|c| matches!(c, b' ' | b'\t' | b'\n' | b'\r'))
+        .count();
+    Ok((&input[len..], &input[..len]))
+}
+
+fn num<'a>(input: &'a str, cx: &Context) -> IResult<&'a str, Value> {
+    // -?[0-9]+(\.[0-9]+)?([eE][-+]?[0-9]+)?
+    let bytes = input.as_bytes();
+    let digits = |from: usize| {
+        bytes[from..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .count()
+    };
+    let mut end = usize::from(Some(&b'-') == bytes.first());
+    match digits(end) {
+        0 => return Err(nom::Err::Error(Error::new(input, ErrorKind::Digit))),
+        n => end += n,
+    }
+    if Some(&b'.') == bytes.get(end) {
+        match digits(end + 1) {
+            0 => {}
+            n => end += 1 + n,
+        }
+    }
+    if let Some(b'e' | b'E') = bytes.get(end) {
+        let sign = usize::from(matches!(bytes.get(end + 1), Some(b'-' | b'+')));
+        match digits(end + 1 + sign) {
+            0 => {}
+            n => end += 1 + sign + n,
+        }
+    }
+    let (v, rest) = input.split_at(end);
+    let value = match v.parse::<f64>() {
+        Ok(value) if value.is_finite() => value,
+        Ok(_) => return cx.fail(input, Lua2JsonError::parse("number out of range", 0..end)),
+        Err(_) => return cx.fail(input, Lua2JsonError::parse("invalid number", 0..end)),
+    };
+    Ok((
+        rest,
+        if cx.opts.raw_numbers {
+            Value::Number {
+                value,
+                raw: v.to_string(),
+            }
+        } else {
+            Value::Float(value)
+        },
+    ))
+}
+
+/// The body of a quoted string, from between its quotes, with its escapes decoded
+/// only if it has any.
+enum Quoted<'a> {
+    Plain(&'a str),
+    /// Which may not be valid UTF-8.
+    Decoded(Vec<u8>),
+}
+
+fn quoted<'a>(input: &'a str, cx: &Context) -> IResult<&'a str, Quoted<'a>> {
+    let (body, quote) = one_of("\"'")(input)?;
+    let quote = quote as u8;
+    let bytes = body.as_bytes();
+    let mut i = 0;
+    let mut escaped = false;
+    let end = loop {
+        match memchr::memchr3(quote, b'\\', b'\n', &bytes[i..]).map(|at| i + at) {
+            Some(at) if quote == bytes[at] => break at,
+            Some(at) if b'\\' == bytes[at] => {
+                escaped = true;
+                i = (at + 2).min(bytes.len());
+            }
+            _ => {
+                let end = memchr::memchr(b'\n', bytes).unwrap_or(bytes.len());
+                return cx.fail(
+                    input,
+                    Lua2JsonError::parse("unterminated string", 0..end + 1),
+                );
+            }
+        }
+    };
+    let rest = &body[end + 1..];
+    if !escaped {
+        return Ok((rest, Quoted::Plain(&body[..end])));
+    }
+    match unescape(&bytes[..end]) {
+        Ok(decoded) => Ok((rest, Quoted::Decoded(decoded))),
+        Err(e) => cx.fail(body, e),
+    }
+}
+
+fn quoted_string<'a>(input: &'a str, cx: &Context) -> IResult<&'a str, String> {
+    match quoted(input, cx)? {
+        (rest, Quoted::Plain(s)) => Ok((rest, s.to_string())),
+        (rest, Quoted::Decoded(bytes)) => match String::from_utf8(bytes) {
+            Ok(s) => Ok((rest, s)),
+            Err(_) => {
+                let span = 0..input.len() - rest.len();
+                cx.fail(input, Lua2JsonError::parse("key isn't valid UTF-8", span))
+            }
+        },
+    }
+}
+
+/// Decode the escapes in the body of a Lua string, from between its quotes. Errors have spans
+/// within `raw`.
+pub(crate) fn unescape(raw: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(raw.len());
+    let mut i = 0;
+    while let Some(at) = memchr::memchr(b'\\', &raw[i..]) {
+        out.extend_from_slice(&raw[i..i + at]);
+        let start = i + at;
+        i = start + 1;
+        let c = *raw
+            .get(i)
+            .ok_or_else(|| Lua2JsonError::parse("unterminated escape", start..i))?;
+        i += 1;
+        // the escape so far, for errors about it
+        let escape = |end: usize| start..end.min(raw.len());
+        match c {
+            b'a' => out.push(7),
+            b'b' => out.push(8),
+            b'f' => out.push(12),
+            b'n' | b'\n' => out.push(b'\n'),
+            b'r' => out.push(b'\r'),
+            b't' => out.push(b'\t'),
+            b'v' => out.push(11),
+            b'\\' | b'"' | b'\'' => out.push(c),
+            b'z' => {
+                while raw.get(i).is_some_and(|c| c.is_ascii_whitespace()) {
+                    i += 1;
+                }
+            }
+            b'x' => {
+                let hex = raw
+                    .get(i..i + 2)
+                    .and_then(|hex| str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| {
+                        Lua2JsonError::parse(
+                            "\\x must be followed by two hex digits",
+                            escape(i + 2),
+                        )
+                    })?;
+                out.push(hex);
+                i += 2;
+            }
+            b'0'..=b'9' => {
+                let len = raw[i..]
+                    .iter()
+                    .take(2)
+                    .take_while(|c| c.is_ascii_digit())
+                    .count();
+                let digits = str::from_utf8(&raw[i - 1..i + len]).expect("ascii");
+                let byte = digits.parse::<u8>().map_err(|_| {
+                    Lua2JsonError::parse("decimal escape larger than 255", escape(i + len))
+                })?;
+                out.push(byte);
+                i += len;
+            }
+            b'u' => {
+                let close = raw[i..].iter().position(|c| b'}' == *c);
+                let c = close
+                    .filter(|_| Some(&b'{') == raw.get(i))
+                    .and_then(|close| str::from_utf8(&raw[i + 1..i + close]).ok())
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| {
+                        let end = close.map_or(raw.len(), |close| i + close + 1);
+                        Lua2JsonError::parse("invalid \\u{...} escape", escape(end))
+                    })?;
+                out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                i += close.expect("checked") + 1;
+            }
+            other => {
+                return Err(Lua2JsonError::Unsupported {
+                    construct: format!("escape \\{}", other as char),
+                    span: escape(i),
+                })
+            }
+        }
+    }
+    out.extend_from_slice(&raw[i..]);
+    Ok(out)
+}
+
+/// A string value, or bytes if it's not valid UTF-8.
+pub(crate) fn string_value(bytes: Vec<u8>) -> Value {
+    match String::from_utf8(bytes) {
+        Ok(s) => Value::String(s),
+        Err(e) => Value::Bytes(e.into_bytes()),
+    }
+}
+
+fn string<'a>(input: &'a str, cx: &Context) -> IResult<&'a str, Value> {
+    map(
+        |i| quoted(i, cx),
+        |q| match q {
+            Quoted::Plain(s) => Value::String(s.to_string()),
+            Quoted::Decoded(bytes) => string_value(bytes),
+        },
+    )(input)
+}
+
+fn bool(input: &str) -> IResult<&str, Value> {
+    map_opt(plain_value_name, |name| match name {
+        "true" => Some(Value::Bool(true)),
+        "false" => Some(Value::Bool(false)),
+        _ => None,
+    })(input)
+}
+
+/// The spellings of infinity and NaN used by Lua serialisers, which write `tostring(x)` or an
+/// expression which evaluates to it, optionally negated.
+fn special_float(input: &str) -> IResult<&str, Value> {
+    let (rest, (minus, name)) = pair(
+        opt(char('-')),
+        alt((tag("math.huge"), tag("1/0"), tag("0/0"), plain_value_name)),
+    )(input)?;
+    let f = match special_name(name) {
+        // not the start of a longer number, like `1/0.5`
+        Some(f) if !rest.starts_with(|c: char| c.is_ascii_digit() || '.' == c) => f,
+        _ => return Err(nom::Err::Error(Error::new(input, ErrorKind::Tag))),
+    };
+    Ok((rest, Value::Float(if minus.is_some() { -f } else { f })))
+}
+
+pub(crate) fn special_name(name: &str) -> Option<f64> {
+    match name {
+        "math.huge" | "1/0" | "inf" => Some(f64::INFINITY),
+        "0/0" | "nan" => Some(f64::NAN),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn atom(input: &str) -> IResult<&str, Value> {
+    atom_with(input, &Context::new(&ParseOptions::default(), input))
+}
+
+fn atom_with<'a>(input: &'a str, cx: &Context) -> IResult<&'a str, Value> {
+    // the alternatives can mostly be told apart by their first character
+    match input.as_bytes().first() {
+        Some(b'"' | b'\'') => {
+            let (rest, value) = string(input, cx)?;
+            match (cx.opts.limits.max_string, value.as_bytes()) {
+                (Some(max), Some(bytes)) if bytes.len() > max => {
+                    cx.exceed(input, Limit::StringLength, max)
+                }
+                _ => Ok((rest, value)),
+            }
+        }
+        Some(b't' | b'f') => alt((bool, special_float, |i| variable(i, cx)))(input),
+        Some(b'0'..=b'9' | b'-') => alt((special_float, |i| num(i, cx)))(input),
+        _ => alt((special_float, |i| variable(i, cx)))(input),
+    }
+}
+
+/// A name standing for a value assigned earlier in the document, or found by
+/// [`ParseOptions::resolve`].
+fn variable<'a>(input: &'a str, cx: &Context) -> IResult<&'a str, Value> {
+    let (rest, name) = identifier(input, cx)?;
+    let assigned = cx.variables.as_ref().and_then(|variables| {
+        let variables = variables.borrow();
+        let found = variables.iter().rev().find(|(n, _)| n == name);
+        found.map(|(_, v)| v.clone())
+    });
+    let v = match assigned.or_else(|| cx.opts.resolve.and_then(|resolve| resolve(name))) {
+        Some(v) => v,
+        None if (cx.variables.is_some() || cx.opts.resolve.is_some()) && !cx.opts.lenient => {
+            let e = Lua2JsonError::parse(format!("undefined variable `{name}`"), 0..name.len());
+            return cx.fail(input, e);
+        }
+        None => return Err(nom::Err::Error(Error::new(input, ErrorKind::Verify))),
+    };
+    // a small document can repeat a large table many times
+    if let Some(max) = cx.opts.limits.max_entries {
+        let entries = cx.entries.get() + entry_count(&v);
+        if entries > max {
+            return cx.exceed(input, Limit::Entries, max);
+        }
+        cx.entries.set(entries);
+    }
+    Ok((rest, v))
+}
+
+/// The entries in a value's tables, however deeply nested.
+fn entry_count(v: &Value) -> usize {
+    v.values().map(|v| 1 + entry_count(v)).sum()
+}
+
+fn plain_value_name(input: &str) -> IResult<&str, &str> {
+    recognize(pair(
+        take_while1(|c: char| c.is_ascii_alphabetic() || '_' == c),
+        take_while(|c: char| c.is_ascii_alphanumeric() || '_' == c),
+    ))(input)
+}
+
+/// A name, which with [`ParseOptions::unicode_names`] can be any Unicode identifier.
+fn identifier<'a>(input: &'a str, cx: &Context) -> IResult<&'a str, &'a str> {
+    if !cx.opts.unicode_names {
+        return plain_value_name(input);
+    }
+    recognize(pair(
+        take_while1(|c: char| unicode_ident::is_xid_start(c) || '_' == c),
+        take_while(unicode_ident::is_xid_continue),
+    ))(input)
+}
+
+/// The digits of a key like `[1]`, which is named by them, like `["1"]` would be.
+fn integer_key(input: &str) -> IResult<&str, &str> {
+    delimited(
+        char('['),
+        take_while1(|c: char| c.is_ascii_digit()),
+        char(']'),
+    )(input)
+}
+
+fn bracketed_value_name<'a>(input: &'a str, cx: &Context) -> IResult<&'a str, String> {
+    delimited(char('['), |i| quoted_string(i, cx), char(']'))(input)
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn value_name(input: &str) -> IResult<&str, Key> {
+    let opts = ParseOptions::default();
+    let cx = Context::new(&opts, input);
+    let parsed = alt((
+        map(plain_value_name, Key::from),
+        map(|i| bracketed_value_name(i, &cx), Key::Name),
+        map(integer_key, |digits| Key::Integer(digits.to_string())),
+    ))(input);
+    parsed
+}
+
+/// Count another entry, failing if there are too many.
+fn count_entry<'a>(input: &'a str, cx: &Context) -> IResult<&'a str, ()> {
+    let entries = cx.entries.get() + 1;
+    match cx.opts.limits.max_entries {
+        Some(max) if entries > max => cx.exceed(input, Limit::Entries, max),
+        _ => {
+            cx.entries.set(entries);
+            Ok((input, ()))
+        }
+    }
+}
+
+/// The name of an entry, and the `=` after it, if it has one.
+fn entry_name<'a>(input: &'a str, cx: &Context) -> IResult<&'a str, Option<Key>> {
+    // only allocate names once they're known to be names rather than values like `true`
+    let name = alt((
+        map(|i| identifier(i, cx), |name| (Cow::Borrowed(name), false)),
+        map(
+            |i| bracketed_value_name(i, cx),
+            |name| (Cow::Owned(name), false),
+        ),
+        map(integer_key, |digits| (Cow::Borrowed(digits), true)),
+    ));
+    map(
+        opt(terminated(
+            delimited(ws, name, ws),
+            delimited(ws, char('='), ws),
+        )),
+        |name| {
+            name.map(|(name, integer)| match integer {
+                true => Key::Integer(name.into_owned()),
+                false => cx.name(name),
+            })
+        },
+    )(input)
+}
+
+fn maybe_named_value<'a>(input: &'a str, cx: &Context) -> IResult<&'a str, (Option<Key>, Value)> {
+    count_entry(input, cx)?;
+    let mark = cx.mark();
+    let result = pair(|i| entry_name(i, cx), delimited(ws, |i| value(i, cx), ws))(input);
+    cx.record_entry(mark, result.as_ref().ok().map(|(_, (_, v))| v));
+    result
+}
+
+/// An entry of a table which [`ParseOptions::only`] applies to, or `None` if it's skipped.
+fn selected_entry<'a>(
+    input: &'a str,
+    cx: &Context,
+) -> IResult<&'a str, Option<(Option<Key>, Value)>> {
+    count_entry(input, cx)?;
+    let mark = cx.mark();
+    let result = entry_name(input, cx).and_then(|(after, name)| {
+        let (rest, v) = delimited(ws, |i| selected_value(i, name.as_deref(), cx), ws)(after)?;
+        Ok((rest, v.map(|v| (name, v))))
+    });
+    let kept = match &result {
+        Ok((_, Some((_, v)))) => Some(v),
+        _ => None,
+    };
+    cx.record_entry(mark, kept);
+    result
+}
+
+/// Parse the value of an entry, or of an assignment, named `name`, if [`ParseOptions::only`]
+/// selects it, or skip over it and return `None`.
+fn selected_value<'a>(
+    input: &'a str,
+    name: Option<&str>,
+    cx: &Context,
+) -> IResult<&'a str, Option<Value>> {
+    let selected = cx.selecting.borrow().as_ref().map(|(paths, matched)| {
+        let on = paths
+            .iter()
+            .copied()
+            .filter(|&p| name == Some(cx.only[p][*matched]))
+            .collect::<Vec<_>>();
+        let whole = on.iter().any(|&p| cx.only[p].len() == matched + 1);
+        (on, matched + 1, whole)
+    });
+    let inside = match selected {
+        None => return map(|i| value(i, cx), Some)(input),
+        Some((on, _, _)) if on.is_empty() => return skip(input, cx),
+        Some((_, _, true)) => None,
+        Some((on, matched, false)) => Some((on, matched)),
+    };
+    let outside = cx.selecting.replace(inside);
+    let result = value(input, cx);
+    cx.selecting.replace(outside);
+    result.map(|(rest, v)| (rest, Some(v)))
+}
+
+/// Skip over the value at the start of `input`, which for a table is done by only matching up
+/// its brackets.
+fn skip<'a, T>(input: &'a str, cx: &Context) -> IResult<&'a str, Option<T>> {
+    match table_len(input) {
+        Some(len) => Ok((&input[len..], None)),
+        None => value(input, cx).map(|(rest, _)| (rest, None)),
+    }
+}
+
+/// The length of the table at the start of `input`, found by matching up its braces outside
+/// of strings, or `None` if it isn't a table or they don't match up.
+fn table_len(input: &str) -> Option<usize> {
+    let bytes = input.as_bytes();
+    if Some(&b'{') != bytes.first() {
+        return None;
+    }
+    let mut depth = 0usize;
+    let mut i = 0;
+    while let Some(at) = bytes[i..]
+        .iter()
+        .position(|c| matches!(c, b'"' | b'\'' | b'{' | b'}'))
+    {
+        i += at;
+        match bytes[i] {
+            quote @ (b'"' | b'\'') => loop {
+                i += 1 + memchr::memchr2(quote, b'\\', &bytes[i + 1..])?;
+                if quote == bytes[i] {
+                    break;
+                }
+                i += 1;
+            },
+            b'{' => depth += 1,
+            _ => {
+                depth -= 1;
+                if 0 == depth {
+                    return Some(i + 1);
+                }
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+/// The entries of a table, between its braces, without any trailing comma.
+fn table_entries<'a>(input: &'a str, cx: &Context) -> IResult<&'a str, Table> {
+    let comma = |i| delimited(ws, char(','), ws)(i);
+    let selecting = cx.selecting.borrow().is_some();
+    match selecting {
+        true => map(
+            separated_list0(comma, |i| selected_entry(i, cx)),
+            |entries| entries.into_iter().flatten().collect(),
+        )(input),
+        false => separated_list0(comma, |i| maybe_named_value(i, cx))(input),
+    }
+}
+
+fn table<'a>(input: &'a str, cx: &Context) -> IResult<&'a str, Value> {
+    let depth = cx.depth.get() + 1;
+    if let Some(max) = cx.opts.limits.max_depth.filter(|&max| depth > max) {
+        return cx.exceed(input, Limit::Depth, max);
+    }
+    cx.depth.set(depth);
+    let (mark, entries) = (cx.mark(), cx.entries.get());
+    let result = map(
+        delimited(
+            delimited(ws, char('{'), ws),
+            terminated(|i| table_entries(i, cx), opt(char(','))),
+            delimited(ws, char('}'), ws),
+        ),
+        Value::Object,
+    )(input);
+    cx.depth.set(depth - 1);
+    if let Some(nodes) = &cx.nodes {
+        let mut nodes = nodes.borrow_mut();
+        let children = nodes.split_off(mark);
+        if let Ok((rest, _)) = &result {
+            let at = cx.offset(input);
+            nodes.push(Some(Node {
+                span: at..at + span(input, rest).end,
+                entries: cx.entries.get() - entries,
+                children,
+            }));
+        }
+    }
+    result
+}
+
+fn value<'a>(input: &'a str, cx: &Context) -> IResult<&'a str, Value> {
+    let (rest, first) = match operand(input, cx) {
+        Err(nom::Err::Error(_)) if cx.opts.lenient => return raw(input, cx),
+        Err(nom::Err::Error(e)) => match terminated(|i| identifier(i, cx), dots)(input) {
+            Ok(_) => return concatenated_name(input, cx),
+            Err(_) => {
+                cx.furthest.set(cx.furthest.get().max(cx.offset(e.input)));
+                return Err(nom::Err::Error(e));
+            }
+        },
+        parsed => parsed?,
+    };
+    match dots(rest) {
+        Ok(_) => concatenation(input, rest, first, cx),
+        Err(_) => Ok((rest, first)),
+    }
+}
+
+fn operand<'a>(input: &'a str, cx: &Context) -> IResult<&'a str, Value> {
+    match input.as_bytes().first() {
+        Some(b'{') => table(input, cx),
+        _ if cx.opts.arithmetic => sum(input, cx),
+        _ => atom_with(input, cx),
+    }
+}
+
+// sum: product ([+-] product)*
+// product: unary ([*/%] unary)*
+// unary: - unary | power
+// power: primary (^ unary)?
+// primary: ( sum ) | table | atom
+
+fn sum<'a>(input: &'a str, cx: &Context) -> IResult<&'a str, Value> {
+    binary(input, cx, "+-", product)
+}
+
+fn product<'a>(input: &'a str, cx: &Context) -> IResult<&'a str, Value> {
+    binary(input, cx, "*/%", unary)
+}
+
+/// Left associative operators out of `ops` between `operand`s.
+fn binary<'a>(
+    input: &'a str,
+    cx: &Context,
+    ops: &str,
+    operand: fn(&'a str, &Context) -> IResult<&'a str, Value>,
+) -> IResult<&'a str, Value> {
+    let (mut rest, mut v) = operand(input, cx)?;
+    while let Ok((after, op)) = delimited(ws, one_of(ops), ws)(rest) {
+        let (end, rhs) = operand(after, cx)?;
+        let a = arithmetic_operand(input, rest, &v, cx)?;
+        let b = arithmetic_operand(after, end, &rhs, cx)?;
+        v = Value::Float(match op {
+            '+' => a + b,
+            '-' => a - b,
+            '*' => a * b,
+            '/' => a / b,
+            _ => {
+                // like Lua, the result has the sign of the divisor
+                let m = libm::fmod(a, b);
+                if (m > 0. && b < 0.) || (m < 0. && b > 0.) {
+                    m + b
+                } else {
+                    m
+                }
+            }
+        });
+        rest = end;
+    }
+    Ok((rest, v))
+}
+
+fn unary<'a>(input: &'a str, cx: &Context) -> IResult<&'a str, Value> {
+    let Some(after) = input.strip_prefix('-') else {
+        return power(input, cx);
+    };
+    // a negative number is a literal, unless it's raised to a power, which binds tighter
+    if let Ok((rest, v)) = atom_with(input, cx) {
+        if v.as_f64().is_some() && carets(rest).is_err() {
+            return Ok((rest, v));
+        }
+    }
+    let (after, _) = ws(after)?;
+    let (rest, v) = unary(after, cx)?;
+    let f = arithmetic_operand(after, rest, &v, cx)?;
+    Ok((rest, Value::Float(-f)))
+}
+
+fn carets(input: &str) -> IResult<&str, char> {
+    delimited(ws, char('^'), ws)(input)
+}
+
+fn power<'a>(input: &'a str, cx: &Context) -> IResult<&'a str, Value> {
+    let (rest, v) = match input.as_bytes().first() {
+        Some(b'(') => parenthesised(input, cx)?,
+        Some(b'{') => table(input, cx)?,
+        _ => atom_with(input, cx)?,
+    };
+    let Ok((after, _)) = carets(rest) else {
+        return Ok((rest, v));
+    };
+    // right associative, so 2^3^2 is 2^9
+    let (end, exponent) = unary(after, cx)?;
+    let a = arithmetic_operand(input, rest, &v, cx)?;
+    let b = arithmetic_operand(after, end, &exponent, cx)?;
+    Ok((end, Value::Float(libm::pow(a, b))))
+}
+
+fn parenthesised<'a>(input: &'a str, cx: &Context) -> IResult<&'a str, Value> {
+    let depth = cx.depth.get() + 1;
+    if let Some(max) = cx.opts.limits.max_depth.filter(|&max| depth > max) {
+        return cx.exceed(input, Limit::Depth, max);
+    }
+    cx.depth.set(depth);
+    let result = delimited(pair(char('('), ws), |i| sum(i, cx), pair(ws, char(')')))(input);
+    cx.depth.set(depth - 1);
+    result
+}
+
+/// The number `v`, parsed from `start` up to `rest`, to do arithmetic on.
+fn arithmetic_operand<'a>(
+    start: &'a str,
+    rest: &str,
+    v: &Value,
+    cx: &Context,
+) -> Result<f64, nom::Err<Error<&'a str>>> {
+    match v.as_f64() {
+        Some(f) => Ok(f),
+        None => {
+            let construct = format!("arithmetic on a {}", v.type_name());
+            let span = span(start, rest);
+            cx.fail(start, Lua2JsonError::Unsupported { construct, span })
+                .map(|(_, f)| f)
+        }
+    }
+}
+
+/// The span of the value parsed from `start` up to `rest`, relative to `start`.
+fn span(start: &str, rest: &str) -> Range<usize> {
+    // tables take the whitespace after them
+    0..start[..start.len() - rest.len()].trim_end().len()
+}
+
+/// The concatenation operator, and the whitespace around it.
+fn dots(input: &str) -> IResult<&str, &str> {
+    delimited(ws, tag(".."), ws)(input)
+}
+
+/// Fold the value `first`, from `input` up to `rest`, and the values concatenated onto it
+/// with `..` into one string, when they're all strings.
+fn concatenation<'a>(
+    input: &'a str,
+    rest: &'a str,
+    first: Value,
+    cx: &Context,
+) -> IResult<&'a str, Value> {
+    let mut bytes = Vec::new();
+    let (mut start, mut rest, mut v) = (input, rest, first);
+    loop {
+        match v.as_bytes() {
+            Some(b) => bytes.extend_from_slice(b),
+            None => {
+                let construct = format!("concatenation of a {}", v.type_name());
+                let span = span(start, rest);
+                return cx.fail(start, Lua2JsonError::Unsupported { construct, span });
+            }
         }
+        let Ok((next, _)) = dots(rest) else {
+            break;
+        };
+        let (after, value) = match operand(next, cx) {
+            Err(nom::Err::Error(_)) if identifier(next, cx).is_ok() => {
+                return concatenated_name(next, cx)
+            }
+            parsed => parsed?,
+        };
+        (start, rest, v) = (next, after, value);
     }
-    pub fn f64(&self) -> Result<f64> {
-        match self {
-            Value::Float(t) => Ok(*t),
-            _ => Err(anyhow!("expected float but found {self:?}")),
-        }
+    if let Some(max) = cx.opts.limits.max_string.filter(|&max| bytes.len() > max) {
+        return cx.exceed(input, Limit::StringLength, max);
     }
-    pub fn get(&self, key: &str) -> Result<Value> {
-        Ok(self
-            .table()?
-            .iter()
-            .find(|(k, _)| *k == Some(key.to_string()))
-            .ok_or(anyhow!("no matching key"))?
-            .1
-            .clone())
+    Ok((rest, string_value(bytes)))
+}
+
+/// Fail on the name at the start of `input`, which is being concatenated, like `prefix` in
+/// `prefix .. "x"`, as only literals can be folded.
+fn concatenated_name<'a, T>(input: &'a str, cx: &Context) -> IResult<&'a str, T> {
+    let (_, name) = identifier(input, cx)?;
+    let construct = format!("concatenation of `{name}`, which isn't a literal");
+    let span = 0..name.len();
+    cx.fail(input, Lua2JsonError::Unsupported { construct, span })
+}
+
+/// The source of the value at the start of `input`, with a warning, for
+/// [`ParseOptions::lenient`].
+fn raw<'a>(input: &'a str, cx: &Context) -> IResult<&'a str, Value> {
+    let text = input[..raw_len(input)].trim_end();
+    if text.is_empty() {
+        return Err(nom::Err::Error(Error::new(input, ErrorKind::Fail)));
     }
+    let at = cx.offset(input);
+    cx.warnings.borrow_mut().push(Warning {
+        message: "unsupported value kept as raw text".to_string(),
+        span: at..at + text.len(),
+    });
+    Ok((&input[text.len()..], Value::Raw(text.to_string())))
 }
 
-// atom: number or string
-// table: { (label? value), * }
-// value = atom | table
+/// The length of the value at the start of `input`, up to the first `,` or closing
+/// bracket which isn't inside brackets, a string or a block like `function ... end`.
+fn raw_len(input: &str) -> usize {
+    let bytes = input.as_bytes();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while let Some(&c) = bytes.get(i) {
+        match c {
+            b'"' | b'\'' => {
+                i += 1;
+                while let Some(&d) = bytes.get(i) {
+                    i += if b'\\' == d { 2 } else { 1 };
+                    if c == d {
+                        break;
+                    }
+                }
+                continue;
+            }
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' | b',' if 0 == depth => break,
+            b')' | b']' | b'}' => depth -= 1,
+            c if c.is_ascii_alphabetic() || b'_' == c => {
+                let (_, word) = plain_value_name(&input[i..]).expect("starts with a letter");
+                match word {
+                    "function" | "do" | "if" | "repeat" => depth += 1,
+                    "end" | "until" => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+                i += word.len();
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    i.min(bytes.len())
+}
 
-fn ws(input: &str) -> IResult<&str, &str> {
-    multispace0(input)
+pub fn parse(s: &str) -> Result<Table> {
+    parse_with(s, &ParseOptions::default())
 }
 
-fn num(input: &str) -> IResult<&str, Value> {
-    let (rest, v) = recognize(tuple((
-        opt(char('-')),
-        digit1,
-        opt(tuple((char('.'), digit1))),
-    )))(input)?;
-    Ok((rest, Value::Float(v.parse::<f64>().expect("close enough"))))
+/// Parse a table from `bytes` in the given encoding, which is converted to UTF-8 first.
+pub fn parse_bytes(bytes: &[u8], encoding: Encoding) -> Result<Table> {
+    parse(&decode(bytes, encoding)?)
 }
 
-fn quoted_string(input: &str) -> IResult<&str, String> {
-    delimited(
-        char('"'),
-        escaped_transform(
-            none_of("\\\n\""),
-            '\\',
-            alt((nom::combinator::value("\"", tag("\"")),)),
-        ),
-        char('"'),
-    )(input)
+/// Parse any value, like `"text"`, `1.5` or `{1, 2}`, rather than only a table.
+pub fn parse_value(s: &str) -> Result<Value> {
+    let opts = ParseOptions::default();
+    let cx = Context::new(&opts, s);
+    let parsed = delimited(ws, |i| value(i, &cx), ws)(s);
+    match parsed {
+        Ok(("", v)) => Ok(v),
+        Ok((rest, _)) => Err(trailing(&cx, rest)),
+        Err(e) => Err(cx.error(e)),
+    }
 }
 
-fn string(input: &str) -> IResult<&str, Value> {
-    map(quoted_string, |v: String| Value::String(v))(input)
+fn trailing(cx: &Context, rest: &str) -> Lua2JsonError {
+    let at = cx.offset(rest);
+    Lua2JsonError::parse("unexpected trailing data", at..cx.len)
 }
 
-fn bool(input: &str) -> IResult<&str, Value> {
-    alt((
-        map(tag("true"), |_| Value::Bool(true)),
-        map(tag("false"), |_| Value::Bool(false)),
-    ))(input)
+/// Parse a table, like [`parse`], with options.
+///
+/// Exceeding one of the [`ParseOptions::limits`] fails with [`Lua2JsonError::LimitExceeded`].
+pub fn parse_with(s: &str, opts: &ParseOptions) -> Result<Table> {
+    parse_with_warnings(s, opts).map(|(t, _)| t)
 }
 
-fn atom(input: &str) -> IResult<&str, Value> {
-    alt((num, string, bool))(input)
+/// Parse a table, like [`parse_with`], along with warnings about the values which
+/// [`ParseOptions::lenient`] kept as [`Value::Raw`].
+pub fn parse_with_warnings(s: &str, opts: &ParseOptions) -> Result<(Table, Vec<Warning>)> {
+    check_input(s, opts)?;
+    let cx = Context::new(opts, s);
+    match value(s, &cx) {
+        Ok(("", Value::Object(t))) => Ok((t, cx.warnings.take())),
+        Ok((rest, Value::Object(_))) => Err(trailing(&cx, rest)),
+        Ok((rest, _)) => Err(not_a_table(&cx, s, rest)),
+        Err(e) => Err(cx.error(e)),
+    }
 }
 
-fn plain_value_name(input: &str) -> IResult<&str, &str> {
-    take_while1(|c: char| c.is_ascii_alphabetic() || '_' == c)(input)
+/// Parse a table, like [`parse_with`], along with its [`Node`], as though it were at `depth`
+/// in a document which already has `entries` entries outside of it, for the limits.
+#[cfg(feature = "std")]
+pub(crate) fn parse_node(
+    s: &str,
+    opts: &ParseOptions,
+    depth: usize,
+    entries: usize,
+) -> Result<(Table, Node)> {
+    let mut cx = Context::new(opts, s);
+    cx.depth.set(depth);
+    cx.entries.set(entries);
+    cx.nodes = Some(RefCell::new(Vec::new()));
+    match value(s, &cx) {
+        Ok(("", Value::Object(t))) => {
+            let node = cx
+                .nodes
+                .take()
+                .and_then(|nodes| nodes.into_inner().pop().flatten());
+            Ok((t, node.expect("a table was parsed")))
+        }
+        Ok((rest, Value::Object(_))) => Err(trailing(&cx, rest)),
+        Ok((rest, _)) => Err(not_a_table(&cx, s, rest)),
+        Err(e) => Err(cx.error(e)),
+    }
 }
 
-fn bracketed_value_name(input: &str) -> IResult<&str, String> {
-    delimited(char('['), quoted_string, char(']'))(input)
+/// Parse some of the entries of a table, without its braces, as though they were inside of a
+/// table being parsed like [`parse_with_warnings`], for parsing a table in parts.
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub(crate) fn parse_entries(s: &str, opts: &ParseOptions) -> Result<(Table, Vec<Warning>)> {
+    let cx = Context::new(opts, s);
+    cx.depth.set(1);
+    let result = terminated(|i| table_entries(i, &cx), pair(opt(char(',')), ws))(s);
+    match result {
+        Ok(("", t)) => Ok((t, cx.warnings.take())),
+        Ok((rest, _)) => Err(trailing(&cx, rest)),
+        Err(e) => Err(cx.error(e)),
+    }
 }
 
-fn value_name(input: &str) -> IResult<&str, String> {
-    alt((
-        map(plain_value_name, |s| s.to_string()),
-        bracketed_value_name,
-    ))(input)
+fn check_input(s: &str, opts: &ParseOptions) -> Result<()> {
+    match opts.limits.max_input {
+        Some(max) if s.len() > max => Err(LimitExceeded {
+            limit: Limit::InputSize,
+            max,
+        }
+        .into()),
+        _ => Ok(()),
+    }
 }
 
-fn maybe_named_value(input: &str) -> IResult<&str, (Option<String>, Value)> {
-    pair(
-        opt(terminated(
-            delimited(ws, value_name, ws),
-            delimited(ws, char('='), ws),
-        )),
-        delimited(ws, value, ws),
-    )(input)
+/// The error for a value other than a table, parsed from `start` up to `rest`.
+fn not_a_table(cx: &Context, start: &str, rest: &str) -> Lua2JsonError {
+    let span = span(start, rest);
+    let at = cx.offset(start);
+    Lua2JsonError::parse("expected a table", at + span.start..at + span.end)
 }
 
-fn table(input: &str) -> IResult<&str, Value> {
-    map(
-        delimited(
-            delimited(ws, char('{'), ws),
-            terminated(
-                separated_list0(delimited(ws, char(','), ws), maybe_named_value),
-                opt(char(',')),
-            ),
-            delimited(ws, char('}'), ws),
-        ),
-        |pairs| {
-            Value::Object(
-                pairs
-                    .into_iter()
-                    .map(|(s, v)| (s.map(|s| s.to_string()), v))
-                    .collect(),
-            )
-        },
-    )(input)
+/// Parse a chunk of Lua assignments, where values can refer to the names assigned before them:
+///
+/// ```
+/// use lua2json::parse::{parse_document, ParseOptions, Value};
+///
+/// let doc = "local W = 1920\nwidth = W; title = \"main\"";
+/// let table = Value::Object(parse_document(doc, &ParseOptions::default()).unwrap());
+/// assert_eq!(Some(1920.), table["width"].as_f64());
+/// assert_eq!(None, table.get_opt("W"));
+/// ```
+///
+/// The result is the entries assigned without `local`, in the order they were first assigned,
+/// or the table after a final `return`, if there is one.
+pub fn parse_document(s: &str, opts: &ParseOptions) -> Result<Table> {
+    check_input(s, opts)?;
+    let mut cx = Context::new(opts, s);
+    cx.variables = Some(RefCell::new(Vec::new()));
+    let separator = |i| pair(ws, opt(pair(char(';'), ws)))(i);
+    let mut globals = Table::new();
+    let (mut rest, _) = ws(s).map_err(|e| cx.error(e))?;
+    while !rest.is_empty() {
+        let (after, mut name) = identifier(rest, &cx).map_err(|e| cx.error(e))?;
+        if "return" == name {
+            let (after, _) = ws(after).map_err(|e| cx.error(e))?;
+            let (end, v) = value(after, &cx).map_err(|e| cx.error(e))?;
+            let Value::Object(t) = v else {
+                return Err(not_a_table(&cx, after, end));
+            };
+            return match separator(end).map_err(|e| cx.error(e))? {
+                ("", _) => Ok(t),
+                (rest, _) => Err(trailing(&cx, rest)),
+            };
+        }
+        let mut after = after;
+        let local = "local" == name;
+        if local {
+            (after, name) = preceded(ws, |i| identifier(i, &cx))(after).map_err(|e| cx.error(e))?;
+        }
+        let (after, _) = delimited(ws, char('='), ws)(after).map_err(|e| cx.error(e))?;
+        let (end, v) = match local {
+            // as what's kept can refer to locals
+            true => {
+                let outside = cx.selecting.take();
+                let result = value(after, &cx);
+                cx.selecting.replace(outside);
+                result.map(|(end, v)| (end, Some(v)))
+            }
+            false => selected_value(after, Some(name), &cx),
+        }
+        .map_err(|e| cx.error(e))?;
+        if let Some(v) = v {
+            if !local {
+                let key = cx.name(Cow::Borrowed(name));
+                match globals.iter_mut().find(|(k, _)| k.as_ref() == Some(&key)) {
+                    Some((_, existing)) => *existing = v.clone(),
+                    None => globals.push((Some(key), v.clone())),
+                }
+            }
+            let variables = cx.variables.as_ref().expect("parsing a document");
+            variables.borrow_mut().push((name.to_string(), v));
+        }
+        (rest, _) = separator(end).map_err(|e| cx.error(e))?;
+    }
+    Ok(globals)
 }
 
-fn value(input: &str) -> IResult<&str, Value> {
-    alt((atom, table))(input)
+/// Parse a sequence of documents, like tables saved one after another into the same file:
+/// each is an assignment, like `AccountData = {...}`, or a table on its own, separated by
+/// whitespace, `--` comments or `;`.
+///
+/// ```
+/// use lua2json::parse::{parse_multi, ParseOptions, Value};
+///
+/// let docs = "AccountData = {id = 1}\n-- later\nAccountData = {id = 2}\n{3}";
+/// let docs = parse_multi(docs, &ParseOptions::default()).unwrap();
+/// assert_eq!(Some("AccountData"), docs[1].0.as_deref());
+/// assert_eq!(Some(2.), docs[1].1["id"].as_f64());
+/// assert_eq!(None, docs[2].0);
+/// ```
+///
+/// Each assignment is an entry named by its name, even when a name is repeated, and each table
+/// on its own is a positional entry.
+pub fn parse_multi(s: &str, opts: &ParseOptions) -> Result<Table> {
+    check_input(s, opts)?;
+    let cx = Context::new(opts, s);
+    let assignment = |i| -> IResult<&str, Option<(Option<Key>, Value)>> {
+        let (i, name) = terminated(|i| identifier(i, &cx), delimited(ws, char('='), ws))(i)?;
+        let (end, v) = selected_value(i, Some(name), &cx)?;
+        Ok((end, v.map(|v| (Some(cx.name(Cow::Borrowed(name))), v))))
+    };
+    let mut documents = Table::new();
+    let mut rest = between_documents(s, &cx)?;
+    while !rest.is_empty() {
+        let (end, document) = match assignment(rest) {
+            Ok(assigned) => assigned,
+            Err(nom::Err::Error(_)) if rest.starts_with('{') => table(rest, &cx)
+                .map(|(end, v)| (end, Some((None, v))))
+                .map_err(|e| cx.error(e))?,
+            Err(e) => return Err(cx.error(e)),
+        };
+        documents.extend(document);
+        rest = between_documents(end, &cx)?;
+    }
+    Ok(documents)
 }
 
-pub fn parse(s: &str) -> Result<Table> {
-    match value(s).map_err(|e| anyhow!("{e:?}"))? {
-        ("", Value::Object(t)) => Ok(t),
-        (rest, Value::Object(_)) => bail!("unexpected trailing data: {rest:?})"),
-        _ => bail!("unexpected non-object"),
+/// Skip the whitespace, comments and semicolons at the start of `input`.
+fn between_documents<'a>(mut input: &'a str, cx: &Context) -> Result<&'a str> {
+    loop {
+        input = input.trim_start_matches([' ', '\t', '\n', '\r', ';']);
+        if !input.starts_with("--") {
+            return Ok(input);
+        }
+        match lex::comment(input) {
+            (TokenKind::Error, _) => {
+                let at = cx.offset(input);
+                return Err(Lua2JsonError::parse("unterminated comment", at..cx.len));
+            }
+            (_, len) => input = &input[len..],
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::parse::{parse, string, Table, Value};
-    use anyhow::Result;
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    use crate::encoding::Encoding;
+    use crate::error::{line_column, AccessError, Limit, LimitExceeded, Lua2JsonError};
+    use crate::parse::{
+        atom, parse, parse_bytes, parse_document, parse_multi, parse_value, parse_with,
+        parse_with_warnings, Key, Limits, ParseOptions, Table, Value,
+    };
 
     #[test]
     fn simple() {
         assert_eq!(Table::new(), parse("{}").unwrap());
         assert_eq!(
-            vec![(Some("a".to_string()), Value::Float(5.))],
+            vec![(Some("a".into()), Value::Float(5.))],
             parse("{a=5}").unwrap()
         );
 
         assert_eq!(
-            vec![(Some("abc".to_string()), Value::Float(5.))],
+            vec![(Some("abc".into()), Value::Float(5.))],
             parse("{abc=5}").unwrap()
         );
 
         assert_eq!(
-            vec![(Some("a".to_string()), Value::Float(5.5))],
+            vec![(Some("a".into()), Value::Float(5.5))],
             parse("{a=5.5}").unwrap()
         );
         assert_eq!(
-            vec![(Some("a".to_string()), Value::String("hello".to_string()))],
+            vec![(Some("a".into()), Value::String("hello".to_string()))],
             parse("{a=\"hello\"}").unwrap()
         );
         assert_eq!(
             vec![
-                (Some("a".to_string()), Value::Float(5.)),
-                (Some("b".to_string()), Value::Float(6.))
+                (Some("a".into()), Value::Float(5.)),
+                (Some("b".into()), Value::Float(6.))
             ],
             parse("{a=5,b=6}").unwrap()
         );
 
         assert_eq!(
             vec![
-                (Some("a".to_string()), Value::Float(5.)),
-                (Some("b".to_string()), Value::Float(6.))
+                (Some("a".into()), Value::Float(5.)),
+                (Some("b".into()), Value::Float(6.))
             ],
             parse("{a=5,b=6 ,}").unwrap()
         );
@@ -195,35 +1734,743 @@ mod tests {
         assert_eq!(
             vec![(
                 None,
-                Value::Object(vec![(Some("a".to_string()), Value::Float(5.))])
+                Value::Object(vec![(Some("a".into()), Value::Float(5.))])
             )],
             parse("{{a=5}}").unwrap()
         );
 
         assert_eq!(
-            vec![(Some("a_b".to_string()), Value::Float(5.))],
+            vec![(Some("a_b".into()), Value::Float(5.))],
             parse("{a_b=5}").unwrap()
         );
+        assert_eq!(
+            vec![(Some("a1".into()), Value::Float(5.))],
+            parse("{a1=5}").unwrap()
+        );
 
         assert_eq!(
-            vec![(Some("a".to_string()), Value::Float(5.))],
+            vec![(Some("a".into()), Value::Float(5.))],
             parse(r#"{["a"]=5}"#).unwrap()
         );
         assert_eq!(
-            vec![(Some("a".to_string()), Value::Bool(true))],
+            vec![(Some("a".into()), Value::Bool(true))],
             parse(r#"{["a"]=true}"#).unwrap()
         );
     }
 
+    #[test]
+    fn arrays() {
+        let v = Value::Object(parse(r#"{"a", {}, x = 1, "b"}"#).unwrap());
+        assert_eq!(4, v.len());
+        assert_eq!(&Value::String("b".to_string()), v.index(2).unwrap());
+        assert!(v.index(3).is_err());
+        assert!(Value::Bool(true).index(0).is_err());
+        assert!(!v.is_array_like());
+        assert_eq!(None, v.as_array());
+
+        let v = Value::Object(parse(r#"{"a", {}}"#).unwrap());
+        assert!(v.is_array_like());
+        assert_eq!(
+            Some(vec![
+                &Value::String("a".to_string()),
+                &Value::Object(Table::new())
+            ]),
+            v.as_array()
+        );
+        assert!(v.index(1).unwrap().is_empty());
+        assert!(!Value::Float(1.).is_array_like());
+    }
+
+    #[test]
+    fn mutation() {
+        let mut v = Value::Object(parse(r#"{a = 1, "x", b = {c = 2}}"#).unwrap());
+        assert_eq!(
+            Some(Value::Float(1.)),
+            v.set("a", Value::Bool(true)).unwrap()
+        );
+        assert_eq!(None, v.set("d", Value::Float(3.)).unwrap());
+        *v.get_mut("b").unwrap().get_mut("c").unwrap() = Value::Float(4.);
+        assert_eq!(Some(Value::Float(3.)), v.remove("d").unwrap());
+        assert_eq!(None, v.remove("d").unwrap());
+        v.push(Value::String("z".to_string())).unwrap();
+        v.insert(0, Value::String("w".to_string())).unwrap();
+        v.insert(3, Value::String("end".to_string())).unwrap();
+        assert!(v.insert(5, Value::Bool(false)).is_err());
+        assert_eq!(
+            parse(r#"{a = true, "w", "x", b = {c = 4}, "z", "end"}"#).unwrap(),
+            *v.table().unwrap()
+        );
+        assert!(v.get_mut("nope").is_err());
+        assert!(Value::Bool(true).push(Value::Bool(false)).is_err());
+    }
+
+    #[test]
+    fn index_ops() {
+        let mut v = Value::Object(parse(r#"{config = {width = 5, "a"}, "b"}"#).unwrap());
+        assert_eq!(Value::Float(5.), v["config"]["width"]);
+        assert_eq!(Value::String("a".to_string()), v["config"][0]);
+        assert_eq!(Value::String("b".to_string()), v[0]);
+        v["config"]["width"] = Value::Float(6.);
+        v[0] = Value::Bool(true);
+        assert_eq!(
+            parse(r#"{config = {width = 6, "a"}, true}"#).unwrap(),
+            *v.table().unwrap()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "no matching key")]
+    fn index_missing() {
+        let v = Value::Object(parse("{a = 1}").unwrap());
+        let _ = &v["b"];
+    }
+
+    #[test]
+    fn accessors() {
+        let v = Value::Object(parse(r#"{s = "x", f = 1.5, b = true}"#).unwrap());
+        assert_eq!(Some("x"), v["s"].as_str());
+        assert_eq!(Some(1.5), v["f"].as_f64());
+        assert_eq!(Some(true), v["b"].as_bool());
+        assert_eq!(None, v["s"].as_f64());
+        assert_eq!(Some(3), v.as_table().map(|t| t.len()));
+        assert_eq!(None, v["b"].as_table());
+        assert_eq!("x", v.get("s").unwrap().string().unwrap());
+        assert!(v.get("f").unwrap().string().is_err());
+    }
+
+    #[test]
+    fn access_errors() {
+        let v = Value::Object(parse(r#"{a = 1, "x"}"#).unwrap());
+        assert_eq!(Some(&Value::Float(1.)), v.get_opt("a"));
+        assert_eq!(None, v.get_opt("b"));
+        assert_eq!(None, Value::Bool(true).get_opt("a"));
+        assert_eq!(
+            AccessError::KeyNotFound {
+                key: "b".to_string()
+            },
+            v.get("b").unwrap_err()
+        );
+        assert_eq!(
+            AccessError::TypeMismatch {
+                expected: "table",
+                found: "number"
+            },
+            v["a"].get("b").unwrap_err()
+        );
+        assert_eq!(
+            AccessError::IndexOutOfRange { index: 1, len: 1 },
+            v.index(1).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn iterators() {
+        let v = Value::Object(parse(r#"{a = 1, "x"}"#).unwrap());
+        assert_eq!(
+            vec![
+                (Some("a"), &Value::Float(1.)),
+                (None, &Value::String("x".to_string()))
+            ],
+            v.entries().collect::<Vec<_>>()
+        );
+        assert_eq!(vec![Some("a"), None], v.keys().collect::<Vec<_>>());
+        assert_eq!(2, v.values().count());
+        assert_eq!(2, (&v).into_iter().count());
+        assert_eq!(0, Value::Bool(true).entries().count());
+        assert_eq!(0, Value::Bool(true).into_iter().count());
+        assert_eq!(
+            vec![
+                (Some("a".into()), Value::Float(1.)),
+                (None, Value::String("x".to_string()))
+            ],
+            v.into_entries().collect::<Vec<_>>()
+        );
+    }
+
     #[test]
     fn escaped_strings() {
         assert_eq!(
             ("", Value::String("hello".to_string())),
-            string("\"hello\"").unwrap()
+            atom("\"hello\"").unwrap()
         );
         assert_eq!(
             ("", Value::String("he\"llo".to_string())),
-            string("\"he\\\"llo\"").unwrap()
+            atom("\"he\\\"llo\"").unwrap()
+        );
+        assert_eq!(("", Value::String(String::new())), atom("\"\"").unwrap());
+        assert_eq!(
+            (
+                "",
+                Value::String("\u{7}\u{8}\u{c}\n\r\t\u{b}\\'\nAA\u{1}2é€".to_string())
+            ),
+            atom(
+                r#""\a\b\f\n\r\t\v\\\'\
+\x41\65\0012\u{e9}\z   \u{20AC}""#
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            ("", Value::Bytes(b"\xff\x00".to_vec())),
+            atom(r#""\xff\0""#).unwrap()
+        );
+        for bad in [
+            r#""\q""#,
+            r#""\x4""#,
+            r#""\256""#,
+            r#""\u{110000}""#,
+            r#""\u{41""#,
+        ] {
+            assert!(atom(bad).is_err(), "{bad}");
+        }
+        assert!(parse(r#"{["\xff"] = 1}"#).is_err());
+        let v = Value::Object(parse(r#"{["\x61"] = "\xff"}"#).unwrap());
+        assert_eq!(Some(&b"\xff"[..]), v["a"].as_bytes());
+        assert_eq!(None, v["a"].as_str());
+        assert_eq!("string", v["a"].type_name());
+    }
+
+    #[test]
+    fn single_quoted_strings() {
+        assert_eq!(
+            ("", Value::String("it's \"x\"".to_string())),
+            atom(r#"'it\'s "x"'"#).unwrap()
+        );
+        assert_eq!(
+            parse(r#"{["a b"] = "c", "}"}"#).unwrap(),
+            parse(r#"{['a b'] = 'c', '}'}"#).unwrap()
+        );
+        let opts = ParseOptions {
+            only: vec!["b".to_string()],
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            parse("{b = 1}").unwrap(),
+            parse_with("{a = {'}'}, b = 1}", &opts).unwrap()
+        );
+        assert!(atom("'a\"").is_err());
+    }
+
+    #[test]
+    fn special_floats() {
+        let v = Value::Object(
+            parse("{math.huge, -math.huge, 1/0, -1/0, 0/0, -nan, inf, inf = 1, 10}").unwrap(),
+        );
+        let floats = v.values().map(|v| v.as_f64().unwrap()).collect::<Vec<_>>();
+        assert_eq!(
+            vec![f64::INFINITY, -f64::INFINITY, f64::INFINITY, -f64::INFINITY],
+            floats[..4]
+        );
+        assert!(floats[4].is_nan() && floats[5].is_nan());
+        assert_eq!(vec![f64::INFINITY, 1., 10.], floats[6..]);
+        assert_eq!(Some("inf"), v.keys().nth(7).unwrap());
+        assert!(parse("{2/0}").is_err());
+        assert!(parse("{math.tiny}").is_err());
+        assert!(parse("{-true}").is_err());
+    }
+
+    #[test]
+    fn exponents() {
+        let v = Value::Object(parse("{1e3, -2.5E-2, 1e+2}").unwrap());
+        let floats = v.values().map(|v| v.as_f64().unwrap()).collect::<Vec<_>>();
+        assert_eq!(vec![1000., -0.025, 100.], floats);
+        assert!(parse("{1e}").is_err());
+        assert_eq!(
+            Err(Lua2JsonError::parse("number out of range", 4..13)),
+            parse("{1, -1e999999}")
+        );
+        let digits = format!("{{{}}}", "9".repeat(400));
+        assert_eq!(Some(1..401), parse(&digits).unwrap_err().span());
+    }
+
+    #[test]
+    fn concatenation() {
+        let v = Value::Object(
+            parse(r#"{path = "C:\\base" .. "\\sub", "a".."b"..  "c", ["k"] = "\xff" .. ""}"#)
+                .unwrap(),
+        );
+        assert_eq!(Some("C:\\base\\sub"), v["path"].as_str());
+        assert_eq!(Some("abc"), v[0].as_str());
+        assert_eq!(Some(&b"\xff"[..]), v["k"].as_bytes());
+
+        let unsupported = |s: &str| match parse(s) {
+            Err(Lua2JsonError::Unsupported { construct, span }) => (construct, span),
+            other => panic!("{other:?}"),
+        };
+        assert_eq!(
+            (
+                "concatenation of `prefix`, which isn't a literal".to_string(),
+                7..13
+            ),
+            unsupported(r#"{msg = prefix .. "x"}"#)
+        );
+        assert_eq!(8..13, unsupported(r#"{"x" .. other}"#).1);
+        assert_eq!(
+            ("concatenation of a number".to_string(), 8..9),
+            unsupported(r#"{"x" .. 1}"#)
+        );
+        assert_eq!(1..3, unsupported(r#"{{} .. "x"}"#).1);
+        let opts = ParseOptions {
+            limits: Limits {
+                max_string: Some(2),
+                ..Limits::default()
+            },
+            ..ParseOptions::default()
+        };
+        assert!(parse_with(r#"{"ab" .. "c"}"#, &opts).is_err());
+    }
+
+    #[test]
+    fn arithmetic() {
+        let opts = ParseOptions {
+            arithmetic: true,
+            ..ParseOptions::default()
+        };
+        let eval = |s: &str| {
+            let table = parse_with(&format!("{{{s}}}"), &opts).unwrap();
+            table[0].1.as_f64().unwrap()
+        };
+        assert_eq!(900., eval("60*15"));
+        assert_eq!(-12., eval("-(10+2)"));
+        assert_eq!(7., eval("1 + 2 * 3"));
+        assert_eq!(9., eval("(1 + 2) * 3"));
+        assert_eq!(-4., eval("-2^2"));
+        assert_eq!(512., eval("2^3^2"));
+        assert_eq!(0.5, eval("2^-1"));
+        assert_eq!(2., eval("1/0.5"));
+        assert_eq!(1., eval("10 - 4 - 5"));
+        assert_eq!(2., eval("-7 % 3"));
+        assert_eq!(-1.5, eval("5.5 % -3.5"));
+        assert_eq!(f64::INFINITY, eval("1/0"));
+        assert_eq!(-f64::INFINITY, eval("-math.huge"));
+        assert!(eval("0/0").is_nan());
+
+        let v = Value::Object(parse_with(r#"{a = -3, b = ("x"), "y" .. "z"}"#, &opts).unwrap());
+        assert_eq!(Some(-3.), v["a"].as_f64());
+        assert_eq!(Some("x"), v["b"].as_str());
+        assert_eq!(Some("yz"), v[0].as_str());
+        assert_eq!(
+            Err(Lua2JsonError::Unsupported {
+                construct: "arithmetic on a string".to_string(),
+                span: 5..8
+            }),
+            parse_with(r#"{1 + "x"}"#, &opts)
+        );
+        assert!(parse_with("{(1}", &opts).is_err());
+        assert!(parse("{60*15}").is_err());
+    }
+
+    #[test]
+    fn variables() {
+        fn resolve(name: &str) -> Option<Value> {
+            ("SCALE" == name).then(|| Value::from(2.))
+        }
+        let opts = ParseOptions {
+            resolve: Some(resolve),
+            arithmetic: true,
+            ..ParseOptions::default()
+        };
+        let v = Value::Object(parse_with("{a = SCALE * 3, trueish = SCALE}", &opts).unwrap());
+        assert_eq!(Some(6.), v["a"].as_f64());
+        assert_eq!(Some(2.), v["trueish"].as_f64());
+        assert_eq!(
+            Err(Lua2JsonError::parse("undefined variable `OTHER`", 5..10)),
+            parse_with("{a = OTHER}", &opts)
+        );
+        assert!(parse("{a = SCALE}").is_err());
+
+        let doc = r#"
+            local W = 1920
+            local H = W * 9 / 16;
+            screen = {width = W, height = H}
+            name = "a"; name = name .. "b"
+        "#;
+        let v = Value::Object(parse_document(doc, &opts).unwrap());
+        assert_eq!(
+            v["screen"],
+            Value::Object(parse("{width = 1920, height = 1080}").unwrap())
+        );
+        assert_eq!(Some("ab"), v["name"].as_str());
+        assert_eq!(
+            vec![Some("screen"), Some("name")],
+            v.keys().collect::<Vec<_>>()
+        );
+
+        let doc = "local t = {1, SCALE}\nreturn {t, t}";
+        assert_eq!(
+            parse("{{1, 2}, {1, 2}}").unwrap(),
+            parse_document(doc, &opts).unwrap()
+        );
+        assert!(parse_document("return {}\nx = 1", &opts).is_err());
+        assert!(parse_document("return 1", &opts).is_err());
+        assert!(parse_document("x = y", &ParseOptions::default()).is_err());
+
+        let opts = ParseOptions {
+            limits: Limits {
+                max_entries: Some(20),
+                ..Limits::default()
+            },
+            ..ParseOptions::default()
+        };
+        let doc = "local a = {1, 2, 3, 4}\nlocal b = {a, a, a, a}\nc = {b, b}";
+        assert!(matches!(
+            parse_document(doc, &opts),
+            Err(Lua2JsonError::LimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn multi() {
+        let opts = ParseOptions::default();
+        let docs = r#"
+            AccountData = {name = "a"} -- the first
+            --[[ appended
+                 later ]]
+            AccountData = {name = "b"};
+            {1, 2} Count = 3
+        "#;
+        assert_eq!(
+            parse(r#"{AccountData = {name = "a"}, AccountData = {name = "b"}, {1, 2}, Count = 3}"#)
+                .unwrap(),
+            parse_multi(docs, &opts).unwrap()
+        );
+        assert!(parse_multi("", &opts).unwrap().is_empty());
+        assert_eq!(
+            Err(Lua2JsonError::parse("unexpected '}'", 9..10)),
+            parse_multi("a = {} {}}", &opts)
+        );
+        assert_eq!(
+            Err(Lua2JsonError::parse("unterminated comment", 7..16)),
+            parse_multi("a = {} --[[ open", &opts)
+        );
+        assert!(parse_multi("1", &opts).is_err());
+    }
+
+    #[test]
+    fn only() {
+        let opts = ParseOptions {
+            only: vec!["a.b".to_string(), "c".to_string()],
+            ..ParseOptions::default()
+        };
+        let doc = r#"{a = {b = {1}, x = {"}", {}}}, c = {d = 2}, e = {"{"}, f = 3, c = 4, 5}"#;
+        assert_eq!(
+            parse(r#"{a = {b = {1}}, c = {d = 2}, c = 4}"#).unwrap(),
+            parse_with(doc, &opts).unwrap()
+        );
+        // skipped tables only have their braces matched up
+        assert!(parse_with("{e = {1 2}, c = 1}", &opts).is_ok());
+        assert!(parse_with("{e = {1}}, c = 1}", &opts).is_err());
+        assert!(parse_with("{e = {\"}", &opts).is_err());
+
+        let doc = "local x = {1}\nc = {y = x}\na = {b = 2, z = 3}\ne = x";
+        assert_eq!(
+            parse(r#"{c = {y = {1}}, a = {b = 2}}"#).unwrap(),
+            parse_document(doc, &opts).unwrap()
+        );
+        assert_eq!(
+            parse(r#"{c = {}, c = true}"#).unwrap(),
+            parse_multi("c = {}\ne = {}\nc = true", &opts).unwrap()
+        );
+    }
+
+    #[test]
+    fn lenient() {
+        let opts = ParseOptions {
+            lenient: true,
+            ..ParseOptions::default()
+        };
+        let doc = r#"{
+            on_use = function(a, b) if a then return a, "}" end end,
+            lib = require("lib.util"), n = 1,
+            {x, y.z}
+        }"#;
+        let (t, warnings) = parse_with_warnings(doc, &opts).unwrap();
+        let raw = |s: &str| Value::Raw(s.to_string());
+        assert_eq!(
+            vec![
+                (
+                    Some("on_use".into()),
+                    raw(r#"function(a, b) if a then return a, "}" end end"#)
+                ),
+                (Some("lib".into()), raw(r#"require("lib.util")"#)),
+                (Some("n".into()), Value::Float(1.)),
+                (
+                    None,
+                    Value::Object(vec![(None, raw("x")), (None, raw("y.z"))])
+                ),
+            ],
+            t
+        );
+        assert_eq!(4, warnings.len());
+        assert_eq!(23..69, warnings[0].span);
+        assert_eq!("function", &doc[warnings[0].span.clone()][..8]);
+        assert_eq!(
+            "unsupported value kept as raw text at byte 23",
+            warnings[0].to_string()
+        );
+
+        assert_eq!(t, parse_with(doc, &opts).unwrap());
+        assert!(parse_with(doc, &ParseOptions::default()).is_err());
+        assert_eq!(Table::new(), parse_with("{ }", &opts).unwrap());
+        assert!(parse_with("{a = }", &opts).is_err());
+    }
+
+    #[test]
+    fn unicode_names() {
+        let opts = ParseOptions {
+            unicode_names: true,
+            ..ParseOptions::default()
+        };
+        let t = parse_with("{größe = 5, 名前 = \"x\", _ü1 = true}", &opts).unwrap();
+        assert_eq!(
+            vec![Some("größe"), Some("名前"), Some("_ü1")],
+            t.iter().map(|(k, _)| k.as_deref()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Err(Lua2JsonError::parse("unexpected 'g'", 1..2)),
+            parse("{größe = 5}")
+        );
+        assert!(parse_with("{1größe = 5}", &opts).is_err());
+
+        let doc = "local 幅 = 2\nw = 幅";
+        let v = Value::Object(parse_document(doc, &opts).unwrap());
+        assert_eq!(Some(2.), v["w"].as_f64());
+    }
+
+    #[test]
+    fn lowercase_keys() {
+        let doc = r#"{Width = 1, [" HEIGHT "] = 2, Nested = {Title = "x"}, "Kept"}"#;
+        let v = Value::Object(parse(doc).unwrap());
+        assert_eq!(Some(&Value::Float(1.)), v.get_ci("width"));
+        assert_eq!(Some(&Value::Float(2.)), v.get_ci("Height"));
+        assert_eq!(None, v.get_ci("kept"));
+        let v = Value::Object(parse("{width = 1, Width = 2}").unwrap());
+        assert_eq!(Some(&Value::Float(2.)), v.get_ci("Width"));
+
+        let opts = ParseOptions {
+            lowercase_keys: true,
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            parse(r#"{width = 1, height = 2, nested = {title = "x"}, "Kept"}"#).unwrap(),
+            parse_with(doc, &opts).unwrap()
+        );
+        assert_eq!(
+            Some("width"),
+            parse_multi("Width = {}", &opts).unwrap()[0].0.as_deref()
+        );
+    }
+
+    #[test]
+    fn intern_keys() {
+        let doc = r#"{{id = 1, ["max hp"] = 2, [3] = 3}, {id = 4, ["max hp"] = 5, [3] = 6}}"#;
+        let opts = ParseOptions {
+            intern_keys: true,
+            ..ParseOptions::default()
+        };
+        let table = parse_with(doc, &opts).unwrap();
+        assert_eq!(parse(doc).unwrap(), table);
+        let keys = |i: usize| match &table[i].1 {
+            Value::Object(t) => t
+                .iter()
+                .map(|(k, _)| k.clone().unwrap())
+                .collect::<Vec<_>>(),
+            v => panic!("{v:?}"),
+        };
+        let (a, b) = (keys(0), keys(1));
+        for (a, b) in a[..2].iter().zip(&b[..2]) {
+            match (a, b) {
+                (Key::Shared(a), Key::Shared(b)) => assert!(Arc::ptr_eq(a, b)),
+                keys => panic!("{keys:?}"),
+            }
+        }
+        assert_eq!(Key::Integer("3".to_string()), a[2]);
+
+        let shared = |k: &Option<Key>| match k {
+            Some(Key::Shared(k)) => k.clone(),
+            k => panic!("{k:?}"),
+        };
+        let docs = parse_multi("Data = {1}\nData = {Data = 2}", &opts).unwrap();
+        assert!(Arc::ptr_eq(&shared(&docs[0].0), &shared(&docs[1].0)));
+        let Value::Object(inner) = &docs[1].1 else {
+            panic!("{docs:?}")
+        };
+        assert!(Arc::ptr_eq(&shared(&docs[0].0), &shared(&inner[0].0)));
+        let globals = parse_document("A = 1\nB = {A = 2}", &opts).unwrap();
+        let Value::Object(inner) = &globals[1].1 else {
+            panic!("{globals:?}")
+        };
+        assert!(Arc::ptr_eq(&shared(&globals[0].0), &shared(&inner[0].0)));
+    }
+
+    #[test]
+    fn bytes() {
+        assert_eq!(
+            parse(r#"{name = "Zoë"}"#).unwrap(),
+            parse_bytes(b"{name = \"Zo\xeb\"}", Encoding::Latin1).unwrap()
+        );
+        assert_eq!(
+            parse("{1}").unwrap(),
+            parse_bytes(b"\xef\xbb\xbf{1}", Encoding::Auto).unwrap()
+        );
+        assert!(parse_bytes(b"{\"\xeb\"}", Encoding::Utf8).is_err());
+    }
+
+    #[test]
+    fn raw_numbers() {
+        let opts = ParseOptions {
+            raw_numbers: true,
+            ..ParseOptions::default()
+        };
+        let v = Value::Object(parse_with("{a = 0.10, b = {-3}}", &opts).unwrap());
+        assert_eq!(
+            &Value::Number {
+                value: 0.1,
+                raw: "0.10".to_string()
+            },
+            &v["a"]
+        );
+        assert_eq!(Some(-3.), v["b"][0].as_f64());
+        assert_eq!("number", v["b"][0].type_name());
+        assert_eq!(Value::Float(0.1), parse("{a = 0.10}").unwrap()[0].1);
+    }
+
+    #[test]
+    fn text() {
+        let v: Value = r#" {a = 5, b = {"x\n", true}, ["c d"] = {}} "#.parse().unwrap();
+        assert_eq!(r#"{a = 5, b = {"x\n", true}, ["c d"] = {}}"#, v.to_string());
+        assert_eq!(
+            "{\n  a = 1,\n}",
+            format!("{:#}", "{a = 1}".parse::<Value>().unwrap())
+        );
+        assert_eq!(Value::Float(-1.5), "-1.5".parse().unwrap());
+        assert_eq!(Value::String("s".to_string()), "\"s\"".parse().unwrap());
+        assert!("{} {}".parse::<Value>().is_err());
+        assert!("".parse::<Value>().is_err());
+    }
+
+    #[test]
+    fn maps() {
+        let v = Value::Object(parse(r#"{b = 1, "x", a = {2}, b = 3, "y"}"#).unwrap());
+        let (positional, named) = v.split();
+        assert_eq!(vec![Value::from("x"), Value::from("y")], positional);
+        assert_eq!(
+            BTreeMap::from([
+                ("a".to_string(), Value::from(vec![Value::from(2.)])),
+                ("b".to_string(), Value::from(3.)),
+            ]),
+            named
+        );
+        assert_eq!(
+            Err(AccessError::TypeMismatch {
+                expected: "table with named entries",
+                found: "table"
+            }),
+            v.to_map()
+        );
+        let mut v = v;
+        v.table_mut().unwrap().retain(|(k, _)| k.is_some());
+        assert_eq!(Ok(named), v.to_map());
+        assert!(Value::from(1.).to_map().is_err());
+        assert_eq!((vec![], BTreeMap::new()), Value::from(1.).split());
+    }
+
+    #[test]
+    fn limits() {
+        let exceeded = |limits: Limits, s: &str| {
+            let opts = ParseOptions {
+                limits,
+                ..ParseOptions::default()
+            };
+            match parse_with(s, &opts) {
+                Err(Lua2JsonError::LimitExceeded(exceeded)) => exceeded,
+                other => panic!("{other:?}"),
+            }
+        };
+        let input = r#"{a = {b = {"xyz"}}, 2}"#;
+        let at = |limit, max| LimitExceeded { limit, max };
+        let limits = Limits {
+            max_input: Some(21),
+            ..Limits::default()
+        };
+        assert_eq!(at(Limit::InputSize, 21), exceeded(limits, input));
+        let limits = Limits {
+            max_string: Some(2),
+            ..Limits::default()
+        };
+        assert_eq!(at(Limit::StringLength, 2), exceeded(limits, input));
+        let limits = Limits {
+            max_entries: Some(3),
+            ..Limits::default()
+        };
+        assert_eq!(at(Limit::Entries, 3), exceeded(limits, input));
+        let limits = Limits {
+            max_depth: Some(2),
+            ..Limits::default()
+        };
+        assert_eq!(at(Limit::Depth, 2), exceeded(limits, input));
+        assert_eq!(
+            "tables nested more than 2 deep",
+            at(Limit::Depth, 2).to_string()
+        );
+
+        let opts = ParseOptions {
+            limits: Limits {
+                max_input: Some(22),
+                max_string: Some(3),
+                max_entries: Some(4),
+                max_depth: Some(3),
+            },
+            ..ParseOptions::default()
+        };
+        assert_eq!(parse(input).unwrap(), parse_with(input, &opts).unwrap());
+        assert!(matches!(
+            parse_with("{[true] = 2}", &opts),
+            Err(Lua2JsonError::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn errors() {
+        let span = |s: &str| parse(s).unwrap_err().span().unwrap();
+        assert_eq!(7..8, span("{a = 1}}"));
+        assert_eq!(7..7, span("{a = {}"));
+        assert_eq!(6..9, span(r#"{a = "\x4"}"#));
+        assert_eq!(5..11, span(r#"{1, ["\xff"] = 1}"#));
+        assert_eq!(0..1, span("1"));
+        assert_eq!(
+            "unexpected end of input at byte 7",
+            parse("{a = {}").unwrap_err().to_string()
+        );
+        assert_eq!(
+            Err(Lua2JsonError::Unsupported {
+                construct: "escape \\q".to_string(),
+                span: 1..3
+            }),
+            parse_value(r#""\q""#)
+        );
+        assert_eq!(
+            "invalid UTF-8 at byte 4",
+            parse_bytes(b"{1, \xff}", Encoding::Utf8)
+                .unwrap_err()
+                .to_string()
+        );
+
+        let e = anyhow::Error::from(parse("{").unwrap_err());
+        assert!(e.downcast_ref::<Lua2JsonError>().is_some());
+
+        // where the fault is, not the start of the entry the parser backtracked to
+        let at = |s: &str| {
+            let e = parse(s).unwrap_err();
+            (e.to_string(), line_column(s, e.span().unwrap().start))
+        };
+        assert_eq!(
+            ("unterminated string at byte 11".to_string(), (2, 6)),
+            at("{a=1,\n b = \"x}")
+        );
+        assert_eq!(
+            ("unexpected '}' at byte 11".to_string(), (1, 12)),
+            at("{a=1, b={c=}, d={e=}}")
         );
+        assert_eq!((1, 12), at("{a = {1, 2,, 3}}").1);
     }
 }
@@ -0,0 +1,355 @@
+use anyhow::{anyhow, bail, Result};
+
+use crate::parse::{Key, Table, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Segment {
+    Key(String),
+    /// The nth positional (unnamed) entry, counting from zero.
+    Index(usize),
+}
+
+impl std::fmt::Display for Segment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Segment::Key(key) => write!(f, "key {key:?}"),
+            Segment::Index(index) => write!(f, "positional entry {index}"),
+        }
+    }
+}
+
+/// Split a path like `a.b[2]["c.d"]` into segments, each with the length of the path up to
+/// and including it, for error messages.
+pub(crate) fn segments(path: &str) -> Result<Vec<(Segment, usize)>> {
+    let mut out = Vec::new();
+    let mut rest = path;
+    while !rest.is_empty() {
+        let at = path.len() - rest.len();
+        if let Some(after) = rest.strip_prefix("[\"") {
+            let mut key = String::new();
+            let mut chars = after.char_indices();
+            let end = loop {
+                match chars.next() {
+                    Some((i, '"')) => break i,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, c)) => key.push(c),
+                        None => bail!("unterminated key in path {path:?}"),
+                    },
+                    Some((_, c)) => key.push(c),
+                    None => bail!("unterminated key in path {path:?}"),
+                }
+            };
+            rest = after[end + 1..]
+                .strip_prefix(']')
+                .ok_or_else(|| anyhow!("expected ']' at {} in path {path:?}", at + end + 3))?;
+            out.push((Segment::Key(key), path.len() - rest.len()));
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after
+                .find(']')
+                .ok_or_else(|| anyhow!("unterminated index in path {path:?}"))?;
+            let index = after[..end]
+                .parse()
+                .map_err(|_| anyhow!("invalid index {:?} in path {path:?}", &after[..end]))?;
+            rest = &after[end + 1..];
+            out.push((Segment::Index(index), path.len() - rest.len()));
+        } else {
+            let after = match rest.strip_prefix('.') {
+                Some(after) if !out.is_empty() => after,
+                _ if out.is_empty() => rest,
+                _ => bail!("unexpected {:?} at {at} in path {path:?}", &rest[..1]),
+            };
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            if 0 == end {
+                bail!("empty key at {at} in path {path:?}");
+            }
+            rest = &after[end..];
+            out.push((
+                Segment::Key(after[..end].to_string()),
+                path.len() - rest.len(),
+            ));
+        }
+    }
+    Ok(out)
+}
+
+/// Append `key` to a path in the syntax understood by [`Value::get_path`].
+pub(crate) fn push_key(path: &mut String, key: &str) {
+    if key.is_empty() || key.contains(['.', '[', ']', '"', '\\']) {
+        push_quoted_key(path, key);
+    } else {
+        if !path.is_empty() {
+            path.push('.');
+        }
+        path.push_str(key);
+    }
+}
+
+/// Append `key` to a path as `["key"]`, however it's spelled.
+fn push_quoted_key(path: &mut String, key: &str) {
+    path.push_str("[\"");
+    for c in key.chars() {
+        if '"' == c || '\\' == c {
+            path.push('\\');
+        }
+        path.push(c);
+    }
+    path.push_str("\"]");
+}
+
+/// Whether `key` is spelled like an integer key, like `1` from `[1]`.
+fn is_digits(key: &str) -> bool {
+    !key.is_empty() && key.bytes().all(|c| c.is_ascii_digit())
+}
+
+/// Append the nth positional entry to a path, like [`push_key`].
+pub(crate) fn push_index(path: &mut String, index: usize) {
+    path.push_str(&format!("[{index}]"));
+}
+
+fn position(table: &Table, segment: &Segment) -> Option<usize> {
+    match segment {
+        Segment::Key(key) => table
+            .iter()
+            .position(|(k, _)| k.as_deref() == Some(key.as_str())),
+        Segment::Index(index) => table
+            .iter()
+            .enumerate()
+            .filter(|(_, (k, _))| k.is_none())
+            .nth(*index)
+            .map(|(i, _)| i),
+    }
+}
+
+/// The value at the end of `path` inside `value`, if there is one.
+pub(crate) fn lookup<'v>(mut value: &'v Value, path: &[Segment]) -> Option<&'v Value> {
+    for segment in path {
+        let table = value.as_table()?;
+        value = &table[position(table, segment)?].1;
+    }
+    Some(value)
+}
+
+fn step(value: &Value, path: &str, upto: usize, segment: &Segment) -> Result<usize> {
+    let table = match value.as_table() {
+        Some(table) => table,
+        None => bail!(
+            "{:?} is not a table, but {value:?}, looking up {path:?}",
+            &path[..upto]
+        ),
+    };
+    position(table, segment)
+        .ok_or_else(|| anyhow!("no {segment} in {:?}, looking up {path:?}", &path[..upto]))
+}
+
+impl Value {
+    /// Look up a nested value by a path: `.key` or `["key"]` for named entries, and `[n]` for
+    /// the nth positional entry, counting from zero, e.g. `players[2].stats["max hp"]`.
+    pub fn get_path(&self, path: &str) -> Result<&Value> {
+        let mut value = self;
+        let mut upto = 0;
+        for (segment, end) in segments(path)? {
+            let i = step(value, path, upto, &segment)?;
+            value = &value.table()?[i].1;
+            upto = end;
+        }
+        Ok(value)
+    }
+
+    /// Like [`Value::get_path`], but mutable.
+    pub fn get_path_mut(&mut self, path: &str) -> Result<&mut Value> {
+        let mut value = self;
+        let mut upto = 0;
+        for (segment, end) in segments(path)? {
+            let i = step(value, path, upto, &segment)?;
+            value = &mut value.as_table_mut().expect("step checked for a table")[i].1;
+            upto = end;
+        }
+        Ok(value)
+    }
+
+    /// Every value inside this one which isn't a table, along with its path in the syntax of
+    /// [`Value::get_path`], like `players[2].stats.hp`, in order. Empty tables are kept, as
+    /// they'd otherwise be lost, and a value which isn't a table has the path `""`.
+    pub fn flatten(&self) -> Vec<(String, Value)> {
+        let mut out = Vec::new();
+        flatten(self, &mut String::new(), &mut out);
+        out
+    }
+
+    /// The inverse of [`Value::flatten`]: a table with each value at its path, creating the
+    /// tables along the way. Positional entries must be given in order. Keys of only digits
+    /// written like `.1` are integer keys, like `[1]` in Lua, and ones written like `["1"]` are
+    /// strings.
+    pub fn unflatten(entries: impl IntoIterator<Item = (String, Value)>) -> Result<Value> {
+        let mut root = Value::Object(Table::new());
+        for (path, value) in entries {
+            let mut target = &mut root;
+            let mut start = 0;
+            for (segment, end) in segments(&path)? {
+                let quoted = path[start..].starts_with("[\"");
+                start = end;
+                let table = match target {
+                    Value::Object(table) => table,
+                    _ => bail!("{:?} is not a table, setting {path:?}", &path[..end]),
+                };
+                let i = match position(table, &segment) {
+                    Some(i) => i,
+                    None => {
+                        let key = match segment {
+                            Segment::Key(key) if !quoted && is_digits(&key) => {
+                                Some(Key::Integer(key))
+                            }
+                            Segment::Key(key) => Some(Key::Name(key)),
+                            Segment::Index(index)
+                                if index == table.iter().filter(|(k, _)| k.is_none()).count() =>
+                            {
+                                None
+                            }
+                            Segment::Index(_) => {
+                                bail!("{:?} is out of order, setting {path:?}", &path[..end])
+                            }
+                        };
+                        table.push((key, Value::Object(Table::new())));
+                        table.len() - 1
+                    }
+                };
+                target = &mut table[i].1;
+            }
+            *target = value;
+        }
+        Ok(root)
+    }
+}
+
+fn flatten(value: &Value, path: &mut String, out: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Object(table) if !table.is_empty() => {
+            let mut positional = 0;
+            for (k, v) in table {
+                let len = path.len();
+                match k {
+                    // so `unflatten` can tell `["1"]` from `[1]`, which is written `.1`
+                    Some(Key::Name(k)) if is_digits(k) => push_quoted_key(path, k),
+                    Some(Key::Shared(k)) if is_digits(k) => push_quoted_key(path, k),
+                    Some(k) => push_key(path, k),
+                    None => {
+                        push_index(path, positional);
+                        positional += 1;
+                    }
+                }
+                flatten(v, path, out);
+                path.truncate(len);
+            }
+        }
+        _ => out.push((path.clone(), value.clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::{to_lua, FormatOptions, Indent};
+    use crate::parse::{parse, Table, Value};
+
+    fn doc() -> Value {
+        Value::Object(parse(r#"{a = {b = {"x", {c = 5}, {c = 6}}}, ["d.e"] = true, 7}"#).unwrap())
+    }
+
+    #[test]
+    fn get_path() {
+        let doc = doc();
+        assert_eq!(&doc, doc.get_path("").unwrap());
+        assert_eq!(&Value::Float(6.), doc.get_path("a.b[2].c").unwrap());
+        assert_eq!(
+            &Value::Float(5.),
+            doc.get_path(r#"["a"]["b"][1]["c"]"#).unwrap()
+        );
+        assert_eq!(&Value::Bool(true), doc.get_path(r#"["d.e"]"#).unwrap());
+        assert_eq!(&Value::Float(7.), doc.get_path("[0]").unwrap());
+
+        let err = doc.get_path("a.b[3].c").unwrap_err().to_string();
+        assert!(err.contains("\"a.b\""), "{err}");
+        assert!(err.contains("\"a.b[3].c\""), "{err}");
+        let err = doc.get_path("a.b[0].c").unwrap_err().to_string();
+        assert!(err.contains("not a table"), "{err}");
+
+        assert!(doc.get_path("a..b").is_err());
+        assert!(doc.get_path("a[x]").is_err());
+        assert!(doc.get_path("a[\"b").is_err());
+    }
+
+    #[test]
+    fn push_key() {
+        let mut path = String::new();
+        for key in ["a", "b.c", "", "q\"\\"] {
+            super::push_key(&mut path, key);
+        }
+        super::push_index(&mut path, 3);
+        super::push_key(&mut path, "d");
+        assert_eq!(r#"a["b.c"][""]["q\"\\"][3].d"#, path);
+
+        let mut doc = Value::Object(Table::new());
+        doc.set("b.c", Value::Object(Table::new())).unwrap();
+        doc["b.c"].set("q\"\\", Value::Bool(true)).unwrap();
+        let mut path = String::new();
+        super::push_key(&mut path, "b.c");
+        super::push_key(&mut path, "q\"\\");
+        assert_eq!(&Value::Bool(true), doc.get_path(&path).unwrap());
+    }
+
+    #[test]
+    fn flatten() {
+        let doc = Value::Object(
+            parse(r#"{players = {{name = "a"}, {name = "b", stats = {hp = 3}}}, e = {}, 7}"#)
+                .unwrap(),
+        );
+        let flat = doc.flatten();
+        assert_eq!(
+            vec![
+                ("players[0].name", Value::from("a")),
+                ("players[1].name", Value::from("b")),
+                ("players[1].stats.hp", Value::from(3.)),
+                ("e", Value::Object(Table::new())),
+                ("[0]", Value::from(7.)),
+            ],
+            flat.iter()
+                .map(|(k, v)| (k.as_str(), v.clone()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(doc, Value::unflatten(flat).unwrap());
+        assert_eq!(
+            vec![(String::new(), Value::from(1.))],
+            Value::from(1.).flatten()
+        );
+        assert_eq!(
+            Value::Object(parse("{1}").unwrap()),
+            Value::unflatten([("[0]".to_string(), Value::from(1.))]).unwrap()
+        );
+        assert!(Value::unflatten([("[1]".to_string(), Value::from(1.))]).is_err());
+
+        let doc = Value::Object(parse(r#"{[1] = {[2] = "a"}, ["3"] = "b", [04] = "c"}"#).unwrap());
+        let flat = doc.flatten();
+        assert_eq!(
+            vec!["1.2", r#"["3"]"#, "04"],
+            flat.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>()
+        );
+        let opts = FormatOptions {
+            indent: Indent::None,
+            ..FormatOptions::default()
+        };
+        let lua = to_lua(&Value::unflatten(flat).unwrap(), &opts);
+        assert_eq!(r#"{[1] = {[2] = "a"}, ["3"] = "b", [04] = "c"}"#, lua);
+        let clash = [
+            ("a".to_string(), Value::from(1.)),
+            ("a.b".to_string(), Value::from(2.)),
+        ];
+        assert!(Value::unflatten(clash).is_err());
+    }
+
+    #[test]
+    fn get_path_mut() {
+        let mut doc = doc();
+        *doc.get_path_mut("a.b[1].c").unwrap() = Value::Bool(false);
+        assert_eq!(&Value::Bool(false), doc.get_path("a.b[1].c").unwrap());
+        assert!(doc.get_path_mut("a.z").is_err());
+    }
+}
@@ -0,0 +1,348 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+
+use crate::parse::Value;
+
+/// A selector over a [`Value`], as a `|`-separated pipeline of stages, each of which is either
+/// a path or a `select`:
+///
+///  * paths are made of `.key`, `["key"]`, `[n]` for the nth positional entry, and `[]` or `*`
+///    for every entry, e.g. `players.*.name` or `.players[].name`,
+///  * `select(path op literal)` keeps values whose path compares to the literal, with `op` one
+///    of `==`, `!=`, `<`, `<=`, `>`, `>=`, and the literal a number, string or boolean. As in
+///    jq, a missing path is `nil`, so it's `!=` every literal, and no other comparison holds.
+///
+/// For example, `.players[] | select(.level > 10) | .name`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    stages: Vec<Stage>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Stage {
+    Path(Vec<Step>),
+    Select(Vec<Step>, Op, Value),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+    All,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Query> {
+        let mut cursor = Cursor { s: query, pos: 0 };
+        let mut stages = Vec::new();
+        loop {
+            cursor.ws();
+            let select = cursor
+                .rest()
+                .strip_prefix("select")
+                .is_some_and(|r| r.trim_start().starts_with('('));
+            stages.push(if select {
+                cursor.eat("select");
+                cursor.ws();
+                cursor.expect("(")?;
+                cursor.ws();
+                let path = cursor.path()?;
+                cursor.ws();
+                let op = cursor.op()?;
+                cursor.ws();
+                let literal = cursor.literal()?;
+                cursor.ws();
+                cursor.expect(")")?;
+                Stage::Select(path, op, literal)
+            } else {
+                Stage::Path(cursor.path()?)
+            });
+            cursor.ws();
+            if cursor.rest().is_empty() {
+                return Ok(Query { stages });
+            }
+            cursor.expect("|")?;
+        }
+    }
+
+    /// Every value the query selects, in order.
+    pub fn eval<'v>(&self, value: &'v Value) -> Vec<&'v Value> {
+        let mut values = vec![value];
+        for stage in &self.stages {
+            values = match stage {
+                Stage::Path(path) => values.into_iter().flat_map(|v| follow(v, path)).collect(),
+                Stage::Select(path, op, literal) => values
+                    .into_iter()
+                    .filter(|v| {
+                        let found = follow(v, path);
+                        if found.is_empty() {
+                            return Op::Ne == *op;
+                        }
+                        found.iter().any(|v| compare(v, *op, literal))
+                    })
+                    .collect(),
+            };
+        }
+        values
+    }
+}
+
+impl FromStr for Query {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Query> {
+        Query::parse(s)
+    }
+}
+
+/// Evaluate `query` against `value`; see [`Query`].
+pub fn query<'v>(value: &'v Value, query: &str) -> Result<Vec<&'v Value>> {
+    Ok(Query::parse(query)?.eval(value))
+}
+
+fn follow<'v>(value: &'v Value, path: &[Step]) -> Vec<&'v Value> {
+    let mut values = vec![value];
+    for step in path {
+        values = values
+            .into_iter()
+            .flat_map(|v| -> Vec<&Value> {
+                match step {
+                    Step::Key(key) => v.get_opt(key).into_iter().collect(),
+                    Step::Index(i) => v.index(*i).ok().into_iter().collect(),
+                    Step::All => v.values().collect(),
+                }
+            })
+            .collect();
+    }
+    values
+}
+
+fn compare(value: &Value, op: Op, literal: &Value) -> bool {
+    use std::cmp::Ordering;
+    let ordering = match (value, literal) {
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => match (value.as_bytes(), literal.as_bytes()) {
+            (Some(a), Some(b)) => Some(a.cmp(b)),
+            _ => match (value.as_f64(), literal.as_f64()) {
+                (Some(a), Some(b)) => a.partial_cmp(&b),
+                _ => None,
+            },
+        },
+    };
+    match (op, ordering) {
+        (Op::Ne, None) => true,
+        (_, None) => false,
+        (Op::Eq, Some(o)) => o == Ordering::Equal,
+        (Op::Ne, Some(o)) => o != Ordering::Equal,
+        (Op::Lt, Some(o)) => o == Ordering::Less,
+        (Op::Le, Some(o)) => o != Ordering::Greater,
+        (Op::Gt, Some(o)) => o == Ordering::Greater,
+        (Op::Ge, Some(o)) => o != Ordering::Less,
+    }
+}
+
+struct Cursor<'q> {
+    s: &'q str,
+    pos: usize,
+}
+
+impl<'q> Cursor<'q> {
+    fn rest(&self) -> &'q str {
+        &self.s[self.pos..]
+    }
+
+    fn ws(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        let found = self.rest().starts_with(token);
+        if found {
+            self.pos += token.len();
+        }
+        found
+    }
+
+    fn expect(&mut self, token: &str) -> Result<()> {
+        if !self.eat(token) {
+            return Err(self.error(&format!("expected {token:?}")));
+        }
+        Ok(())
+    }
+
+    fn error(&self, what: &str) -> anyhow::Error {
+        anyhow!("{what} at {} in query {:?}", self.pos, self.s)
+    }
+
+    /// Consume characters up to the next one in `stop`, or the end.
+    fn until(&mut self, stop: &[char]) -> &'q str {
+        let rest = self.rest();
+        let end = rest
+            .find(|c: char| stop.contains(&c) || c.is_whitespace())
+            .unwrap_or(rest.len());
+        self.pos += end;
+        &rest[..end]
+    }
+
+    fn path(&mut self) -> Result<Vec<Step>> {
+        const STOP: &[char] = &['.', '[', ']', '|', '(', ')', '"', '=', '!', '<', '>'];
+        let mut steps = Vec::new();
+        let mut dotted = self.eat(".");
+        loop {
+            if self.eat("[]") || self.eat("*") {
+                steps.push(Step::All);
+            } else if self.eat("[\"") {
+                steps.push(Step::Key(self.quoted()?));
+                self.expect("]")?;
+            } else if self.eat("[") {
+                let index = self.until(STOP);
+                let index = index
+                    .parse()
+                    .map_err(|_| self.error(&format!("invalid index {index:?}")))?;
+                steps.push(Step::Index(index));
+                self.expect("]")?;
+            } else if dotted || steps.is_empty() {
+                let name = self.until(STOP);
+                if name.is_empty() {
+                    if dotted && steps.is_empty() {
+                        return Ok(steps);
+                    }
+                    return Err(self.error("expected a key"));
+                }
+                steps.push(Step::Key(name.to_string()));
+            } else {
+                return Ok(steps);
+            }
+            dotted = self.eat(".");
+        }
+    }
+
+    /// The rest of a string whose opening quote has been consumed.
+    fn quoted(&mut self) -> Result<String> {
+        let mut out = String::new();
+        let mut chars = self.rest().chars();
+        loop {
+            match chars.next() {
+                Some('"') => break,
+                Some('\\') => match chars.next() {
+                    Some(c) => out.push(c),
+                    None => return Err(self.error("unterminated string")),
+                },
+                Some(c) => out.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+        self.pos = self.s.len() - chars.as_str().len();
+        Ok(out)
+    }
+
+    fn op(&mut self) -> Result<Op> {
+        let ops = [
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ];
+        for (token, op) in ops {
+            if self.eat(token) {
+                return Ok(op);
+            }
+        }
+        Err(self.error("expected a comparison"))
+    }
+
+    fn literal(&mut self) -> Result<Value> {
+        if self.eat("\"") {
+            return Ok(Value::String(self.quoted()?));
+        }
+        let word = self.until(&[')']);
+        Ok(match word {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => Value::Float(
+                word.parse()
+                    .map_err(|_| self.error(&format!("invalid literal {word:?}")))?,
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{parse, Value};
+    use crate::query::query;
+
+    fn lua(s: &str) -> Value {
+        Value::Object(parse(s).unwrap())
+    }
+
+    fn strings(values: Vec<&Value>) -> Vec<&str> {
+        values.into_iter().map(|v| v.as_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn paths() {
+        let v = lua(
+            r#"{players = {{name = "a", level = 5}, {name = "b", level = 12}}, ["odd key"] = {x = "y"}}"#,
+        );
+        assert_eq!(
+            vec!["a", "b"],
+            strings(query(&v, "players.*.name").unwrap())
+        );
+        assert_eq!(
+            vec!["a", "b"],
+            strings(query(&v, ".players[].name").unwrap())
+        );
+        assert_eq!(vec!["b"], strings(query(&v, ".players[1].name").unwrap()));
+        assert_eq!(vec!["y"], strings(query(&v, r#"["odd key"].x"#).unwrap()));
+        assert_eq!(vec![&v], query(&v, ".").unwrap());
+        assert!(query(&v, "selection").unwrap().is_empty());
+        assert!(query(&v, ".missing.name").unwrap().is_empty());
+    }
+
+    #[test]
+    fn select() {
+        let v =
+            lua(r#"{players = {{name = "a", level = 5}, {name = "b", level = 12}, {name = "c"}}}"#);
+        let names = |q| strings(query(&v, q).unwrap());
+        assert_eq!(vec!["b"], names(".players[] | select(.level > 10) | .name"));
+        assert_eq!(vec!["a"], names(".players[]|select(.level<=5)|.name"));
+        assert_eq!(
+            vec!["a", "c"],
+            names(r#".players[] | select(.name != "b") | .name"#)
+        );
+        assert_eq!(
+            vec!["c"],
+            names(r#".players[] | select(.name == "c") | .name"#)
+        );
+        assert_eq!(
+            vec!["a", "c"],
+            names(".players[] | select(.level != 12) | .name")
+        );
+        assert_eq!(vec!["a"], names(".players[] | select(.level < 12) | .name"));
+        assert!(names(".players[] | select(.missing == 1) | .name").is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        let v = lua("{}");
+        assert!(query(&v, ".a |").is_err());
+        assert!(query(&v, "select(.a ~ 1)").is_err());
+        assert!(query(&v, "a[x]").is_err());
+        assert!(query(&v, r#"["a"#).is_err());
+        assert!(query(&v, "a b").is_err());
+    }
+}
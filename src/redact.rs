@@ -0,0 +1,182 @@
+use anyhow::Result;
+
+use crate::parse::Value;
+use crate::path::{segments, Segment};
+
+/// The string which [`RedactMode::Replace`] puts in place of redacted values.
+pub const REDACTED: &str = "<redacted>";
+
+/// What [`Value::redact`] does with the entries it matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RedactMode {
+    /// Replace their values with [`REDACTED`].
+    #[default]
+    Replace,
+    /// Remove them entirely.
+    Remove,
+}
+
+/// Entries to hide, like account names or tokens, by their names or paths.
+#[derive(Debug, Clone)]
+pub struct Redaction {
+    patterns: Vec<Vec<Segment>>,
+    pub mode: RedactMode,
+}
+
+impl Redaction {
+    /// Patterns are paths in the syntax of [`Value::get_path`], matched from the root, where
+    /// `*` in a key matches any characters, a key of only `*` also matches any positional
+    /// entry, and `**` matches any number of entries, like `accounts.*.token` or
+    /// `**.password`. A pattern of just a name, like `token`, matches entries with that name
+    /// anywhere.
+    pub fn new(
+        patterns: impl IntoIterator<Item = impl AsRef<str>>,
+        mode: RedactMode,
+    ) -> Result<Redaction> {
+        let patterns = patterns
+            .into_iter()
+            .map(|pattern| {
+                let mut pattern = segments(pattern.as_ref())?
+                    .into_iter()
+                    .map(|(segment, _)| segment)
+                    .collect::<Vec<_>>();
+                if let [Segment::Key(_)] = pattern.as_slice() {
+                    pattern.insert(0, Segment::Key("**".to_string()));
+                }
+                Ok(pattern)
+            })
+            .collect::<Result<_>>()?;
+        Ok(Redaction { patterns, mode })
+    }
+
+    fn matches(&self, path: &[Segment]) -> bool {
+        self.patterns.iter().any(|pattern| matches(pattern, path))
+    }
+}
+
+fn matches(pattern: &[Segment], path: &[Segment]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((Segment::Key(any), rest)) if "**" == any => {
+            (0..=path.len()).any(|skip| matches(rest, &path[skip..]))
+        }
+        Some((first, rest)) => path
+            .split_first()
+            .is_some_and(|(segment, path)| matches_segment(first, segment) && matches(rest, path)),
+    }
+}
+
+fn matches_segment(pattern: &Segment, segment: &Segment) -> bool {
+    match (pattern, segment) {
+        (Segment::Key(pattern), Segment::Key(key)) => glob(pattern, key),
+        (Segment::Key(any), Segment::Index(_)) => "*" == any,
+        (Segment::Index(a), Segment::Index(b)) => a == b,
+        (Segment::Index(_), Segment::Key(_)) => false,
+    }
+}
+
+/// Whether `text` matches `pattern`, where `*` matches any characters.
+fn glob(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((prefix, rest)) => text.strip_prefix(prefix).is_some_and(|text| {
+            (0..=text.len())
+                .filter(|&i| text.is_char_boundary(i))
+                .any(|i| glob(rest, &text[i..]))
+        }),
+    }
+}
+
+impl Value {
+    /// Replace or remove the entries which `redaction` matches, anywhere in this value.
+    pub fn redact(&mut self, redaction: &Redaction) {
+        redact(self, redaction, &mut Vec::new());
+    }
+}
+
+fn redact(value: &mut Value, redaction: &Redaction, path: &mut Vec<Segment>) {
+    let Some(table) = value.as_table_mut() else {
+        return;
+    };
+    let mut positional = 0;
+    table.retain_mut(|(k, v)| {
+        path.push(match k {
+            Some(k) => Segment::Key(k.to_string()),
+            None => {
+                positional += 1;
+                Segment::Index(positional - 1)
+            }
+        });
+        let keep = match redaction.matches(path) {
+            false => {
+                redact(v, redaction, path);
+                true
+            }
+            true if RedactMode::Remove == redaction.mode => false,
+            true => {
+                *v = Value::String(REDACTED.to_string());
+                true
+            }
+        };
+        path.pop();
+        keep
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{parse, Value};
+    use crate::redact::{RedactMode, Redaction};
+
+    fn lua(s: &str) -> Value {
+        Value::Object(parse(s).unwrap())
+    }
+
+    #[test]
+    fn redact() {
+        let doc = r#"{
+            token = "t", name = "me",
+            accounts = {{name = "a", token = "x", api_key = "k"}, {name = "b"}},
+            realms = {eu = {password = "p", id = 1}},
+            "first", "second",
+        }"#;
+        let redaction = Redaction::new(
+            [
+                "token",
+                "accounts.*.name",
+                "**.*_key",
+                "realms.**.password",
+                "[1]",
+            ],
+            RedactMode::Replace,
+        )
+        .unwrap();
+        let mut v = lua(doc);
+        v.redact(&redaction);
+        let r = "<redacted>";
+        assert_eq!(
+            lua(&format!(
+                r#"{{
+                token = "{r}", name = "me",
+                accounts = {{{{name = "{r}", token = "{r}", api_key = "{r}"}}, {{name = "{r}"}}}},
+                realms = {{eu = {{password = "{r}", id = 1}}}},
+                "first", "{r}",
+            }}"#
+            )),
+            v
+        );
+
+        let redaction = Redaction {
+            mode: RedactMode::Remove,
+            ..redaction
+        };
+        let mut v = lua(doc);
+        v.redact(&redaction);
+        assert_eq!(
+            lua(r#"{name = "me", accounts = {{}, {}}, realms = {eu = {id = 1}}, "first"}"#),
+            v
+        );
+
+        assert!(Redaction::new(["a..b"], RedactMode::Replace).is_err());
+    }
+}
@@ -0,0 +1,226 @@
+use crate::format::{to_lua, FormatOptions};
+use crate::parse::{parse_value, Table, Value};
+use crate::path;
+
+/// A way in which a value doesn't come back the same from being written out with [`to_lua`]
+/// and parsed again, at a path in the syntax of [`Value::get_path`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lossiness {
+    /// The Lua doesn't parse at all, as happens with [`Value::Raw`] text which isn't a value.
+    Unparseable {
+        error: String,
+    },
+    /// The entries of a table come back in a different order, as with
+    /// [`FormatOptions::key_order`].
+    Reordered {
+        path: String,
+    },
+    /// A number comes back as a different number. A NaN coming back as a NaN isn't a change,
+    /// and numbers are compared by value, so a [`Value::Number`] can come back as a
+    /// [`Value::Float`].
+    Number {
+        path: String,
+        old: f64,
+        new: f64,
+    },
+    /// A string comes back with different contents, or as [`Value::String`] rather than
+    /// [`Value::Bytes`], or the other way around.
+    String {
+        path: String,
+        old: Value,
+        new: Value,
+    },
+    /// A value comes back as something else altogether, like [`Value::Raw`] text which is
+    /// parsed as a value.
+    Changed {
+        path: String,
+        old: Value,
+        new: Value,
+    },
+    Removed {
+        path: String,
+    },
+    Added {
+        path: String,
+    },
+}
+
+/// Write `value` out with the default [`FormatOptions`], parse it again, and report every way
+/// in which it came back differently, if any.
+pub fn roundtrip_check(value: &Value) -> Vec<Lossiness> {
+    roundtrip_check_with(value, &FormatOptions::default())
+}
+
+/// Like [`roundtrip_check`], writing the Lua with `opts`.
+pub fn roundtrip_check_with(value: &Value, opts: &FormatOptions) -> Vec<Lossiness> {
+    let lua = to_lua(value, opts);
+    let parsed = match parse_value(&lua) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return vec![Lossiness::Unparseable {
+                error: e.to_string(),
+            }]
+        }
+    };
+    let mut losses = Vec::new();
+    compare(value, &parsed, &mut String::new(), &mut losses);
+    losses
+}
+
+fn compare(old: &Value, new: &Value, at: &mut String, losses: &mut Vec<Lossiness>) {
+    let loss = match (old, new) {
+        (Value::Object(old), Value::Object(new)) => return compare_tables(old, new, at, losses),
+        (Value::Bool(a), Value::Bool(b)) if a == b => return,
+        (Value::Raw(a), Value::Raw(b)) if a == b => return,
+        (Value::String(_) | Value::Bytes(_), Value::String(_) | Value::Bytes(_)) => {
+            if old == new {
+                return;
+            }
+            Lossiness::String {
+                path: at.clone(),
+                old: old.clone(),
+                new: new.clone(),
+            }
+        }
+        (
+            Value::Float(a) | Value::Number { value: a, .. },
+            Value::Float(b) | Value::Number { value: b, .. },
+        ) => {
+            // -0 isn't 0, as it's written differently
+            if a.to_bits() == b.to_bits() || (a.is_nan() && b.is_nan()) {
+                return;
+            }
+            Lossiness::Number {
+                path: at.clone(),
+                old: *a,
+                new: *b,
+            }
+        }
+        _ => Lossiness::Changed {
+            path: at.clone(),
+            old: old.clone(),
+            new: new.clone(),
+        },
+    };
+    losses.push(loss);
+}
+
+fn compare_tables(old: &Table, new: &Table, at: &mut String, losses: &mut Vec<Lossiness>) {
+    let matches = (0..old.len())
+        .map(|i| (0..new.len()).find(|&j| id(new, j) == id(old, i)))
+        .collect::<Vec<_>>();
+    if !matches.iter().flatten().is_sorted() {
+        losses.push(Lossiness::Reordered { path: at.clone() });
+    }
+
+    let len = at.len();
+    let mut path = |table: &Table, i: usize| {
+        at.truncate(len);
+        match &table[i].0 {
+            Some(key) => path::push_key(at, key),
+            None => path::push_index(at, id(table, i).1),
+        }
+        at.clone()
+    };
+    for (i, j) in matches.iter().enumerate() {
+        let mut here = path(old, i);
+        match j {
+            Some(j) => compare(&old[i].1, &new[*j].1, &mut here, losses),
+            None => losses.push(Lossiness::Removed { path: here }),
+        }
+    }
+    for j in 0..new.len() {
+        if !matches.contains(&Some(j)) {
+            losses.push(Lossiness::Added { path: path(new, j) });
+        }
+    }
+    at.truncate(len);
+}
+
+/// What entry `i` of `table` is matched up by: its key and how many times that's come before,
+/// or its position.
+fn id(table: &Table, i: usize) -> (Option<&str>, usize) {
+    let key = table[i].0.as_deref();
+    let before = table[..i].iter().filter(|(k, _)| k.as_deref() == key);
+    (key, before.count())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::{FormatOptions, KeyOrder};
+    use crate::parse::{parse, Value};
+    use crate::roundtrip::{roundtrip_check, roundtrip_check_with, Lossiness};
+
+    fn lua(s: &str) -> Value {
+        Value::Object(parse(s).unwrap())
+    }
+
+    #[test]
+    fn losses() {
+        let v = lua(r#"{b = {1.5, -0, 0/0, 1/0, "x\0y"}, a = "\255", a = true, 3}"#);
+        assert_eq!(Vec::<Lossiness>::new(), roundtrip_check(&v));
+
+        let sorted = FormatOptions {
+            key_order: KeyOrder::Sorted,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            vec![Lossiness::Reordered {
+                path: String::new()
+            }],
+            roundtrip_check_with(&v, &sorted)
+        );
+
+        let mut v = lua("{n = 1, s = 2, r = 3, t = 4}");
+        v["n"] = Value::Number {
+            value: 1.,
+            raw: "1.25".to_string(),
+        };
+        v["s"] = Value::Bytes(b"ok".to_vec());
+        v["r"] = Value::Raw("{x = 1}".to_string());
+        assert_eq!(
+            vec![
+                Lossiness::Number {
+                    path: "n".to_string(),
+                    old: 1.,
+                    new: 1.25,
+                },
+                Lossiness::String {
+                    path: "s".to_string(),
+                    old: Value::Bytes(b"ok".to_vec()),
+                    new: Value::from("ok"),
+                },
+                Lossiness::Changed {
+                    path: "r".to_string(),
+                    old: Value::Raw("{x = 1}".to_string()),
+                    new: lua("{x = 1}"),
+                },
+            ],
+            roundtrip_check(&v)
+        );
+
+        v["r"] = Value::Raw("f(".to_string());
+        assert!(matches!(
+            roundtrip_check(&v).as_slice(),
+            [Lossiness::Unparseable { .. }]
+        ));
+
+        let keys = Value::Object(vec![
+            (None, Value::from(1.)),
+            (Some("1".into()), Value::Raw("2, 3".to_string())),
+        ]);
+        assert_eq!(
+            vec![
+                Lossiness::Changed {
+                    path: "1".to_string(),
+                    old: Value::Raw("2, 3".to_string()),
+                    new: Value::from(2.),
+                },
+                Lossiness::Added {
+                    path: "[1]".to_string()
+                },
+            ],
+            roundtrip_check(&keys)
+        );
+    }
+}
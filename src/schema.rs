@@ -0,0 +1,605 @@
+use std::fmt;
+
+use anyhow::{anyhow, bail, Result};
+use serde_json as json;
+
+use crate::parse::{Key, Value};
+use crate::path;
+
+/// The expected shape of a [`Value`], for [`validate`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schema {
+    Any,
+    Boolean,
+    String,
+    Number {
+        integer: bool,
+        minimum: Option<f64>,
+        maximum: Option<f64>,
+    },
+    /// A table with only positional entries.
+    Array {
+        items: Box<Schema>,
+        min_items: Option<usize>,
+        max_items: Option<usize>,
+    },
+    /// Any table; positional entries are checked as if they were named `0`, `1`...
+    Object {
+        properties: Vec<(String, Schema)>,
+        required: Vec<String>,
+        /// Whether keys not in `properties` are allowed.
+        additional: bool,
+    },
+    /// Any of these, as from a JSON Schema `type` listing several types.
+    AnyOf(Vec<Schema>),
+}
+
+impl Schema {
+    /// The JSON Schema name for what this accepts.
+    fn kind(&self) -> &'static str {
+        match self {
+            Schema::Any | Schema::AnyOf(_) => "any",
+            Schema::Boolean => "boolean",
+            Schema::String => "string",
+            Schema::Number { integer: true, .. } => "integer",
+            Schema::Number { .. } => "number",
+            Schema::Array { .. } => "array",
+            Schema::Object { .. } => "object",
+        }
+    }
+
+    /// Whether `value` is of the type this accepts, regardless of its contents.
+    fn accepts_type(&self, value: &Value) -> bool {
+        match (self, value) {
+            (Schema::Any, _) => true,
+            (Schema::AnyOf(options), value) => options.iter().any(|o| o.accepts_type(value)),
+            (Schema::Boolean, Value::Bool(_))
+            | (Schema::String, Value::String(_) | Value::Bytes(_)) => true,
+            (Schema::Number { .. }, Value::Float(_) | Value::Number { .. }) => true,
+            (Schema::Array { .. } | Schema::Object { .. }, Value::Object(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Somewhere a value didn't match its schema, at a path in the syntax of
+/// [`Value::get_path`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "(root)"
+        } else {
+            &self.path
+        };
+        write!(f, "{path}: {}", self.message)
+    }
+}
+
+/// JSON Schema keywords which don't affect validation.
+const ANNOTATIONS: &[&str] = &[
+    "$schema",
+    "$id",
+    "title",
+    "description",
+    "default",
+    "examples",
+];
+
+impl Schema {
+    /// Read the subset of JSON Schema which maps onto [`Schema`]: `type`, `properties`,
+    /// `required`, `additionalProperties` (as a boolean), `items`, `minItems`, `maxItems`,
+    /// `minimum` and `maximum`. Anything else is an error, rather than being ignored.
+    pub fn from_json(schema: &json::Value) -> Result<Schema> {
+        let obj = match schema {
+            json::Value::Bool(true) => return Ok(Schema::Any),
+            json::Value::Object(obj) => obj,
+            other => bail!("expected a schema object, found {other}"),
+        };
+        let kinds = match obj.get("type") {
+            None => Vec::new(),
+            Some(json::Value::String(kind)) => vec![kind.as_str()],
+            Some(json::Value::Array(kinds)) => kinds
+                .iter()
+                .map(|k| k.as_str().ok_or_else(|| anyhow!("unsupported type: {k}")))
+                .collect::<Result<_>>()?,
+            Some(other) => bail!("unsupported type: {other}"),
+        };
+        let known = kinds
+            .iter()
+            .map(|kind| keywords(kind))
+            .collect::<Result<Vec<_>>>()?;
+        if let Some(key) = obj.keys().find(|k| {
+            "type" != *k
+                && !ANNOTATIONS.contains(&k.as_str())
+                && !known.iter().any(|known| known.contains(&k.as_str()))
+        }) {
+            bail!("unsupported keyword: {key:?}");
+        }
+
+        if let Some(json::Value::Array(_)) = obj.get("type") {
+            let mut options = Vec::new();
+            for (kind, keywords) in kinds.into_iter().zip(known) {
+                let mut single = json::Map::new();
+                single.insert("type".to_string(), kind.into());
+                for keyword in keywords {
+                    if let Some(v) = obj.get(*keyword) {
+                        single.insert(keyword.to_string(), v.clone());
+                    }
+                }
+                options.push(Schema::from_json(&json::Value::Object(single))?);
+            }
+            return Ok(Schema::AnyOf(options));
+        }
+
+        let f64_of = |key: &str| -> Result<Option<f64>> {
+            obj.get(key)
+                .map(|v| v.as_f64().ok_or_else(|| anyhow!("{key} must be a number")))
+                .transpose()
+        };
+        let usize_of = |key: &str| -> Result<Option<usize>> {
+            obj.get(key)
+                .map(|v| {
+                    v.as_u64()
+                        .map(|n| n as usize)
+                        .ok_or_else(|| anyhow!("{key} must be a non-negative integer"))
+                })
+                .transpose()
+        };
+
+        Ok(match obj.get("type").and_then(|t| t.as_str()) {
+            None => Schema::Any,
+            Some("boolean") => Schema::Boolean,
+            Some("string") => Schema::String,
+            Some(kind) if kind == "number" || kind == "integer" => Schema::Number {
+                integer: kind == "integer",
+                minimum: f64_of("minimum")?,
+                maximum: f64_of("maximum")?,
+            },
+            Some("array") => Schema::Array {
+                items: Box::new(match obj.get("items") {
+                    Some(items) => Schema::from_json(items)?,
+                    None => Schema::Any,
+                }),
+                min_items: usize_of("minItems")?,
+                max_items: usize_of("maxItems")?,
+            },
+            Some(_) => {
+                let properties = match obj.get("properties") {
+                    Some(json::Value::Object(props)) => props
+                        .iter()
+                        .map(|(k, v)| Ok((k.clone(), Schema::from_json(v)?)))
+                        .collect::<Result<_>>()?,
+                    Some(_) => bail!("properties must be an object"),
+                    None => Vec::new(),
+                };
+                let required = match obj.get("required") {
+                    Some(json::Value::Array(keys)) => keys
+                        .iter()
+                        .map(|k| {
+                            k.as_str()
+                                .map(|k| k.to_string())
+                                .ok_or_else(|| anyhow!("required must list strings"))
+                        })
+                        .collect::<Result<_>>()?,
+                    Some(_) => bail!("required must be an array"),
+                    None => Vec::new(),
+                };
+                let additional = match obj.get("additionalProperties") {
+                    Some(json::Value::Bool(b)) => *b,
+                    Some(_) => bail!("additionalProperties must be a boolean"),
+                    None => true,
+                };
+                Schema::Object {
+                    properties,
+                    required,
+                    additional,
+                }
+            }
+        })
+    }
+}
+
+/// The keywords, other than `type`, which apply to a JSON Schema type.
+fn keywords(kind: &str) -> Result<&'static [&'static str]> {
+    Ok(match kind {
+        "boolean" | "string" => &[],
+        "number" | "integer" => &["minimum", "maximum"],
+        "array" => &["items", "minItems", "maxItems"],
+        "object" => &["properties", "required", "additionalProperties"],
+        other => bail!("unsupported type: {other:?}"),
+    })
+}
+
+/// Everywhere `value` doesn't match `schema`.
+pub fn validate(value: &Value, schema: &Schema) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    validate_at(value, schema, &mut String::new(), &mut violations);
+    violations
+}
+
+fn validate_at(value: &Value, schema: &Schema, at: &mut String, out: &mut Vec<Violation>) {
+    let mut violation = |message: String| {
+        out.push(Violation {
+            path: at.clone(),
+            message,
+        })
+    };
+    match (schema, value) {
+        (Schema::Any, _)
+        | (Schema::Boolean, Value::Bool(_))
+        | (Schema::String, Value::String(_) | Value::Bytes(_)) => {}
+        (
+            Schema::Number {
+                integer,
+                minimum,
+                maximum,
+            },
+            Value::Float(f) | Value::Number { value: f, .. },
+        ) => {
+            if *integer && f.fract() != 0. {
+                violation(format!("expected an integer, found {f}"));
+            }
+            if let Some(min) = minimum.filter(|min| f < min) {
+                violation(format!("{f} is less than the minimum of {min}"));
+            }
+            if let Some(max) = maximum.filter(|max| f > max) {
+                violation(format!("{f} is more than the maximum of {max}"));
+            }
+        }
+        (
+            Schema::Array {
+                items,
+                min_items,
+                max_items,
+            },
+            Value::Object(table),
+        ) => {
+            if !value.is_array_like() {
+                violation("expected array, found table with named entries".to_string());
+                return;
+            }
+            if let Some(min) = min_items.filter(|min| table.len() < *min) {
+                violation(format!(
+                    "expected at least {min} items, found {}",
+                    table.len()
+                ));
+            }
+            if let Some(max) = max_items.filter(|max| table.len() > *max) {
+                violation(format!(
+                    "expected at most {max} items, found {}",
+                    table.len()
+                ));
+            }
+            let len = at.len();
+            for (i, (_, v)) in table.iter().enumerate() {
+                path::push_index(at, i);
+                validate_at(v, items, at, out);
+                at.truncate(len);
+            }
+        }
+        (
+            Schema::Object {
+                properties,
+                required,
+                additional,
+            },
+            Value::Object(table),
+        ) => {
+            for key in required {
+                if value.get_opt(key).is_none() {
+                    violation(format!("missing required key {key:?}"));
+                }
+            }
+            let len = at.len();
+            for (i, (k, v)) in table.iter().enumerate() {
+                let key = k.clone().map_or_else(|| i.to_string(), Key::into_string);
+                match k {
+                    Some(k) => path::push_key(at, k),
+                    None => path::push_index(at, i),
+                }
+                match properties.iter().find(|(name, _)| *name == key) {
+                    Some((_, schema)) => validate_at(v, schema, at, out),
+                    None if !additional => out.push(Violation {
+                        path: at.clone(),
+                        message: "unexpected key".to_string(),
+                    }),
+                    None => {}
+                }
+                at.truncate(len);
+            }
+        }
+        (Schema::AnyOf(options), value) => {
+            let matching = options
+                .iter()
+                .filter(|o| o.accepts_type(value))
+                .collect::<Vec<_>>();
+            match matching.as_slice() {
+                [] => {
+                    let kinds = options.iter().map(|o| o.kind()).collect::<Vec<_>>();
+                    violation(format!(
+                        "expected one of {}, found {}",
+                        kinds.join(", "),
+                        value.type_name()
+                    ));
+                }
+                [only] => validate_at(value, only, at, out),
+                several => {
+                    if several.iter().all(|o| !validate(value, o).is_empty()) {
+                        validate_at(value, several[0], at, out);
+                    }
+                }
+            }
+        }
+        (expected, found) => {
+            violation(format!(
+                "expected {}, found {}",
+                expected.kind(),
+                found.type_name()
+            ));
+        }
+    }
+}
+
+/// Describe the samples as a JSON Schema: the types seen at each path, with keys which were in
+/// every table at that path listed as `required`.
+pub fn infer_schema(samples: &[Value]) -> json::Value {
+    let mut shape = Shape::default();
+    for sample in samples {
+        shape.observe(sample);
+    }
+    let mut schema = json::Map::new();
+    schema.insert(
+        "$schema".to_string(),
+        "https://json-schema.org/draft/2020-12/schema".into(),
+    );
+    if let json::Value::Object(inferred) = shape.to_json() {
+        schema.extend(inferred);
+    }
+    json::Value::Object(schema)
+}
+
+/// Everything seen at one path, for [`infer_schema`].
+#[derive(Default)]
+struct Shape {
+    boolean: bool,
+    string: bool,
+    /// Whether any numbers were seen, and whether any of those weren't whole.
+    number: Option<bool>,
+    /// Tables without entries, which could be either an array or an object.
+    empty: usize,
+    items: Option<Box<Shape>>,
+    /// Tables with named entries, and how often each key was present in them.
+    objects: usize,
+    properties: Vec<(String, usize, Shape)>,
+}
+
+impl Shape {
+    fn observe(&mut self, value: &Value) {
+        match value {
+            Value::Bool(_) => self.boolean = true,
+            Value::String(_) | Value::Bytes(_) | Value::Raw(_) => self.string = true,
+            Value::Float(f) | Value::Number { value: f, .. } => {
+                self.number = Some(self.number.unwrap_or(false) || 0. != f.fract());
+            }
+            Value::Object(table) if table.is_empty() => self.empty += 1,
+            Value::Object(table) if value.is_array_like() => {
+                let items = self.items.get_or_insert_with(Default::default);
+                for (_, v) in table {
+                    items.observe(v);
+                }
+            }
+            Value::Object(table) => {
+                self.objects += 1;
+                let mut seen = Vec::new();
+                for (i, (k, v)) in table.iter().enumerate() {
+                    let key = k.clone().map_or_else(|| i.to_string(), Key::into_string);
+                    let at = match self.properties.iter().position(|(p, _, _)| *p == key) {
+                        Some(at) => at,
+                        None => {
+                            self.properties.push((key, 0, Shape::default()));
+                            self.properties.len() - 1
+                        }
+                    };
+                    if !seen.contains(&at) {
+                        seen.push(at);
+                        self.properties[at].1 += 1;
+                    }
+                    self.properties[at].2.observe(v);
+                }
+            }
+        }
+    }
+
+    fn to_json(&self) -> json::Value {
+        let mut obj = json::Map::new();
+        let mut kinds = Vec::new();
+        if self.boolean {
+            kinds.push("boolean");
+        }
+        if self.string {
+            kinds.push("string");
+        }
+        match self.number {
+            Some(false) => kinds.push("integer"),
+            Some(true) => kinds.push("number"),
+            None => {}
+        }
+        let object = self.objects > 0;
+        if self.items.is_some() || (self.empty > 0 && !object) {
+            kinds.push("array");
+            if let Some(items) = &self.items {
+                obj.insert("items".to_string(), items.to_json());
+            }
+        }
+        if object {
+            kinds.push("object");
+            let tables = self.objects + self.empty;
+            let properties = self
+                .properties
+                .iter()
+                .map(|(k, _, shape)| (k.clone(), shape.to_json()))
+                .collect();
+            let required = self
+                .properties
+                .iter()
+                .filter(|(_, seen, _)| *seen == tables)
+                .map(|(k, _, _)| json::Value::from(k.clone()))
+                .collect::<Vec<_>>();
+            obj.insert("properties".to_string(), json::Value::Object(properties));
+            if !required.is_empty() {
+                obj.insert("required".to_string(), json::Value::Array(required));
+            }
+        }
+        match kinds.as_slice() {
+            [] => {}
+            [kind] => {
+                obj.insert("type".to_string(), (*kind).into());
+            }
+            kinds => {
+                obj.insert("type".to_string(), kinds.to_vec().into());
+            }
+        }
+        let mut ordered = json::Map::new();
+        if let Some(kind) = obj.remove("type") {
+            ordered.insert("type".to_string(), kind);
+        }
+        ordered.extend(obj);
+        json::Value::Object(ordered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::parse::{parse, Value};
+    use crate::schema::{infer_schema, validate, Schema};
+
+    fn lua(s: &str) -> Value {
+        Value::Object(parse(s).unwrap())
+    }
+
+    fn config() -> Schema {
+        Schema::from_json(&json!({
+            "title": "config",
+            "type": "object",
+            "properties": {
+                "width": {"type": "integer", "minimum": 1, "maximum": 4096},
+                "name": {"type": "string"},
+                "tags": {"type": "array", "items": {"type": "string"}, "maxItems": 2},
+                "debug": {"type": "boolean"},
+            },
+            "required": ["width", "name"],
+            "additionalProperties": false,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn valid() {
+        let schema = config();
+        assert_eq!(
+            Vec::<String>::new(),
+            validate(&lua(r#"{width = 1920, name = "x", tags = {"a"}}"#), &schema)
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+        );
+        assert!(validate(&lua("{}"), &Schema::Any).is_empty());
+    }
+
+    #[test]
+    fn violations() {
+        let found = validate(
+            &lua(r#"{width = 1.5, tags = {"a", 2, "c"}, debug = "yes", extra = 1}"#),
+            &config(),
+        );
+        assert_eq!(
+            vec![
+                r#"(root): missing required key "name""#,
+                "width: expected an integer, found 1.5",
+                "tags: expected at most 2 items, found 3",
+                "tags[1]: expected string, found number",
+                "debug: expected boolean, found string",
+                "extra: unexpected key",
+            ],
+            found.iter().map(|v| v.to_string()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "width: 0 is less than the minimum of 1",
+            validate(&lua(r#"{width = 0, name = "n"}"#), &config())[0].to_string()
+        );
+        assert_eq!(
+            "tags: expected array, found table with named entries",
+            validate(
+                &lua(r#"{width = 1, name = "n", tags = {a = 1}}"#),
+                &config()
+            )[0]
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn any_of() {
+        let schema = Schema::from_json(&json!({
+            "type": ["string", "integer"],
+            "minimum": 0,
+        }))
+        .unwrap();
+        assert!(validate(&Value::String("x".to_string()), &schema).is_empty());
+        assert!(validate(&Value::Float(3.), &schema).is_empty());
+        assert_eq!(
+            "(root): -1 is less than the minimum of 0",
+            validate(&Value::Float(-1.), &schema)[0].to_string()
+        );
+        assert_eq!(
+            "(root): expected one of string, integer, found boolean",
+            validate(&Value::Bool(true), &schema)[0].to_string()
+        );
+        assert!(Schema::from_json(&json!({"type": ["string"], "items": {}})).is_err());
+    }
+
+    #[test]
+    fn infer() {
+        let samples = [
+            lua(r#"{name = "a", hp = 10, tags = {"x"}, pos = {x = 1.5}}"#),
+            lua(r#"{name = "b", hp = 12, tags = {}, extra = true}"#),
+            lua(r#"{name = 3, hp = 12, tags = {"y", "z"}}"#),
+        ];
+        let schema = infer_schema(&samples);
+        assert_eq!(
+            json!({
+                "$schema": "https://json-schema.org/draft/2020-12/schema",
+                "type": "object",
+                "properties": {
+                    "name": {"type": ["string", "integer"]},
+                    "hp": {"type": "integer"},
+                    "tags": {"type": "array", "items": {"type": "string"}},
+                    "pos": {"type": "object", "properties": {"x": {"type": "number"}}, "required": ["x"]},
+                    "extra": {"type": "boolean"},
+                },
+                "required": ["name", "hp", "tags"],
+            }),
+            schema
+        );
+        let schema = Schema::from_json(&schema).unwrap();
+        for sample in &samples {
+            assert!(validate(sample, &schema).is_empty());
+        }
+        assert!(!validate(&lua(r#"{hp = 1, tags = {}}"#), &schema).is_empty());
+    }
+
+    #[test]
+    fn unsupported() {
+        assert!(Schema::from_json(&json!({"type": "null"})).is_err());
+        assert!(Schema::from_json(&json!({"type": "string", "pattern": "x"})).is_err());
+        assert!(Schema::from_json(&json!({"type": "object", "required": "x"})).is_err());
+        assert_eq!(Schema::Any, Schema::from_json(&json!({})).unwrap());
+    }
+}
@@ -0,0 +1,470 @@
+use std::fmt;
+
+use anyhow::Result;
+use serde::ser::{self, Serialize};
+
+use crate::de::integer;
+use crate::format::{to_lua, FormatOptions};
+use crate::parse::{string_value, Table, Value};
+
+/// Render `value` as a Lua expression, laid out like [`FormatOptions::default`].
+///
+/// ```
+/// #[derive(serde::Serialize)]
+/// struct Item {
+///     name: &'static str,
+///     weight: f64,
+///     tags: Vec<&'static str>,
+/// }
+///
+/// let item = Item { name: "sword", weight: 1.5, tags: vec!["sharp"] };
+/// assert_eq!(
+///     "{\n  name = \"sword\",\n  weight = 1.5,\n  tags = {\n    \"sharp\",\n  },\n}",
+///     lua2json::ser::to_lua_string(&item).unwrap()
+/// );
+/// ```
+pub fn to_lua_string<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    to_lua_string_with(value, &FormatOptions::default())
+}
+
+/// Render `value` as a Lua expression, laid out according to `opts`.
+pub fn to_lua_string_with<T: Serialize + ?Sized>(
+    value: &T,
+    opts: &FormatOptions,
+) -> Result<String> {
+    Ok(to_lua(&to_value(value)?, opts))
+}
+
+/// Convert `value` to a [`Value`].
+///
+/// Sequences, tuples and tuple structs become tables of positional entries, and maps and
+/// structs tables of named entries, in the order they're serialized. Map keys must be strings,
+/// numbers or booleans. Unit enum variants are their name as a string, and other variants a
+/// table with one entry named after the variant. `None` and `()` are Lua's `nil`, so fields and
+/// map entries with them are left out, but they're an error anywhere else.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value> {
+    match value.serialize(Serializer)? {
+        Some(value) => Ok(value),
+        None => Err(Error::nil("the value").into()),
+    }
+}
+
+#[derive(Debug)]
+struct Error(String);
+
+impl Error {
+    fn nil(what: &str) -> Error {
+        Error(format!("{what} can't be nil"))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error(msg.to_string())
+    }
+}
+
+/// Serializes to a value, or `None` for `nil`.
+struct Serializer;
+
+fn some(value: Value) -> Result<Option<Value>, Error> {
+    Ok(Some(value))
+}
+
+fn named(name: &str, value: Value) -> Value {
+    Value::Object(vec![(Some(name.into()), value)])
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Option<Value>;
+    type Error = Error;
+    type SerializeSeq = Seq;
+    type SerializeTuple = Seq;
+    type SerializeTupleStruct = Seq;
+    type SerializeTupleVariant = Seq;
+    type SerializeMap = Map;
+    type SerializeStruct = Map;
+    type SerializeStructVariant = Map;
+
+    fn serialize_bool(self, b: bool) -> Result<Option<Value>, Error> {
+        some(Value::Bool(b))
+    }
+    fn serialize_i8(self, n: i8) -> Result<Option<Value>, Error> {
+        some(integer(n.into()))
+    }
+    fn serialize_i16(self, n: i16) -> Result<Option<Value>, Error> {
+        some(integer(n.into()))
+    }
+    fn serialize_i32(self, n: i32) -> Result<Option<Value>, Error> {
+        some(integer(n.into()))
+    }
+    fn serialize_i64(self, n: i64) -> Result<Option<Value>, Error> {
+        some(integer(n.into()))
+    }
+    fn serialize_i128(self, n: i128) -> Result<Option<Value>, Error> {
+        some(integer(n))
+    }
+    fn serialize_u8(self, n: u8) -> Result<Option<Value>, Error> {
+        some(integer(n.into()))
+    }
+    fn serialize_u16(self, n: u16) -> Result<Option<Value>, Error> {
+        some(integer(n.into()))
+    }
+    fn serialize_u32(self, n: u32) -> Result<Option<Value>, Error> {
+        some(integer(n.into()))
+    }
+    fn serialize_u64(self, n: u64) -> Result<Option<Value>, Error> {
+        some(integer(n.into()))
+    }
+    fn serialize_u128(self, n: u128) -> Result<Option<Value>, Error> {
+        match i128::try_from(n) {
+            Ok(n) => some(integer(n)),
+            Err(_) => some(Value::Number {
+                value: n as f64,
+                raw: n.to_string(),
+            }),
+        }
+    }
+    fn serialize_f32(self, f: f32) -> Result<Option<Value>, Error> {
+        some(Value::Float(f.into()))
+    }
+    fn serialize_f64(self, f: f64) -> Result<Option<Value>, Error> {
+        some(Value::Float(f))
+    }
+    fn serialize_char(self, c: char) -> Result<Option<Value>, Error> {
+        some(Value::String(c.to_string()))
+    }
+    fn serialize_str(self, s: &str) -> Result<Option<Value>, Error> {
+        some(Value::String(s.to_string()))
+    }
+    fn serialize_bytes(self, b: &[u8]) -> Result<Option<Value>, Error> {
+        some(string_value(b.to_vec()))
+    }
+    fn serialize_none(self) -> Result<Option<Value>, Error> {
+        Ok(None)
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Option<Value>, Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<Option<Value>, Error> {
+        Ok(None)
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Option<Value>, Error> {
+        Ok(None)
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Option<Value>, Error> {
+        some(Value::String(variant.to_string()))
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Option<Value>, Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Option<Value>, Error> {
+        match value.serialize(Serializer)? {
+            Some(value) => some(named(variant, value)),
+            None => some(Value::Object(Table::new())),
+        }
+    }
+    fn serialize_seq(self, len: Option<usize>) -> Result<Seq, Error> {
+        Ok(Seq {
+            variant: None,
+            table: Table::with_capacity(len.unwrap_or(0)),
+        })
+    }
+    fn serialize_tuple(self, len: usize) -> Result<Seq, Error> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Seq, Error> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Seq, Error> {
+        Ok(Seq {
+            variant: Some(variant),
+            table: Table::with_capacity(len),
+        })
+    }
+    fn serialize_map(self, len: Option<usize>) -> Result<Map, Error> {
+        Ok(Map {
+            variant: None,
+            table: Table::with_capacity(len.unwrap_or(0)),
+            key: None,
+        })
+    }
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Map, Error> {
+        self.serialize_map(Some(len))
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Map, Error> {
+        Ok(Map {
+            variant: Some(variant),
+            table: Table::with_capacity(len),
+            key: None,
+        })
+    }
+}
+
+struct Seq {
+    variant: Option<&'static str>,
+    table: Table,
+}
+
+impl Seq {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let value = value
+            .serialize(Serializer)?
+            .ok_or_else(|| Error::nil("elements of sequences"))?;
+        self.table.push((None, value));
+        Ok(())
+    }
+    fn finish(self) -> Result<Option<Value>, Error> {
+        let table = Value::Object(self.table);
+        some(match self.variant {
+            Some(variant) => named(variant, table),
+            None => table,
+        })
+    }
+}
+
+impl ser::SerializeSeq for Seq {
+    type Ok = Option<Value>;
+    type Error = Error;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Option<Value>, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for Seq {
+    type Ok = Option<Value>;
+    type Error = Error;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Option<Value>, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for Seq {
+    type Ok = Option<Value>;
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Option<Value>, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for Seq {
+    type Ok = Option<Value>;
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Option<Value>, Error> {
+        self.finish()
+    }
+}
+
+struct Map {
+    variant: Option<&'static str>,
+    table: Table,
+    /// The key of the entry whose value is next.
+    key: Option<String>,
+}
+
+impl Map {
+    fn insert<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> Result<(), Error> {
+        if let Some(value) = value.serialize(Serializer)? {
+            self.table.push((Some(key.into()), value));
+        }
+        Ok(())
+    }
+    fn finish(self) -> Result<Option<Value>, Error> {
+        let table = Value::Object(self.table);
+        some(match self.variant {
+            Some(variant) => named(variant, table),
+            None => table,
+        })
+    }
+}
+
+fn key<T: Serialize + ?Sized>(key: &T) -> Result<String, Error> {
+    match key.serialize(Serializer)? {
+        Some(Value::String(s)) => Ok(s),
+        Some(Value::Float(f)) => Ok(f.to_string()),
+        Some(Value::Number { raw, .. }) => Ok(raw),
+        Some(Value::Bool(b)) => Ok(b.to_string()),
+        Some(other) => Err(Error(format!(
+            "keys must be strings, numbers or booleans, not a {}",
+            other.type_name()
+        ))),
+        None => Err(Error::nil("keys")),
+    }
+}
+
+impl ser::SerializeMap for Map {
+    type Ok = Option<Value>;
+    type Error = Error;
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, k: &T) -> Result<(), Error> {
+        self.key = Some(key(k)?);
+        Ok(())
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .expect("serialize_key before serialize_value");
+        self.insert(key, value)
+    }
+    fn end(self) -> Result<Option<Value>, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for Map {
+    type Ok = Option<Value>;
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.insert(name.to_string(), value)
+    }
+    fn end(self) -> Result<Option<Value>, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for Map {
+    type Ok = Option<Value>;
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.insert(name.to_string(), value)
+    }
+    fn end(self) -> Result<Option<Value>, Error> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Serialize;
+
+    use crate::format::{FormatOptions, Indent, Quote};
+    use crate::ser::{to_lua_string_with, to_value};
+
+    #[derive(Serialize)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Rect { w: u32, h: u32 },
+        Line(i8, i8),
+    }
+
+    #[derive(Serialize)]
+    struct Config {
+        name: String,
+        #[serde(rename = "max users")]
+        max_users: u64,
+        debug: Option<bool>,
+        shapes: Vec<Shape>,
+        limits: BTreeMap<u8, (bool, char)>,
+        #[serde(with = "serde_bytes_like")]
+        blob: Vec<u8>,
+    }
+
+    mod serde_bytes_like {
+        pub fn serialize<S: serde::Serializer>(b: &[u8], s: S) -> Result<S::Ok, S::Error> {
+            s.serialize_bytes(b)
+        }
+    }
+
+    #[test]
+    fn structs() {
+        let config = Config {
+            name: "app".to_string(),
+            max_users: 10,
+            debug: None,
+            shapes: vec![
+                Shape::Point,
+                Shape::Circle(0.5),
+                Shape::Rect { w: 2, h: 3 },
+                Shape::Line(-1, 1),
+            ],
+            limits: BTreeMap::from([(1, (true, 'x'))]),
+            blob: b"\xff".to_vec(),
+        };
+        let opts = FormatOptions {
+            indent: Indent::None,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            r#"{name = "app", ["max users"] = 10, shapes = {"Point", {Circle = 0.5}, {Rect = {w = 2, h = 3}}, {Line = {-1, 1}}}, limits = {["1"] = {true, "x"}}, blob = "\255"}"#,
+            to_lua_string_with(&config, &opts).unwrap()
+        );
+        let opts = FormatOptions {
+            indent: Indent::None,
+            quote: Quote::Single,
+            quote_keys: true,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            "{['a'] = 'b'}",
+            to_lua_string_with(&BTreeMap::from([("a", "b")]), &opts).unwrap()
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            "elements of sequences can't be nil",
+            to_value(&vec![Some(1), None]).unwrap_err().to_string()
+        );
+        assert!(to_value(&None::<u8>).is_err());
+        assert!(to_value(&BTreeMap::from([(vec![1], 2)])).is_err());
+    }
+}
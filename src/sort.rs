@@ -0,0 +1,152 @@
+use anyhow::Result;
+
+use crate::canonical::CanonicalValue;
+use crate::parse::{Table, Value};
+use crate::path::{lookup, segments, Segment};
+
+/// How [`Value::sort_arrays`] sorts arrays: by a value inside each of their elements.
+#[derive(Debug, Clone)]
+pub struct SortBy {
+    path: Vec<Segment>,
+    /// Whether `path` is just a name, so applies to every array.
+    anywhere: bool,
+    pub descending: bool,
+}
+
+impl SortBy {
+    /// `path` is the path of an array in the syntax of [`Value::get_path`], followed by the
+    /// path inside each of its elements of the value to sort them by, like `players.score` for
+    /// the array `players` of tables with a `score`. A path of just a name, like `score`,
+    /// sorts every array by it.
+    pub fn new(path: &str, descending: bool) -> Result<SortBy> {
+        let path = segments(path)?
+            .into_iter()
+            .map(|(segment, _)| segment)
+            .collect::<Vec<_>>();
+        let anywhere = matches!(path.as_slice(), [Segment::Key(_)]);
+        Ok(SortBy {
+            path,
+            anywhere,
+            descending,
+        })
+    }
+
+    /// The path inside the elements of the array at `path` to sort them by, if it's sorted.
+    fn within(&self, path: &[Segment]) -> Option<&[Segment]> {
+        if self.anywhere {
+            return Some(&self.path);
+        }
+        self.path.strip_prefix(path).filter(|rest| !rest.is_empty())
+    }
+}
+
+impl Value {
+    /// Sort the arrays which `sort` applies to, which are tables of only positional entries,
+    /// comparing values in the order of [`CanonicalValue`]. Elements without the value sort
+    /// last, and ones with equal values stay in the same order.
+    pub fn sort_arrays(&mut self, sort: &SortBy) {
+        sort_arrays(self, sort, &mut Vec::new());
+    }
+
+    /// Cut every array, which is a table of only positional entries, down to its first `max`
+    /// elements, as for a preview of a large document.
+    pub fn limit_arrays(&mut self, max: usize) {
+        let Some(table) = self.as_table_mut() else {
+            return;
+        };
+        if is_array(table) {
+            table.truncate(max);
+        }
+        for (_, v) in table {
+            v.limit_arrays(max);
+        }
+    }
+}
+
+fn is_array(table: &Table) -> bool {
+    table.iter().all(|(k, _)| k.is_none())
+}
+
+fn sort_arrays(value: &mut Value, sort: &SortBy, path: &mut Vec<Segment>) {
+    let Some(table) = value.as_table_mut() else {
+        return;
+    };
+    // before the arrays inside, so their paths are by their positions once sorted
+    if let Some(by) = sort.within(path).filter(|_| is_array(table)) {
+        let mut keyed = std::mem::take(table)
+            .into_iter()
+            .map(|entry| {
+                let key = lookup(&entry.1, by).map(|v| CanonicalValue::new(v.clone()));
+                (key, entry)
+            })
+            .collect::<Vec<_>>();
+        keyed.sort_by(|(a, _), (b, _)| match (a, b) {
+            (Some(a), Some(b)) if sort.descending => b.cmp(a),
+            (Some(a), Some(b)) => a.cmp(b),
+            (a, b) => a.is_none().cmp(&b.is_none()),
+        });
+        table.extend(keyed.into_iter().map(|(_, entry)| entry));
+    }
+    let mut positional = 0;
+    for (k, v) in table {
+        path.push(match k {
+            Some(k) => Segment::Key(k.to_string()),
+            None => {
+                positional += 1;
+                Segment::Index(positional - 1)
+            }
+        });
+        sort_arrays(v, sort, path);
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{parse, Value};
+    use crate::sort::SortBy;
+
+    fn lua(s: &str) -> Value {
+        Value::Object(parse(s).unwrap())
+    }
+
+    #[test]
+    fn sort() {
+        let mut v = lua(
+            r#"{players = {{n = "a", score = 2}, {n = "b"}, {n = "c", score = 10}, {n = "d", score = 2}},
+                other = {{score = 2}, {score = 1}}}"#,
+        );
+        v.sort_arrays(&SortBy::new("players.score", false).unwrap());
+        let names = |v: &Value| -> Vec<String> {
+            v["players"]
+                .as_table()
+                .unwrap()
+                .iter()
+                .map(|(_, p)| p["n"].as_str().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(["a", "d", "c", "b"], names(&v).as_slice());
+        assert_eq!(lua("{{score = 2}, {score = 1}}"), v["other"]);
+
+        v.sort_arrays(&SortBy::new("players.score", true).unwrap());
+        assert_eq!(["c", "a", "d", "b"], names(&v).as_slice());
+
+        // everywhere, by a name
+        v.sort_arrays(&SortBy::new("score", false).unwrap());
+        assert_eq!(lua("{{score = 1}, {score = 2}}"), v["other"]);
+        let mut v = lua("{{x = {3}}, {x = {1}}}");
+        v.sort_arrays(&SortBy::new("x[0]", false).unwrap());
+        assert_eq!(lua("{{x = {1}}, {x = {3}}}"), v);
+        assert!(SortBy::new("a[", false).is_err());
+    }
+
+    #[test]
+    fn limit() {
+        let mut v = lua("{a = {1, 2, 3, {4, 5, 6}}, b = {x = 1, y = 2, z = 3}, 7, 8}");
+        v.limit_arrays(2);
+        assert_eq!(lua("{a = {1, 2}, b = {x = 1, y = 2, z = 3}, 7, 8}"), v);
+        let mut v = lua("{{1, 2, 3}, {4}, {5}}");
+        v.limit_arrays(2);
+        assert_eq!(lua("{{1, 2}, {4}}"), v);
+    }
+}
@@ -0,0 +1,123 @@
+use core::mem::size_of;
+
+use crate::parse::{Key, Table, Value};
+
+/// Counts of what's in a value, from [`Value::stats`], for deciding whether it's small enough
+/// to work on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Stats {
+    /// The number of tables, including the value itself if it's a table.
+    pub tables: usize,
+    /// The number of entries in all the tables together.
+    pub entries: usize,
+    /// How deeply tables are nested, where a table of only scalars has depth one, and a scalar
+    /// has depth zero.
+    pub max_depth: usize,
+    /// The number of [`Value::String`]s and [`Value::Bytes`], not counting keys.
+    pub strings: usize,
+    /// The length of all of those strings together, in bytes.
+    pub string_bytes: usize,
+    /// The length of all the names of entries together, in bytes.
+    pub key_bytes: usize,
+    pub numbers: usize,
+    pub booleans: usize,
+    /// The number of [`Value::Raw`]s.
+    pub raw: usize,
+    /// Roughly how much memory the value takes up on the heap, in bytes: what's allocated for
+    /// tables, strings and keys, but not any overhead of the allocator.
+    pub heap_bytes: usize,
+}
+
+impl Value {
+    /// Count what's in this value, in one pass over it.
+    pub fn stats(&self) -> Stats {
+        let mut stats = Stats::default();
+        count(self, 0, &mut stats);
+        stats
+    }
+}
+
+fn count(value: &Value, depth: usize, stats: &mut Stats) {
+    match value {
+        Value::Object(table) => count_table(table, depth + 1, stats),
+        Value::String(s) => {
+            stats.strings += 1;
+            stats.string_bytes += s.len();
+            stats.heap_bytes += s.capacity();
+        }
+        Value::Bytes(b) => {
+            stats.strings += 1;
+            stats.string_bytes += b.len();
+            stats.heap_bytes += b.capacity();
+        }
+        Value::Float(_) => stats.numbers += 1,
+        Value::Number { raw, .. } => {
+            stats.numbers += 1;
+            stats.heap_bytes += raw.capacity();
+        }
+        Value::Bool(_) => stats.booleans += 1,
+        Value::Raw(raw) => {
+            stats.raw += 1;
+            stats.heap_bytes += raw.capacity();
+        }
+    }
+}
+
+fn count_table(table: &Table, depth: usize, stats: &mut Stats) {
+    stats.tables += 1;
+    stats.entries += table.len();
+    stats.max_depth = stats.max_depth.max(depth);
+    stats.heap_bytes += table.capacity() * size_of::<(Option<Key>, Value)>();
+    for (k, v) in table {
+        match k {
+            Some(Key::Name(k) | Key::Integer(k)) => {
+                stats.key_bytes += k.len();
+                stats.heap_bytes += k.capacity();
+            }
+            // which is allocated once for all the keys sharing it
+            Some(Key::Shared(k)) => stats.key_bytes += k.len(),
+            None => {}
+        }
+        count(v, depth, stats);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{parse, Key, Value};
+    use crate::stats::Stats;
+
+    #[test]
+    fn stats() {
+        let v = Value::Object(
+            parse(r#"{name = "abc", tags = {"x", "\255", {}}, hp = 1, on = true, 2.5}"#).unwrap(),
+        );
+        let stats = v.stats();
+        assert_eq!(
+            Stats {
+                tables: 3,
+                entries: 8,
+                max_depth: 3,
+                strings: 3,
+                string_bytes: 5,
+                key_bytes: 12,
+                numbers: 2,
+                booleans: 1,
+                raw: 0,
+                heap_bytes: stats.heap_bytes,
+            },
+            stats
+        );
+        assert!(stats.heap_bytes > 8 * size_of::<(Option<Key>, Value)>());
+        assert_eq!(0, Value::from(1.).stats().max_depth);
+    }
+
+    /// The README's `--stats` example, which is for 64-bit targets.
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn readme() {
+        let v = Value::Object(parse(r#"{a = "xy", b = {1, 2}}"#).unwrap());
+        let line = format!(r#""raw":0,"heap_bytes":{}}}"#, v.stats().heap_bytes);
+        assert!(include_str!("../README.md").contains(&line), "{line}");
+    }
+}
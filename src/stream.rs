@@ -0,0 +1,1210 @@
+use std::borrow::Cow;
+use std::io::{BufWriter, Read, Write};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+pub use crate::json::{Floats, NonFinite, NonUtf8};
+use crate::parse::{special_name, string_value, unescape, Key, Names, ParseOptions, Table, Value};
+
+/// Options for [`transcode`].
+#[derive(Debug, Clone)]
+pub struct TranscodeOptions {
+    /// Accept any number of whitespace-separated tables, writing each on its own line,
+    /// instead of exactly one.
+    pub multiple: bool,
+    /// How many bytes to request from the reader at a time.
+    pub buffer_size: usize,
+    pub non_finite: NonFinite,
+    pub non_utf8: NonUtf8,
+    pub floats: Floats,
+    /// Write each entry of the root table, which must be an array, on its own line, as
+    /// NDJSON, instead of the whole table on one line. Its entries may also have integer
+    /// keys, like `[1] = {...}`, and are written in the order they appear.
+    pub ndjson: bool,
+    /// How many bytes of input [`transcode_resumable`] reads between checkpoints.
+    pub checkpoint_every: usize,
+}
+
+impl Default for TranscodeOptions {
+    fn default() -> Self {
+        TranscodeOptions {
+            multiple: false,
+            buffer_size: 64 * 1024,
+            non_finite: NonFinite::Null,
+            non_utf8: NonUtf8::Lossy,
+            floats: Floats::Shortest,
+            ndjson: false,
+            checkpoint_every: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// Convert Lua from `reader` to JSON on `writer` in a single pass, without building a `Value`.
+///
+/// The accepted syntax and the JSON produced match [`parse`](crate::parse::parse), with one
+/// exception: whether a table is an array is decided by its first entry, so a table which
+/// starts with positional entries and later has a named one is rejected.
+pub fn transcode(reader: impl Read, writer: impl Write, opts: &TranscodeOptions) -> Result<()> {
+    transcode_resumable(reader, writer, opts, None, |_| Ok(()))
+}
+
+/// Where [`transcode_resumable`] had got to, for carrying on from there after it's stopped,
+/// like when a batch job is preempted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// How much of the input had been read, in bytes.
+    pub input_offset: u64,
+    /// How much JSON had been written, in bytes, all of which had been flushed to the writer.
+    pub output_offset: u64,
+    line: usize,
+    line_start: usize,
+    state: State,
+    frames: Vec<Frame>,
+    records: bool,
+    multiple: bool,
+    ndjson: bool,
+}
+
+impl Checkpoint {
+    /// This checkpoint as JSON, for saving it.
+    pub fn to_json(&self) -> serde_json::Value {
+        let (state, key) = match &self.state {
+            State::Value => ("value", None),
+            State::FirstEntry => ("first_entry", None),
+            State::Entry => ("entry", None),
+            State::CloseOnly => ("close_only", None),
+            State::Name(name) => ("name", Some(name.as_str())),
+            State::BracketKey => ("bracket_key", None),
+            State::BracketClose(key) => ("bracket_close", Some(key.as_str())),
+            State::Equals(key) => ("equals", Some(key.as_str())),
+            State::AfterValue => ("after_value", None),
+            State::End => ("end", None),
+        };
+        let frames = self.frames.iter().map(|frame| match frame {
+            Frame::Pending => serde_json::json!("pending"),
+            Frame::Array => serde_json::json!("array"),
+            Frame::Object(entries) => serde_json::json!(entries),
+        });
+        serde_json::json!({
+            "input_offset": self.input_offset,
+            "output_offset": self.output_offset,
+            "line": self.line,
+            "line_start": self.line_start,
+            "state": state,
+            "key": key,
+            "integer_key": matches!(&self.state, State::BracketClose(k) | State::Equals(k) if k.is_integer()),
+            "frames": frames.collect::<Vec<_>>(),
+            "records": self.records,
+            "multiple": self.multiple,
+            "ndjson": self.ndjson,
+        })
+    }
+
+    /// Read a checkpoint saved with [`to_json`](Self::to_json).
+    pub fn from_json(json: &serde_json::Value) -> Result<Self> {
+        let invalid = || anyhow!("invalid checkpoint");
+        let field = |name: &str| json.get(name).ok_or_else(invalid);
+        let u64_field = |name: &str| field(name)?.as_u64().ok_or_else(invalid);
+        let usize_field = |name: &str| usize::try_from(u64_field(name)?).map_err(|_| invalid());
+        let bool_field = |name: &str| field(name)?.as_bool().ok_or_else(invalid);
+        let key = || {
+            json.get("key")
+                .and_then(|key| key.as_str())
+                .map(str::to_string)
+                .ok_or_else(invalid)
+        };
+        let entry_key = || {
+            let key = key()?;
+            Ok::<_, anyhow::Error>(match json.get("integer_key").and_then(|k| k.as_bool()) {
+                Some(true) => Key::Integer(key),
+                _ => Key::Name(key),
+            })
+        };
+        let state = match field("state")?.as_str().ok_or_else(invalid)? {
+            "value" => State::Value,
+            "first_entry" => State::FirstEntry,
+            "entry" => State::Entry,
+            "close_only" => State::CloseOnly,
+            "name" => State::Name(key()?),
+            "bracket_key" => State::BracketKey,
+            "bracket_close" => State::BracketClose(entry_key()?),
+            "equals" => State::Equals(entry_key()?),
+            "after_value" => State::AfterValue,
+            "end" => State::End,
+            _ => return Err(invalid()),
+        };
+        let frames = field("frames")?
+            .as_array()
+            .ok_or_else(invalid)?
+            .iter()
+            .map(|frame| match frame {
+                serde_json::Value::String(s) if "pending" == s => Ok(Frame::Pending),
+                serde_json::Value::String(s) if "array" == s => Ok(Frame::Array),
+                frame => frame
+                    .as_u64()
+                    .and_then(|entries| usize::try_from(entries).ok())
+                    .map(Frame::Object)
+                    .ok_or_else(invalid),
+            })
+            .collect::<Result<_>>()?;
+        Ok(Checkpoint {
+            input_offset: u64_field("input_offset")?,
+            output_offset: u64_field("output_offset")?,
+            line: usize_field("line")?,
+            line_start: usize_field("line_start")?,
+            state,
+            frames,
+            records: bool_field("records")?,
+            multiple: bool_field("multiple")?,
+            ndjson: bool_field("ndjson")?,
+        })
+    }
+}
+
+/// Like [`transcode`], calling `checkpoint` after about every
+/// [`TranscodeOptions::checkpoint_every`] bytes of input with where it's got to, and carrying
+/// on from `resume` if it's given.
+///
+/// To resume, `reader` has to start at the checkpoint's `input_offset` of the same input, and
+/// `writer` at its `output_offset` of the output, with anything written after that discarded,
+/// like by seeking a file to the offset and truncating it there. The options have to be the
+/// same as when the checkpoint was taken.
+pub fn transcode_resumable(
+    mut reader: impl Read,
+    writer: impl Write,
+    opts: &TranscodeOptions,
+    resume: Option<&Checkpoint>,
+    mut checkpoint: impl FnMut(&Checkpoint) -> Result<()>,
+) -> Result<()> {
+    let writer = Counted {
+        inner: writer,
+        written: resume.map_or(0, |c| c.output_offset),
+    };
+    let mut decoder = Decoder::new(JsonWriter::new(BufWriter::new(writer), opts), opts.multiple);
+    if let Some(resume) = resume {
+        decoder.restore(resume)?;
+    }
+    let mut next = decoder.lexer.offset() + opts.checkpoint_every.max(1) as u64;
+    let mut chunk = vec![0u8; opts.buffer_size.max(1)];
+    loop {
+        let n = reader.read(&mut chunk)?;
+        if 0 == n {
+            break;
+        }
+        decoder.feed(&chunk[..n])?;
+        if decoder.lexer.offset() >= next {
+            checkpoint(&decoder.checkpoint()?)?;
+            next = decoder.lexer.offset() + opts.checkpoint_every.max(1) as u64;
+        }
+    }
+    decoder.finish()?.out.flush()?;
+    Ok(())
+}
+
+/// A writer which counts what's written to it, for [`Checkpoint::output_offset`].
+struct Counted<W> {
+    inner: W,
+    written: u64,
+}
+
+impl<W: Write> Write for Counted<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Convert an array of Lua tables from `reader` to NDJSON on `writer`, one entry per line,
+/// like [`transcode`] with [`TranscodeOptions::ndjson`].
+pub fn to_ndjson(reader: impl Read, writer: impl Write) -> Result<()> {
+    let opts = TranscodeOptions {
+        ndjson: true,
+        ..TranscodeOptions::default()
+    };
+    transcode(reader, writer, &opts)
+}
+
+/// Parse a table from `reader`, like [`parse`](crate::parse::parse), without first reading
+/// the whole input into memory.
+pub fn parse_reader(reader: impl Read) -> Result<Table> {
+    parse_reader_with(reader, &ParseOptions::default())
+}
+
+/// Parse a table from `reader`, like [`parse_reader`], with options. Only
+/// [`ParseOptions::lowercase_keys`] and [`ParseOptions::intern_keys`] are supported, and the
+/// others are errors.
+pub fn parse_reader_with(reader: impl Read, opts: &ParseOptions) -> Result<Table> {
+    let mut decoder = Decoder::new(TableBuilder::new(opts)?, false);
+    read_all(
+        reader,
+        &mut decoder,
+        TranscodeOptions::default().buffer_size,
+    )?;
+    decoder.finish()?.into_table()
+}
+
+/// Parse a table from an async `reader`, like [`parse_reader`].
+#[cfg(feature = "async")]
+pub async fn parse_async(reader: impl tokio::io::AsyncRead + Unpin) -> Result<Table> {
+    use tokio::io::AsyncReadExt;
+
+    let mut reader = reader;
+    let mut decoder = Decoder::new(TableBuilder::new(&ParseOptions::default())?, false);
+    let mut chunk = vec![0u8; TranscodeOptions::default().buffer_size];
+    loop {
+        let n = reader.read(&mut chunk).await?;
+        if 0 == n {
+            return decoder.finish()?.into_table();
+        }
+        decoder.feed(&chunk[..n])?;
+    }
+}
+
+fn read_all(
+    mut reader: impl Read,
+    decoder: &mut Decoder<impl Handler>,
+    buffer_size: usize,
+) -> Result<()> {
+    let mut chunk = vec![0u8; buffer_size.max(1)];
+    loop {
+        let n = reader.read(&mut chunk)?;
+        if 0 == n {
+            return Ok(());
+        }
+        decoder.feed(&chunk[..n])?;
+    }
+}
+
+/// Accepts input in arbitrary chunks, passing the structure found to a [`Handler`].
+struct Decoder<H> {
+    lexer: Lexer,
+    parser: Parser,
+    handler: H,
+}
+
+impl<H: Handler> Decoder<H> {
+    fn new(handler: H, multiple: bool) -> Self {
+        Decoder {
+            lexer: Lexer::default(),
+            parser: Parser::new(multiple),
+            handler,
+        }
+    }
+
+    fn feed(&mut self, data: &[u8]) -> Result<()> {
+        self.lexer.feed(data);
+        while let Some(token) = self.lexer.next().with_context(|| self.lexer.position())? {
+            self.parser
+                .token(token, &mut self.handler)
+                .with_context(|| self.lexer.position())?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<H> {
+        self.lexer.eof = true;
+        self.feed(&[])?;
+        self.parser
+            .finish()
+            .with_context(|| self.lexer.position())?;
+        Ok(self.handler)
+    }
+}
+
+impl<W: Write> Decoder<JsonWriter<BufWriter<Counted<W>>>> {
+    /// Where the transcoding has got to, once everything written so far is flushed.
+    fn checkpoint(&mut self) -> Result<Checkpoint> {
+        self.handler.out.flush()?;
+        Ok(Checkpoint {
+            input_offset: self.lexer.offset(),
+            output_offset: self.handler.out.get_ref().written,
+            line: self.lexer.line,
+            line_start: self.lexer.line_start,
+            state: self.parser.state.clone(),
+            frames: self.handler.frames.clone(),
+            records: self.handler.records,
+            multiple: self.parser.multiple,
+            ndjson: self.handler.ndjson,
+        })
+    }
+
+    fn restore(&mut self, checkpoint: &Checkpoint) -> Result<()> {
+        if (checkpoint.multiple, checkpoint.ndjson) != (self.parser.multiple, self.handler.ndjson) {
+            bail!("the checkpoint was taken with different options");
+        }
+        let consumed = usize::try_from(checkpoint.input_offset)?;
+        self.lexer = Lexer {
+            consumed,
+            line: checkpoint.line,
+            line_start: checkpoint.line_start,
+            token_start: (checkpoint.line, consumed - checkpoint.line_start),
+            ..Lexer::default()
+        };
+        self.parser.state = checkpoint.state.clone();
+        self.parser.depth = checkpoint.frames.len();
+        self.handler.frames = checkpoint.frames.clone();
+        self.handler.records = checkpoint.records;
+        Ok(())
+    }
+}
+
+/// Receives the structure of the document as the parser discovers it.
+trait Handler {
+    fn start_table(&mut self) -> Result<()>;
+    fn entry(&mut self, key: Option<Key>) -> Result<()>;
+    fn scalar(&mut self, value: Value) -> Result<()>;
+    fn end_table(&mut self) -> Result<()>;
+    fn end_document(&mut self) -> Result<()>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    OpenBracket,
+    CloseBracket,
+    Comma,
+    Equals,
+    /// A string's contents, which may not be valid UTF-8.
+    Str(Vec<u8>),
+    Num(f64),
+    /// A number which is only digits, like `1`, which may be an integer key like `[1]`.
+    Digits(String),
+    Name(String),
+}
+
+/// Splits buffered input into tokens, reporting `None` when it needs more input to continue.
+#[derive(Default)]
+struct Lexer {
+    buf: Vec<u8>,
+    pos: usize,
+    /// Bytes dropped from the front of `buf`.
+    consumed: usize,
+    line: usize,
+    line_start: usize,
+    /// Where the most recent token started, as (line, column).
+    token_start: (usize, usize),
+    eof: bool,
+}
+
+impl Lexer {
+    fn feed(&mut self, data: &[u8]) {
+        self.buf.drain(..self.pos);
+        self.consumed += self.pos;
+        self.pos = 0;
+        self.buf.extend_from_slice(data);
+    }
+
+    /// How much of the input has been split into tokens, in bytes.
+    fn offset(&self) -> u64 {
+        (self.consumed + self.pos) as u64
+    }
+
+    fn position(&self) -> String {
+        let (line, column) = self.token_start;
+        format!("at line {}, column {}", line + 1, column + 1)
+    }
+
+    fn next(&mut self) -> Result<Option<Token>> {
+        while let Some(&c) = self.buf.get(self.pos) {
+            if !matches!(c, b' ' | b'\t' | b'\r' | b'\n') {
+                break;
+            }
+            self.pos += 1;
+            if b'\n' == c {
+                self.line += 1;
+                self.line_start = self.consumed + self.pos;
+            }
+        }
+        let c = match self.buf.get(self.pos) {
+            Some(&c) => c,
+            None => return Ok(None),
+        };
+        self.token_start = (self.line, self.consumed + self.pos - self.line_start);
+
+        let simple = match c {
+            b'{' => Some(Token::Open),
+            b'}' => Some(Token::Close),
+            b'[' => Some(Token::OpenBracket),
+            b']' => Some(Token::CloseBracket),
+            b',' => Some(Token::Comma),
+            b'=' => Some(Token::Equals),
+            _ => None,
+        };
+        if let Some(token) = simple {
+            self.pos += 1;
+            return Ok(Some(token));
+        }
+
+        let found = match c {
+            b'"' | b'\'' => self.string()?,
+            b'-' | b'0'..=b'9' => self.number()?,
+            b'a'..=b'z' | b'A'..=b'Z' | b'_' => self.name(),
+            _ => bail!(
+                "unexpected character {:?}",
+                String::from_utf8_lossy(&self.buf[self.pos..])
+                    .chars()
+                    .next()
+                    .unwrap_or_default()
+            ),
+        };
+        Ok(found.map(|(len, token)| {
+            self.pos += len;
+            token
+        }))
+    }
+
+    /// The input from the start of the current token.
+    fn rest(&self) -> &[u8] {
+        &self.buf[self.pos..]
+    }
+
+    fn string(&self) -> Result<Option<(usize, Token)>> {
+        let rest = self.rest();
+        let mut i = 1;
+        while let Some(&c) = rest.get(i) {
+            match c {
+                c if c == rest[0] => {
+                    let s = unescape(&rest[1..i])
+                        .map_err(|e| e.offset(self.consumed + self.pos + 1))?;
+                    return Ok(Some((i + 1, Token::Str(s))));
+                }
+                b'\\' if i + 1 < rest.len() => i += 2,
+                b'\\' => break,
+                b'\n' => bail!("newline in string"),
+                _ => i += 1,
+            }
+        }
+        if self.eof {
+            bail!("unterminated string");
+        }
+        Ok(None)
+    }
+
+    fn number(&self) -> Result<Option<(usize, Token)>> {
+        let rest = self.rest();
+        let digits_from = |from: usize| {
+            from + rest[from.min(rest.len())..]
+                .iter()
+                .take_while(|c| c.is_ascii_digit())
+                .count()
+        };
+        let start = usize::from(b'-' == rest[0]);
+        let mut end = digits_from(start);
+        if end == rest.len() && !self.eof {
+            return Ok(None);
+        }
+        if end == start {
+            let special = match self.identifier(start) {
+                None => return Ok(None),
+                Some((len, name)) => special_name(name).map(|f| (len, Token::Num(-f))),
+            };
+            return special
+                .map(Some)
+                .ok_or_else(|| anyhow!("expected digits in number"));
+        }
+        if Some(&b'/') == rest.get(end) {
+            let denominator = digits_from(end + 1);
+            if denominator == rest.len() && !self.eof {
+                return Ok(None);
+            }
+            let text = String::from_utf8_lossy(&rest[start..denominator]);
+            let f = special_name(&text).ok_or_else(|| anyhow!("unsupported division: {text:?}"))?;
+            let f = if 1 == start { -f } else { f };
+            return Ok(Some((denominator, Token::Num(f))));
+        }
+        if Some(&b'.') == rest.get(end) {
+            let fraction = digits_from(end + 1);
+            if fraction == rest.len() && !self.eof {
+                return Ok(None);
+            }
+            if fraction > end + 1 {
+                end = fraction;
+            }
+        }
+        if let Some(b'e' | b'E') = rest.get(end) {
+            let sign = usize::from(matches!(rest.get(end + 1), Some(b'-' | b'+')));
+            let exponent = digits_from(end + 1 + sign);
+            if exponent >= rest.len() && !self.eof {
+                return Ok(None);
+            }
+            if exponent > end + 1 + sign {
+                end = exponent;
+            }
+        }
+        let text = std::str::from_utf8(&rest[..end]).expect("ascii");
+        let v = text
+            .parse::<f64>()
+            .with_context(|| anyhow!("invalid number: {text:?}"))?;
+        if !v.is_finite() {
+            bail!("number out of range: {text:?}");
+        }
+        if text.bytes().all(|c| c.is_ascii_digit()) {
+            return Ok(Some((end, Token::Digits(text.to_string()))));
+        }
+        Ok(Some((end, Token::Num(v))))
+    }
+
+    fn name(&self) -> Option<(usize, Token)> {
+        self.identifier(0)
+            .map(|(len, name)| (len, Token::Name(name.to_string())))
+    }
+
+    /// The identifier starting at `from`, or `math.huge`, with the length up to its end.
+    fn identifier(&self, from: usize) -> Option<(usize, &str)> {
+        let rest = self.rest();
+        let end = |from: usize| {
+            from + rest[from.min(rest.len())..]
+                .iter()
+                .take_while(|c| c.is_ascii_alphanumeric() || b'_' == **c)
+                .count()
+        };
+        let mut len = end(from);
+        if len == rest.len() && !self.eof {
+            return None;
+        }
+        if b"math" == &rest[from..len] && Some(&b'.') == rest.get(len) {
+            let huge = end(len + 1);
+            if huge == rest.len() && !self.eof {
+                return None;
+            }
+            if b"huge" == &rest[len + 1..huge] {
+                len = huge;
+            }
+        }
+        Some((len, std::str::from_utf8(&rest[from..len]).expect("ascii")))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum State {
+    /// Expecting a value; at the root, this must be a table.
+    Value,
+    /// Just inside a `{`.
+    FirstEntry,
+    /// After a `,` inside a table.
+    Entry,
+    /// After `{,`, where only `}` may follow.
+    CloseOnly,
+    /// An identifier at the start of an entry, which is a key if `=` follows.
+    Name(String),
+    /// After the `[` of a `["key"]` or `[1]`.
+    BracketKey,
+    /// After the string or integer of a `["key"]` or `[1]`.
+    BracketClose(Key),
+    /// After a key, before its `=`.
+    Equals(Key),
+    /// After a value inside a table.
+    AfterValue,
+    /// After the root value.
+    End,
+}
+
+struct Parser {
+    state: State,
+    depth: usize,
+    multiple: bool,
+}
+
+impl Parser {
+    fn new(multiple: bool) -> Self {
+        Parser {
+            state: State::Value,
+            depth: 0,
+            multiple,
+        }
+    }
+
+    fn token(&mut self, token: Token, handler: &mut impl Handler) -> Result<()> {
+        let state = std::mem::replace(&mut self.state, State::End);
+        self.state = match (state, token) {
+            (State::Value, token) => self.value(token, handler)?,
+            (State::FirstEntry, Token::Comma) => State::CloseOnly,
+            (State::FirstEntry | State::Entry | State::CloseOnly, Token::Close) => {
+                self.close(handler)?
+            }
+            (State::FirstEntry | State::Entry, Token::Name(name)) => State::Name(name),
+            (State::FirstEntry | State::Entry, Token::OpenBracket) => State::BracketKey,
+            (State::FirstEntry | State::Entry, token) => {
+                handler.entry(None)?;
+                self.value(token, handler)?
+            }
+            (State::Name(name), Token::Equals) if !name.contains('.') => {
+                handler.entry(Some(Key::Name(name)))?;
+                State::Value
+            }
+            (State::Name(name), token) => {
+                handler.entry(None)?;
+                self.state = self.value(Token::Name(name), handler)?;
+                return self.token(token, handler);
+            }
+            (State::BracketKey, Token::Str(key)) => State::BracketClose(Key::Name(
+                String::from_utf8(key).map_err(|_| anyhow!("key is not valid UTF-8"))?,
+            )),
+            (State::BracketKey, Token::Digits(digits)) => State::BracketClose(Key::Integer(digits)),
+            (State::BracketClose(key), Token::CloseBracket) => State::Equals(key),
+            (State::Equals(key), Token::Equals) => {
+                handler.entry(Some(key))?;
+                State::Value
+            }
+            (State::AfterValue, Token::Comma) => State::Entry,
+            (State::AfterValue, Token::Close) => self.close(handler)?,
+            (State::End, token) if self.multiple => self.value(token, handler)?,
+            (State::End, token) => bail!("unexpected trailing data: {token:?}"),
+            (_, token) => bail!("unexpected {token:?}"),
+        };
+        Ok(())
+    }
+
+    fn value(&mut self, token: Token, handler: &mut impl Handler) -> Result<State> {
+        let scalar = match token {
+            Token::Open => {
+                self.depth += 1;
+                handler.start_table()?;
+                return Ok(State::FirstEntry);
+            }
+            _ if 0 == self.depth => bail!("unexpected non-object"),
+            Token::Str(s) => string_value(s),
+            Token::Num(f) => Value::Float(f),
+            Token::Digits(digits) => Value::Float(digits.parse()?),
+            Token::Name(name) if "true" == name => Value::Bool(true),
+            Token::Name(name) if "false" == name => Value::Bool(false),
+            Token::Name(name) => match special_name(&name) {
+                Some(f) => Value::Float(f),
+                None => bail!("expected value, found {:?}", Token::Name(name)),
+            },
+            token => bail!("expected value, found {token:?}"),
+        };
+        handler.scalar(scalar)?;
+        Ok(State::AfterValue)
+    }
+
+    fn close(&mut self, handler: &mut impl Handler) -> Result<State> {
+        handler.end_table()?;
+        self.depth -= 1;
+        Ok(if 0 == self.depth {
+            handler.end_document()?;
+            State::End
+        } else {
+            State::AfterValue
+        })
+    }
+
+    fn finish(&self) -> Result<()> {
+        match self.state {
+            State::End => Ok(()),
+            State::Value if self.multiple => Ok(()),
+            _ => bail!("unexpected end of input"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Frame {
+    /// No entries yet, so it's not known whether this is an array or an object.
+    Pending,
+    Array,
+    Object(usize),
+}
+
+struct JsonWriter<W> {
+    out: W,
+    frames: Vec<Frame>,
+    non_finite: NonFinite,
+    non_utf8: NonUtf8,
+    floats: Floats,
+    ndjson: bool,
+    /// Whether the last document had any entries, and so needs a final newline, when writing
+    /// NDJSON.
+    records: bool,
+}
+
+impl<W: Write> JsonWriter<W> {
+    fn new(out: W, opts: &TranscodeOptions) -> Self {
+        JsonWriter {
+            out,
+            frames: Vec::new(),
+            non_finite: opts.non_finite,
+            non_utf8: opts.non_utf8,
+            floats: opts.floats,
+            ndjson: opts.ndjson,
+            records: false,
+        }
+    }
+}
+
+impl<W: Write> Handler for JsonWriter<W> {
+    fn start_table(&mut self) -> Result<()> {
+        self.frames.push(Frame::Pending);
+        Ok(())
+    }
+
+    fn entry(&mut self, key: Option<Key>) -> Result<()> {
+        if self.ndjson && 1 == self.frames.len() {
+            // a record for each, in the order they're written, as for `{"a", "b"}`
+            if let Some(key) = key.filter(|key| !key.is_integer()) {
+                bail!("NDJSON needs an array at the root, but found the key {key:?}");
+            }
+            let frame = &mut self.frames[0];
+            if let Frame::Array = frame {
+                self.out.write_all(b"\n")?;
+            }
+            *frame = Frame::Array;
+            return Ok(());
+        }
+        let key = key.map(Key::into_string);
+        let frame = self.frames.last_mut().expect("entry outside table");
+        let (i, key) = match (&frame, key) {
+            (Frame::Pending, None) => {
+                *frame = Frame::Array;
+                self.out.write_all(b"[")?;
+                return Ok(());
+            }
+            (Frame::Array, None) => {
+                self.out.write_all(b",")?;
+                return Ok(());
+            }
+            (Frame::Array, Some(key)) => {
+                bail!("named entry {key:?} after positional entries can't be streamed")
+            }
+            (Frame::Pending, Some(key)) => {
+                self.out.write_all(b"{")?;
+                (0, key)
+            }
+            (Frame::Object(i), key) => {
+                self.out.write_all(b",")?;
+                (*i, key.unwrap_or_else(|| i.to_string()))
+            }
+        };
+        *frame = Frame::Object(i + 1);
+        serde_json::to_writer(&mut self.out, &key)?;
+        self.out.write_all(b":")?;
+        Ok(())
+    }
+
+    fn scalar(&mut self, value: Value) -> Result<()> {
+        match value {
+            Value::Float(f) if f.is_finite() => {
+                serde_json::to_writer(&mut self.out, &self.floats.to_json(f))?
+            }
+            Value::Float(f) | Value::Number { value: f, .. } => {
+                serde_json::to_writer(&mut self.out, &self.non_finite.to_json(f)?)?
+            }
+            Value::String(s) | Value::Raw(s) => serde_json::to_writer(&mut self.out, &s)?,
+            Value::Bytes(b) => serde_json::to_writer(&mut self.out, &self.non_utf8.to_json(&b)?)?,
+            Value::Bool(b) => serde_json::to_writer(&mut self.out, &b)?,
+            Value::Object(_) => unreachable!("tables are streamed"),
+        }
+        Ok(())
+    }
+
+    fn end_table(&mut self) -> Result<()> {
+        let frame = self.frames.pop().expect("unbalanced table");
+        if self.ndjson && self.frames.is_empty() {
+            self.records = matches!(frame, Frame::Array);
+            return Ok(());
+        }
+        let close: &[u8] = match frame {
+            Frame::Pending => b"[]",
+            Frame::Array => b"]",
+            Frame::Object(_) => b"}",
+        };
+        self.out.write_all(close)?;
+        Ok(())
+    }
+
+    fn end_document(&mut self) -> Result<()> {
+        if !self.ndjson || self.records {
+            self.out.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects the document back into a [`Table`].
+struct TableBuilder {
+    opts: ParseOptions,
+    names: Names,
+    /// Tables which are still open, with the key they will be stored under.
+    stack: Vec<(Option<Key>, Table)>,
+    key: Option<Key>,
+    done: Option<Table>,
+}
+
+impl TableBuilder {
+    fn new(opts: &ParseOptions) -> Result<Self> {
+        let limits = &opts.limits;
+        if opts.raw_numbers
+            || opts.arithmetic
+            || opts.resolve.is_some()
+            || opts.lenient
+            || opts.unicode_names
+            || !opts.only.is_empty()
+            || limits.max_input.is_some()
+            || limits.max_string.is_some()
+            || limits.max_entries.is_some()
+            || limits.max_depth.is_some()
+        {
+            bail!("only lowercase_keys and intern_keys can be used when streaming");
+        }
+        Ok(TableBuilder {
+            opts: opts.clone(),
+            names: Names::default(),
+            stack: Vec::new(),
+            key: None,
+            done: None,
+        })
+    }
+
+    fn into_table(self) -> Result<Table> {
+        self.done.ok_or_else(|| anyhow!("no table found"))
+    }
+}
+
+impl Handler for TableBuilder {
+    fn start_table(&mut self) -> Result<()> {
+        self.stack.push((self.key.take(), Table::new()));
+        Ok(())
+    }
+
+    fn entry(&mut self, key: Option<Key>) -> Result<()> {
+        self.key = key.map(|key| match key {
+            Key::Name(name) => self.names.key(&self.opts, Cow::Owned(name)),
+            key => key,
+        });
+        Ok(())
+    }
+
+    fn scalar(&mut self, value: Value) -> Result<()> {
+        let (_, table) = self.stack.last_mut().expect("scalar outside table");
+        table.push((self.key.take(), value));
+        Ok(())
+    }
+
+    fn end_table(&mut self) -> Result<()> {
+        let (key, table) = self.stack.pop().expect("unbalanced table");
+        match self.stack.last_mut() {
+            Some((_, parent)) => parent.push((key, Value::Object(table))),
+            None => self.done = Some(table),
+        }
+        Ok(())
+    }
+
+    fn end_document(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json::{to_json_string, JsonOptions};
+    use std::sync::Arc;
+
+    use crate::parse::{parse, parse_with, Key, ParseOptions, Value};
+    use crate::stream::{
+        parse_reader, parse_reader_with, to_ndjson, transcode, transcode_resumable, Checkpoint,
+        NonFinite, NonUtf8, TranscodeOptions,
+    };
+    use anyhow::Result;
+
+    fn transcode_with(input: &str, opts: &TranscodeOptions) -> Result<String> {
+        let mut out = Vec::new();
+        transcode(input.as_bytes(), &mut out, opts)?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    fn json(input: &str) -> String {
+        let whole = transcode_with(input, &TranscodeOptions::default()).unwrap();
+        let tiny = TranscodeOptions {
+            buffer_size: 1,
+            ..TranscodeOptions::default()
+        };
+        assert_eq!(whole, transcode_with(input, &tiny).unwrap());
+        whole
+    }
+
+    #[test]
+    fn simple() {
+        assert_eq!("[]\n", json("{}"));
+        assert_eq!("{\"a\":5.0}\n", json("{a=5}"));
+        assert_eq!("{\"a\":5.5,\"b\":-6.0}\n", json("{a=5.5, b = -6 ,}"));
+        assert_eq!("[\"x\",true,false]\n", json(r#"{"x",true , false}"#));
+        assert_eq!(
+            "{\"foo\":\"bar\",\"baz\":{\"qux\":\"qu\\\"ux\"}}\n",
+            json(r#"{foo = "bar", baz = { ["qux"] = "qu\"ux" }}"#)
+        );
+        assert_eq!("[[{\"a\":5.0}],[]]\n", json("{{{a=5}},{}}"));
+        assert_eq!("{\"a\":1.0,\"1\":2.0}\n", json("{a=1,2}"));
+        assert_eq!("{\"true\":1.0}\n", json("{true=1}"));
+        assert_eq!("{\"a1\":1.0}\n", json("{a1=1}"));
+    }
+
+    #[test]
+    fn special_floats() {
+        assert_eq!(
+            "[null,null,null,null,null]\n",
+            json("{math.huge, -math.huge, -1/0, 0/0, -nan}")
+        );
+        assert_eq!("{\"inf\":null}\n", json("{inf = inf}"));
+        let opts = TranscodeOptions {
+            non_finite: NonFinite::String,
+            ..TranscodeOptions::default()
+        };
+        assert_eq!(
+            "[\"Infinity\",\"-Infinity\",\"NaN\",1.5]\n",
+            transcode_with("{1/0, -math.huge, 0/0, 1.5}", &opts).unwrap()
+        );
+        let opts = TranscodeOptions {
+            non_finite: NonFinite::Error,
+            ..TranscodeOptions::default()
+        };
+        assert!(transcode_with("{1/0}", &opts).is_err());
+        assert!(transcode_with("{2/0}", &opts).is_err());
+        assert!(transcode_with("{math.tiny}", &opts).is_err());
+        assert!(transcode_with("{math.huge = 1}", &opts).is_err());
+        assert!(transcode_with("{-x}", &opts).is_err());
+    }
+
+    #[test]
+    fn byte_strings() {
+        assert_eq!(
+            "[\"a\\n\\t\\\"'\",\"é\",\"\\u0007\",\"\"]\n",
+            json(r#"{"a\n\t\"\'", "\u{e9}", "\7", ""}"#)
+        );
+        assert_eq!("[\"a\u{fffd}\"]\n", json(r#"{"a\xff"}"#));
+        let opts = TranscodeOptions {
+            non_utf8: NonUtf8::Base64,
+            ..TranscodeOptions::default()
+        };
+        assert_eq!(
+            "[\"/w==\",\"ok\"]\n",
+            transcode_with(r#"{"\255", "ok"}"#, &opts).unwrap()
+        );
+        let mut out = Vec::new();
+        transcode(&b"{\"\xe9t\xe9\"}"[..], &mut out, &opts).unwrap();
+        assert_eq!(b"[\"6XTp\"]\n", &out[..]);
+        let opts = TranscodeOptions {
+            non_utf8: NonUtf8::Error,
+            ..TranscodeOptions::default()
+        };
+        assert!(transcode_with(r#"{"\xff"}"#, &opts).is_err());
+        assert!(transcode_with(r#"{["\xff"] = 1}"#, &opts).is_err());
+        assert!(transcode_with(r#"{"\256"}"#, &opts).is_err());
+    }
+
+    #[test]
+    fn ndjson() {
+        let lines = |input: &str| {
+            let mut out = Vec::new();
+            to_ndjson(input.as_bytes(), &mut out).map(|()| String::from_utf8(out).unwrap())
+        };
+        assert_eq!(
+            "{\"a\":1.0}\n[2.0,{\"b\":[]}]\n\"c\"\n",
+            lines("{\n  {a = 1},\n  {2, {b = {}}},\n  \"c\",\n}").unwrap()
+        );
+        assert_eq!("", lines("{}").unwrap());
+        assert!(lines("{{}, a = {}}").is_err());
+        assert!(lines("{a = {}}").is_err());
+        assert_eq!("{\"1\":\"a\"}\n", lines("{{[1] = \"a\"}}").unwrap());
+        assert_eq!(
+            "\"a\"\n\"b\"\n",
+            lines("{[1] = \"a\", [2] = \"b\"}").unwrap()
+        );
+
+        let opts = TranscodeOptions {
+            ndjson: true,
+            multiple: true,
+            buffer_size: 1,
+            ..TranscodeOptions::default()
+        };
+        assert_eq!(
+            "1.0\n2.0\n3.0\n",
+            transcode_with("{1, 2} {} {3}", &opts).unwrap()
+        );
+    }
+
+    #[test]
+    fn multiple() {
+        let opts = TranscodeOptions {
+            multiple: true,
+            ..TranscodeOptions::default()
+        };
+        assert_eq!(
+            "{\"a\":1.0}\n[2.0]\n",
+            transcode_with("{a=1}\n{\n2\n}\n", &opts).unwrap()
+        );
+        assert_eq!("", transcode_with("  ", &opts).unwrap());
+    }
+
+    #[test]
+    fn errors() {
+        let opts = TranscodeOptions::default();
+        assert!(transcode_with("", &opts).is_err());
+        assert!(transcode_with("5", &opts).is_err());
+        assert!(transcode_with("{a=}", &opts).is_err());
+        assert!(transcode_with("{a=1", &opts).is_err());
+        assert!(transcode_with("{} {}", &opts).is_err());
+        assert!(transcode_with("{1, a=2}", &opts).is_err());
+        let err = transcode_with("{\n  a = \"b\\q\"\n}", &opts).unwrap_err();
+        assert!(format!("{err:#}").contains("line 2, column 7"), "{err:#}");
+    }
+
+    #[test]
+    fn resume() {
+        let input =
+            "{\n  {name = \"a\", [\"max hp\"] = 10, [12] = 1},\n  {tags = {1, 2.5}, on = true},\n} {{}}";
+        for opts in [
+            TranscodeOptions {
+                multiple: true,
+                ..TranscodeOptions::default()
+            },
+            TranscodeOptions {
+                multiple: true,
+                ndjson: true,
+                ..TranscodeOptions::default()
+            },
+        ] {
+            let opts = TranscodeOptions {
+                buffer_size: 1,
+                checkpoint_every: 1,
+                ..opts
+            };
+            let mut whole = Vec::new();
+            let mut checkpoints = Vec::new();
+            transcode_resumable(input.as_bytes(), &mut whole, &opts, None, |c| {
+                checkpoints.push(c.clone());
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(transcode_with(input, &opts).unwrap().as_bytes(), whole);
+            assert!(checkpoints.len() > 20);
+            for checkpoint in checkpoints {
+                let checkpoint = Checkpoint::from_json(&checkpoint.to_json()).unwrap();
+                let mut out = whole[..checkpoint.output_offset as usize].to_vec();
+                let rest = &input.as_bytes()[checkpoint.input_offset as usize..];
+                transcode_resumable(rest, &mut out, &opts, Some(&checkpoint), |_| Ok(())).unwrap();
+                assert_eq!(whole, out, "{checkpoint:?}");
+            }
+        }
+
+        let mut checkpoint = None;
+        let opts = TranscodeOptions {
+            buffer_size: 4,
+            checkpoint_every: 4,
+            ..TranscodeOptions::default()
+        };
+        let input = "{a = 1,\n b = }";
+        transcode_resumable(&input.as_bytes()[..12], Vec::new(), &opts, None, |c| {
+            checkpoint = Some(c.clone());
+            Ok(())
+        })
+        .unwrap_err();
+        let checkpoint = checkpoint.unwrap();
+        // errors after resuming are still reported where they are in the whole input
+        let rest = &input.as_bytes()[checkpoint.input_offset as usize..];
+        let err = transcode_resumable(rest, Vec::new(), &opts, Some(&checkpoint), |_| Ok(()))
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("line 2, column 6"), "{err:#}");
+        let ndjson = TranscodeOptions {
+            ndjson: true,
+            ..opts
+        };
+        assert!(transcode_resumable(
+            "".as_bytes(),
+            Vec::new(),
+            &ndjson,
+            Some(&checkpoint),
+            |_| Ok(())
+        )
+        .is_err());
+        assert!(Checkpoint::from_json(&serde_json::json!({"input_offset": 1})).is_err());
+    }
+
+    #[test]
+    fn reader() {
+        for input in [
+            "{}",
+            "{a=1,2,{b=\"c\"}}",
+            "{1, a=2, {}}",
+            "{['a'] = 'b\\'c'}",
+            "{[1] = \"a\", [2] = \"b\", [\"3\"] = {[01] = true}}",
+        ] {
+            assert_eq!(
+                parse(input).unwrap(),
+                parse_reader(input.as_bytes()).unwrap()
+            );
+        }
+        assert!(parse_reader("{".as_bytes()).is_err());
+
+        let opts = ParseOptions {
+            intern_keys: true,
+            lowercase_keys: true,
+            ..ParseOptions::default()
+        };
+        let input = "{{Name = 1, [2] = 2}, {name = 3}}";
+        let table = parse_reader_with(input.as_bytes(), &opts).unwrap();
+        assert_eq!(parse_with(input, &opts).unwrap(), table);
+        let name = |i: usize| match &table[i].1 {
+            Value::Object(t) => match &t[0].0 {
+                Some(Key::Shared(k)) => k.clone(),
+                k => panic!("{k:?}"),
+            },
+            v => panic!("{v:?}"),
+        };
+        assert!(Arc::ptr_eq(&name(0), &name(1)));
+        let opts = ParseOptions {
+            lenient: true,
+            ..ParseOptions::default()
+        };
+        assert!(parse_reader_with(input.as_bytes(), &opts).is_err());
+    }
+
+    #[test]
+    fn integer_keys() {
+        let input = r#"{[1] = "a", [2] = {[10] = true, x = 5}, ["3"] = "c", [04] = 4}"#;
+        let value = Value::Object(parse(input).unwrap());
+        let whole = to_json_string(&value, &JsonOptions::default()).unwrap();
+        assert_eq!(whole + "\n", json(input));
+        assert_eq!(
+            "{\"1\":\"a\",\"2\":{\"10\":true,\"x\":5.0},\"3\":\"c\",\"04\":4.0}\n",
+            json(input)
+        );
+    }
+
+    #[test]
+    fn exponents() {
+        let input = "{1e3, 2.5E-2, -1e+2, 7}";
+        assert_eq!(
+            parse(input).unwrap(),
+            parse_reader(input.as_bytes()).unwrap()
+        );
+        assert_eq!("[1000.0,0.025,-100.0,7.0]\n", json(input));
+        for input in ["{1e999}", "{1e}", "{1e+}"] {
+            assert_eq!(
+                parse(input).is_ok(),
+                parse_reader(input.as_bytes()).is_ok(),
+                "{input}"
+            );
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_reader() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let input = "{1, a=2, {b=true}}";
+        assert_eq!(
+            parse(input).unwrap(),
+            runtime
+                .block_on(crate::stream::parse_async(input.as_bytes()))
+                .unwrap()
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use anyhow::{bail, Result};
+
+use crate::parse::{Table, Value};
+use crate::path::{push_index, push_key};
+
+/// Render `value` as a TOML document, which it must be a table with named entries for.
+///
+/// Tables with only positional entries become arrays, like they do in JSON, and whole numbers
+/// become integers. Tables with both positional and named entries, arrays with elements of
+/// different types, and strings which aren't valid UTF-8 can't be represented, so are errors.
+pub fn to_toml(value: &Value) -> Result<String> {
+    let table = match value {
+        Value::Object(table) if table.is_empty() => return Ok(String::new()),
+        Value::Object(table) if table.iter().all(|(k, _)| k.is_some()) => table,
+        other => bail!(
+            "a TOML document must be a table with named entries, not {}",
+            describe(other)
+        ),
+    };
+    let mut path = String::new();
+    let document = ::toml::Value::Table(convert_table(table, &mut path)?);
+    Ok(::toml::to_string(&document)?)
+}
+
+fn describe(value: &Value) -> &'static str {
+    match value {
+        Value::Object(table) if table.iter().all(|(k, _)| k.is_none()) => "an array",
+        Value::Object(_) => "a table with positional entries",
+        other => other.type_name(),
+    }
+}
+
+fn at(path: &str) -> &str {
+    if path.is_empty() {
+        "(root)"
+    } else {
+        path
+    }
+}
+
+fn convert(value: &Value, path: &mut String) -> Result<::toml::Value> {
+    Ok(match value {
+        Value::Bool(b) => ::toml::Value::Boolean(*b),
+        Value::String(s) | Value::Raw(s) => ::toml::Value::String(s.clone()),
+        Value::Bytes(_) => bail!("{}: strings must be valid UTF-8 in TOML", at(path)),
+        Value::Number { raw, .. } if raw.parse::<i64>().is_ok() => {
+            ::toml::Value::Integer(raw.parse().expect("checked"))
+        }
+        Value::Float(f) | Value::Number { value: f, .. } => number(*f),
+        Value::Object(table) if table.iter().all(|(k, _)| k.is_none()) => {
+            convert_array(table, path)?
+        }
+        Value::Object(table) if table.iter().all(|(k, _)| k.is_some()) => {
+            ::toml::Value::Table(convert_table(table, path)?)
+        }
+        Value::Object(_) => bail!(
+            "{}: tables with both positional and named entries can't be represented in TOML",
+            at(path)
+        ),
+    })
+}
+
+fn number(f: f64) -> ::toml::Value {
+    if 0. == f.fract() && f.abs() < 2f64.powi(63) {
+        ::toml::Value::Integer(f as i64)
+    } else {
+        ::toml::Value::Float(f)
+    }
+}
+
+fn convert_table(
+    table: &Table,
+    path: &mut String,
+) -> Result<::toml::map::Map<String, ::toml::Value>> {
+    let mut out = ::toml::map::Map::new();
+    for (k, v) in table {
+        let k = k.as_deref().expect("only named entries");
+        let len = path.len();
+        push_key(path, k);
+        let v = convert(v, path)?;
+        path.truncate(len);
+        out.insert(k.to_string(), v);
+    }
+    Ok(out)
+}
+
+fn convert_array(table: &Table, path: &mut String) -> Result<::toml::Value> {
+    let mut items = Vec::with_capacity(table.len());
+    for (i, (_, v)) in table.iter().enumerate() {
+        let len = path.len();
+        push_index(path, i);
+        items.push(convert(v, path)?);
+        path.truncate(len);
+    }
+    // a mix of integers and floats is fine, as they're all Lua numbers
+    if items.iter().any(|v| v.is_float()) {
+        for item in &mut items {
+            if let ::toml::Value::Integer(n) = item {
+                *item = ::toml::Value::Float(*n as f64);
+            }
+        }
+    }
+    if let Some(first) = items.first() {
+        if let Some(other) = items.iter().find(|v| v.type_str() != first.type_str()) {
+            bail!(
+                "{}: arrays must have elements of one type in TOML, found {} and {}",
+                at(path),
+                first.type_str(),
+                other.type_str()
+            );
+        }
+    }
+    Ok(::toml::Value::Array(items))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{parse, Value};
+    use crate::toml::to_toml;
+
+    fn toml(s: &str) -> anyhow::Result<String> {
+        to_toml(&Value::Object(parse(s).unwrap()))
+    }
+
+    #[test]
+    fn document() {
+        assert_eq!(
+            r#"name = "app"
+version = 2
+ratio = 1.5
+tags = ["a", "b"]
+mixed = [1.0, 2.5]
+empty = []
+
+[server]
+host = "localhost"
+port = 8080
+
+[[users]]
+name = "a"
+
+[[users]]
+name = "b"
+"#,
+            toml(
+                r#"{name = "app", version = 2, ratio = 1.5, tags = {"a", "b"}, mixed = {1, 2.5}, empty = {},
+                server = {host = "localhost", port = 8080}, users = {{name = "a"}, {name = "b"}}}"#
+            )
+            .unwrap()
+        );
+        assert_eq!("", toml("{}").unwrap());
+    }
+
+    #[test]
+    fn errors() {
+        let message = |s| toml(s).unwrap_err().to_string();
+        assert_eq!(
+            "a TOML document must be a table with named entries, not an array",
+            message("{1, 2}")
+        );
+        assert_eq!(
+            "a.b: arrays must have elements of one type in TOML, found integer and string",
+            message(r#"{a = {b = {1, "x"}}}"#)
+        );
+        assert_eq!(
+            "a[1]: tables with both positional and named entries can't be represented in TOML",
+            message("{a = {{}, {1, x = 2}}}")
+        );
+        assert_eq!(
+            "s: strings must be valid UTF-8 in TOML",
+            message(r#"{s = "\xff"}"#)
+        );
+        assert!(to_toml(&Value::Bool(true)).is_err());
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+
+use crate::parse::{Key, Value};
+use crate::walk::Visitor;
+
+/// A change to the names of entries, which [`Value::transform_keys`] makes in every table.
+/// Positional entries are left alone.
+pub trait Transform {
+    /// The new name for an entry named `key`, or `None` to remove the entry.
+    fn rename(&self, key: &str) -> Option<String>;
+}
+
+impl<T: Transform + ?Sized> Transform for &T {
+    fn rename(&self, key: &str) -> Option<String> {
+        (**self).rename(key)
+    }
+}
+
+impl<T: Transform + ?Sized> Transform for Box<T> {
+    fn rename(&self, key: &str) -> Option<String> {
+        (**self).rename(key)
+    }
+}
+
+/// Each transform in turn, removing the entry if any of them do.
+impl<T: Transform> Transform for [T] {
+    fn rename(&self, key: &str) -> Option<String> {
+        let mut key = key.to_string();
+        for transform in self {
+            key = transform.rename(&key)?;
+        }
+        Some(key)
+    }
+}
+
+impl<T: Transform> Transform for Vec<T> {
+    fn rename(&self, key: &str) -> Option<String> {
+        self.as_slice().rename(key)
+    }
+}
+
+/// Rewrite names in a case, splitting them into words at `_`, `-` and spaces, and where
+/// capitals start words, like `maxHP` or `HTTPCode`. Leading underscores are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    /// `likeThis`.
+    Camel,
+    /// `like_this`.
+    Snake,
+}
+
+impl Transform for Case {
+    fn rename(&self, key: &str) -> Option<String> {
+        let body = key.trim_start_matches('_');
+        let mut out = key[..key.len() - body.len()].to_string();
+        for (i, word) in words(body).iter().enumerate() {
+            match self {
+                Case::Snake => {
+                    if i > 0 {
+                        out.push('_');
+                    }
+                    out.extend(word.chars().flat_map(char::to_lowercase));
+                }
+                Case::Camel if i == 0 => out.extend(word.chars().flat_map(char::to_lowercase)),
+                Case::Camel => {
+                    let mut chars = word.chars();
+                    out.extend(chars.next().into_iter().flat_map(char::to_uppercase));
+                    out.extend(chars.flat_map(char::to_lowercase));
+                }
+            }
+        }
+        Some(out)
+    }
+}
+
+fn words(s: &str) -> Vec<&str> {
+    let chars = s.char_indices().collect::<Vec<_>>();
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, &(at, c)) in chars.iter().enumerate() {
+        if matches!(c, '_' | '-' | ' ') {
+            words.extend(start.take().map(|start| &s[start..at]));
+            continue;
+        }
+        let prev = i.checked_sub(1).map(|i| chars[i].1);
+        let next = chars.get(i + 1).map(|&(_, c)| c);
+        // the `C` of `maxHPCode`, after a lowercase letter or before one
+        let capital = c.is_uppercase()
+            && (prev.is_some_and(|p| p.is_lowercase() || p.is_numeric())
+                || prev.is_some_and(char::is_uppercase) && next.is_some_and(char::is_lowercase));
+        match start {
+            Some(start) if capital => words.push(&s[start..at]),
+            Some(_) => continue,
+            None => {}
+        }
+        start = Some(at);
+    }
+    words.extend(start.map(|start| &s[start..]));
+    words
+}
+
+/// Remove entries whose names match a regex anywhere, like `^_` for names starting with `_`.
+#[derive(Debug, Clone)]
+pub struct DropKeys(pub Regex);
+
+impl Transform for DropKeys {
+    fn rename(&self, key: &str) -> Option<String> {
+        (!self.0.is_match(key)).then(|| key.to_string())
+    }
+}
+
+/// Rename entries named by a key of the map to its value, leaving others alone.
+#[derive(Debug, Clone, Default)]
+pub struct Rename(pub HashMap<String, String>);
+
+impl Transform for Rename {
+    fn rename(&self, key: &str) -> Option<String> {
+        Some(self.0.get(key).map_or(key, |new| new.as_str()).to_string())
+    }
+}
+
+impl Value {
+    /// Apply `transform` to the name of every entry of every table in this value.
+    pub fn transform_keys(&mut self, transform: &(impl Transform + ?Sized)) {
+        struct Keys<'t, T: ?Sized>(&'t T);
+        impl<T: Transform + ?Sized> Visitor for Keys<'_, T> {
+            fn visit_entry(&mut self, key: &mut Option<Key>, _: &mut Value) -> bool {
+                let Some(name) = key else {
+                    return true;
+                };
+                match self.0.rename(name) {
+                    // an integer key which keeps its digits stays an integer
+                    Some(new) if *name == *new => {}
+                    Some(new) => *name = Key::Name(new),
+                    None => return false,
+                }
+                true
+            }
+        }
+        self.walk(&mut Keys(transform));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use regex::Regex;
+
+    use crate::parse::{parse, Value};
+    use crate::transform::{Case, DropKeys, Rename, Transform};
+
+    fn lua(s: &str) -> Value {
+        Value::Object(parse(s).unwrap())
+    }
+
+    #[test]
+    fn cases() {
+        let cases = [
+            ("max_hp", "maxHp", "max_hp"),
+            ("maxHP", "maxHp", "max_hp"),
+            ("HTTPCode", "httpCode", "http_code"),
+            ("item2Name", "item2Name", "item2_name"),
+            ("_private-key", "_privateKey", "_private_key"),
+            ("Größe", "größe", "größe"),
+            ("", "", ""),
+        ];
+        for (key, camel, snake) in cases {
+            assert_eq!(Some(camel.to_string()), Case::Camel.rename(key), "{key}");
+            assert_eq!(Some(snake.to_string()), Case::Snake.rename(key), "{key}");
+        }
+    }
+
+    #[test]
+    fn transform_keys() {
+        let mut v = lua(r#"{_id = 1, maxHP = 2, stats = {_x = {}, baseDmg = 3, "a"}, old = 4}"#);
+        let transforms: Vec<Box<dyn Transform>> = vec![
+            Box::new(DropKeys(Regex::new("^_").unwrap())),
+            Box::new(Rename(HashMap::from([(
+                "old".to_string(),
+                "newName".to_string(),
+            )]))),
+            Box::new(Case::Snake),
+        ];
+        v.transform_keys(&transforms);
+        assert_eq!(
+            lua(r#"{max_hp = 2, stats = {base_dmg = 3, "a"}, new_name = 4}"#),
+            v
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use crate::parse::{Key, Value};
+
+/// Callbacks for [`Value::walk`]; both default to doing nothing.
+pub trait Visitor {
+    /// Called for each entry of each table, before its value is walked. The key and value may
+    /// be changed, and returning `false` removes the entry.
+    fn visit_entry(&mut self, key: &mut Option<Key>, value: &mut Value) -> bool {
+        let _ = (key, value);
+        true
+    }
+
+    /// Called for each value which isn't a table, including when this is the root.
+    fn visit_scalar(&mut self, value: &mut Value) {
+        let _ = value;
+    }
+}
+
+impl Value {
+    /// Walk this value and everything inside it, depth-first in order.
+    pub fn walk(&mut self, visitor: &mut impl Visitor) {
+        match self {
+            Value::Object(table) => table.retain_mut(|(k, v)| {
+                if !visitor.visit_entry(k, v) {
+                    return false;
+                }
+                v.walk(visitor);
+                true
+            }),
+            scalar => visitor.visit_scalar(scalar),
+        }
+    }
+
+    /// Apply `f` to every value which isn't a table.
+    pub fn map_values(&mut self, f: impl FnMut(&mut Value)) {
+        struct Scalars<F>(F);
+        impl<F: FnMut(&mut Value)> Visitor for Scalars<F> {
+            fn visit_scalar(&mut self, value: &mut Value) {
+                (self.0)(value)
+            }
+        }
+        self.walk(&mut Scalars(f));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{parse, Key, Value};
+    use crate::walk::Visitor;
+
+    fn lua(s: &str) -> Value {
+        Value::Object(parse(s).unwrap())
+    }
+
+    #[test]
+    fn walk() {
+        struct Tidy;
+        impl Visitor for Tidy {
+            fn visit_entry(&mut self, key: &mut Option<Key>, _: &mut Value) -> bool {
+                match key {
+                    Some(k) if k.starts_with('_') => false,
+                    Some(k) => {
+                        *k = k.to_uppercase().into();
+                        true
+                    }
+                    None => true,
+                }
+            }
+        }
+        let mut v = lua(r#"{a = 1, _b = 2, c = {_d = {}, e = "f", "g"}}"#);
+        v.walk(&mut Tidy);
+        assert_eq!(lua(r#"{A = 1, C = {E = "f", "g"}}"#), v);
+    }
+
+    #[test]
+    fn map_values() {
+        let mut v = lua(r#"{a = 1.4, b = {2.6, "x"}}"#);
+        v.map_values(|v| {
+            if let Value::Float(f) = v {
+                *f = f.round();
+            }
+        });
+        assert_eq!(lua(r#"{a = 1, b = {3, "x"}}"#), v);
+
+        let mut v = Value::Float(1.5);
+        v.map_values(|v| *v = Value::Bool(true));
+        assert_eq!(Value::Bool(true), v);
+    }
+}
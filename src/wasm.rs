@@ -0,0 +1,23 @@
+use wasm_bindgen::prelude::*;
+
+use crate::format::Indent;
+use crate::json::{to_json_string, JsonOptions};
+use crate::parse::{parse, Value};
+
+/// Convert a Lua table to JSON, indented by two spaces if `pretty`, throwing the error message
+/// if it can't be.
+#[wasm_bindgen]
+pub fn parse_to_json(input: &str, pretty: bool) -> Result<String, JsValue> {
+    let opts = JsonOptions {
+        indent: if pretty {
+            Indent::Spaces(2)
+        } else {
+            Indent::None
+        },
+        ..JsonOptions::default()
+    };
+    let json = parse(input)
+        .map_err(anyhow::Error::from)
+        .and_then(|table| to_json_string(&Value::Object(table), &opts));
+    json.map_err(|e| JsValue::from_str(&format!("{e:#}")))
+}
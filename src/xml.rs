@@ -0,0 +1,287 @@
+use std::fmt::Write;
+
+use anyhow::{bail, Result};
+
+use crate::format::Indent;
+use crate::parse::{Table, Value};
+use crate::path::{push_index, push_key};
+
+/// Options for [`to_xml`].
+#[derive(Debug, Clone)]
+pub struct XmlOptions {
+    /// The name of the element around the whole value.
+    pub root: String,
+    /// The name of the elements for positional entries, and for named entries whose names
+    /// aren't XML names, like `max hp`, which are given a `key` attribute with the name.
+    pub item: String,
+    pub scalars: Scalars,
+    /// [`Indent::None`] for the whole document on one line, otherwise each element on its own
+    /// line, indented by this.
+    pub indent: Indent,
+}
+
+impl Default for XmlOptions {
+    fn default() -> Self {
+        XmlOptions {
+            root: "root".to_string(),
+            item: "item".to_string(),
+            scalars: Scalars::default(),
+            indent: Indent::None,
+        }
+    }
+}
+
+/// How [`to_xml`] writes the entries of tables which aren't tables themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scalars {
+    /// As elements with the value as their text, like `<hp>10</hp>`.
+    #[default]
+    Elements,
+    /// As attributes of the table's element, like `<player hp="10">`, when they're named with
+    /// an XML name. Positional entries are still elements.
+    Attributes,
+}
+
+/// Render `value` as an XML document, with an element for each table and entry.
+///
+/// Numbers are written like `10` or `1.5`, with `INF`, `-INF` and `NaN` for the ones which
+/// aren't finite, as in XML Schema. Strings which aren't valid UTF-8, or which have control
+/// characters that XML can't represent, are errors.
+pub fn to_xml(value: &Value, opts: &XmlOptions) -> Result<String> {
+    for name in [&opts.root, &opts.item] {
+        if !is_name(name) {
+            bail!("{name:?} isn't an XML name");
+        }
+    }
+    let mut out = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let mut path = String::new();
+    element(&mut out, &opts.root, None, value, opts, 0, &mut path)?;
+    if Indent::None != opts.indent {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn at(path: &str) -> &str {
+    if path.is_empty() {
+        "(root)"
+    } else {
+        path
+    }
+}
+
+/// Whether `s` can be the name of an element or attribute. Names starting with `xml` are
+/// reserved, and `:` is left out, as it's for namespaces.
+fn is_name(s: &str) -> bool {
+    let start = |c: char| c.is_alphabetic() || '_' == c;
+    s.starts_with(start)
+        && s.chars()
+            .all(|c| start(c) || c.is_alphanumeric() || matches!(c, '-' | '.'))
+        && !s.get(..3).is_some_and(|p| p.eq_ignore_ascii_case("xml"))
+}
+
+/// Write the element `name` for `value`, with a `key` attribute if it has one.
+fn element(
+    out: &mut String,
+    name: &str,
+    key: Option<&str>,
+    value: &Value,
+    opts: &XmlOptions,
+    depth: usize,
+    path: &mut String,
+) -> Result<()> {
+    newline(out, opts.indent, depth);
+    out.push('<');
+    out.push_str(name);
+    if let Some(key) = key {
+        out.push_str(" key=\"");
+        escape(out, key, true, path)?;
+        out.push('"');
+    }
+    let Value::Object(table) = value else {
+        out.push('>');
+        text(out, value, false, path)?;
+        write!(out, "</{name}>").expect("infallible");
+        return Ok(());
+    };
+    let attributes = Scalars::Attributes == opts.scalars;
+    let mut children = false;
+    for (k, v) in table {
+        match k {
+            Some(k) if attributes && is_name(k) && !matches!(v, Value::Object(_)) => {
+                let len = path.len();
+                push_key(path, k);
+                write!(out, " {k}=\"").expect("infallible");
+                text(out, v, true, path)?;
+                out.push('"');
+                path.truncate(len);
+            }
+            _ => children = true,
+        }
+    }
+    if !children {
+        out.push_str("/>");
+        return Ok(());
+    }
+    out.push('>');
+    entries(out, table, opts, depth + 1, path)?;
+    newline(out, opts.indent, depth);
+    write!(out, "</{name}>").expect("infallible");
+    Ok(())
+}
+
+fn entries(
+    out: &mut String,
+    table: &Table,
+    opts: &XmlOptions,
+    depth: usize,
+    path: &mut String,
+) -> Result<()> {
+    let attributes = Scalars::Attributes == opts.scalars;
+    let mut index = 0;
+    for (k, v) in table {
+        let len = path.len();
+        match k {
+            Some(k) if attributes && is_name(k) && !matches!(v, Value::Object(_)) => continue,
+            Some(k) if is_name(k) => {
+                push_key(path, k);
+                element(out, k, None, v, opts, depth, path)?;
+            }
+            Some(k) => {
+                push_key(path, k);
+                element(out, &opts.item, Some(k), v, opts, depth, path)?;
+            }
+            None => {
+                push_index(path, index);
+                index += 1;
+                element(out, &opts.item, None, v, opts, depth, path)?;
+            }
+        }
+        path.truncate(len);
+    }
+    Ok(())
+}
+
+fn newline(out: &mut String, indent: Indent, depth: usize) {
+    match indent {
+        Indent::None => {}
+        Indent::Spaces(n) => {
+            out.push('\n');
+            out.push_str(&" ".repeat(n * depth));
+        }
+        Indent::Tab => {
+            out.push('\n');
+            out.push_str(&"\t".repeat(depth));
+        }
+    }
+}
+
+/// Write a value which isn't a table as text, escaped for an attribute if `attribute`.
+fn text(out: &mut String, value: &Value, attribute: bool, path: &str) -> Result<()> {
+    match value {
+        Value::String(s) | Value::Raw(s) => escape(out, s, attribute, path)?,
+        Value::Bytes(_) => bail!("{}: strings must be valid UTF-8 in XML", at(path)),
+        Value::Number { raw, .. } => out.push_str(raw),
+        Value::Float(f) if f.is_nan() => out.push_str("NaN"),
+        Value::Float(f) if f.is_infinite() => out.push_str(if *f > 0. { "INF" } else { "-INF" }),
+        Value::Float(f) => write!(out, "{f}").expect("infallible"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Object(_) => unreachable!("tables are elements"),
+    }
+    Ok(())
+}
+
+fn escape(out: &mut String, s: &str, attribute: bool, path: &str) -> Result<()> {
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if attribute => out.push_str("&quot;"),
+            // which would otherwise be normalised to spaces
+            '\t' | '\n' | '\r' if attribute => write!(out, "&#{};", c as u32).expect("infallible"),
+            '\r' => out.push_str("&#13;"),
+            '\t' | '\n' => out.push(c),
+            c if c.is_control() && c < ' ' => {
+                bail!("{}: XML can't represent the character {c:?}", at(path))
+            }
+            c => out.push(c),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::Indent;
+    use crate::parse::{parse, Value};
+    use crate::xml::{to_xml, Scalars, XmlOptions};
+
+    fn xml(s: &str, opts: &XmlOptions) -> anyhow::Result<String> {
+        to_xml(&Value::Object(parse(s).unwrap()), opts)
+    }
+
+    #[test]
+    fn document() {
+        let doc = r#"{name = "a & <b>", tags = {"x", 2.5}, ["max hp"] = 10, e = {}, stats = {on = true, 1}}"#;
+        let opts = XmlOptions {
+            indent: Indent::Spaces(2),
+            ..XmlOptions::default()
+        };
+        assert_eq!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<root>
+  <name>a &amp; &lt;b&gt;</name>
+  <tags>
+    <item>x</item>
+    <item>2.5</item>
+  </tags>
+  <item key="max hp">10</item>
+  <e/>
+  <stats>
+    <on>true</on>
+    <item>1</item>
+  </stats>
+</root>
+"#,
+            xml(doc, &opts).unwrap()
+        );
+
+        let opts = XmlOptions {
+            root: "save".to_string(),
+            item: "entry".to_string(),
+            scalars: Scalars::Attributes,
+            indent: Indent::None,
+        };
+        assert_eq!(
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                r#"<save name="a &amp; &lt;b&gt;"><tags><entry>x</entry><entry>2.5</entry></tags>"#,
+                r#"<entry key="max hp">10</entry><e/><stats on="true"><entry>1</entry></stats></save>"#,
+            ),
+            xml(doc, &opts).unwrap()
+        );
+        assert_eq!(
+            r#"<?xml version="1.0" encoding="UTF-8"?><save a="x&quot;&#10;y" b="INF"/>"#,
+            xml(r#"{a = "x\"\ny", b = 1/0}"#, &opts).unwrap()
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let opts = XmlOptions::default();
+        assert_eq!(
+            "a[0]: strings must be valid UTF-8 in XML",
+            xml(r#"{a = {"\xff"}}"#, &opts).unwrap_err().to_string()
+        );
+        assert_eq!(
+            "b: XML can't represent the character '\\0'",
+            xml(r#"{b = "\0"}"#, &opts).unwrap_err().to_string()
+        );
+        let opts = XmlOptions {
+            item: "1st".to_string(),
+            ..XmlOptions::default()
+        };
+        assert!(xml("{}", &opts).is_err());
+    }
+}